@@ -0,0 +1,432 @@
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use sdl2::audio::AudioSpecWAV;
+use sdl2::pixels::Color;
+use sdl2::render::{Texture, TextureCreator};
+use sdl2::surface::Surface;
+
+// Assets shared by all the parts: the files are found with a search path that
+// starts at the executable, each file is loaded once and given a handle, and
+// the files that change while the game runs are loaded again:
+//
+//     let mut textures = Assets::new(SearchPath::from_exe());
+//     let sheet = textures.load("assets/characters.bmp", |path| load_texture(&creator, path))?;
+//     loop {
+//         textures.poll(|path| load_texture(&creator, path));
+//         canvas.copy(textures.get(sheet), None, None)?;
+//     }
+
+// Time between two checks of the modification times of the files
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+// Number of folders above the executable where the assets are searched. A game
+// run with cargo is in `target/debug`, two folders below the crate
+const EXE_LEVELS: usize = 3;
+
+// Folders where the assets are searched, in order. The assets are found in the
+// same place no matter where the game is started from
+#[derive(Clone, Debug, PartialEq)]
+pub struct SearchPath {
+    folders: Vec<PathBuf>,
+}
+
+impl SearchPath {
+    pub fn new(folders: Vec<PathBuf>) -> Self {
+        SearchPath { folders }
+    }
+
+    // The folder of the executable and the folders above it
+    pub fn from_exe() -> Self {
+        let folders = env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+            .map(|folder| {
+                folder
+                    .ancestors()
+                    .take(EXE_LEVELS + 1)
+                    .map(Path::to_path_buf)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        SearchPath { folders }
+    }
+
+    pub fn folders(&self) -> &[PathBuf] {
+        &self.folders
+    }
+
+    // The file in the first folder that has it. An absolute path is used as it
+    // is
+    pub fn resolve(&self, path: impl AsRef<Path>) -> Result<PathBuf, String> {
+        let path = path.as_ref();
+        if path.is_absolute() {
+            return Ok(path.to_path_buf());
+        }
+
+        self.folders
+            .iter()
+            .map(|folder| folder.join(path))
+            .find(|candidate| candidate.exists())
+            .ok_or(format!("Asset not found: {}", path.display()))
+    }
+
+    // Like resolve, but a file that isn't found is given as it is, relative to
+    // the current folder. It is used for the optional files and folders
+    pub fn find(&self, path: impl AsRef<Path>) -> PathBuf {
+        let path = path.as_ref();
+        self.resolve(path).unwrap_or_else(|_| path.to_path_buf())
+    }
+}
+
+// A handle to an asset of a type. It is only an index, so it can be copied and
+// kept by the game instead of a reference to the asset
+pub struct Handle<T> {
+    index: usize,
+    kind: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+// The traits are written by hand so the assets don't need to implement them
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for Handle<T> {}
+
+impl<T> PartialEq for Handle<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.index == other.index
+    }
+}
+
+impl<T> Eq for Handle<T> {}
+
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Handle({})", self.index)
+    }
+}
+
+struct Entry<T> {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    asset: T,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+// The loaded assets of one type. Loading the same file twice gives the same
+// handle without reading the file again. The assets are loaded by a function
+// given by the game, because some of them, like the textures, need SDL objects
+// that the manager doesn't own
+pub struct Assets<T> {
+    search: SearchPath,
+    entries: Vec<Entry<T>>,
+    handles: HashMap<PathBuf, usize>,
+    last_poll: Option<Instant>,
+}
+
+impl<T> Assets<T> {
+    pub fn new(search: SearchPath) -> Self {
+        Assets {
+            search,
+            entries: Vec::new(),
+            handles: HashMap::new(),
+            last_poll: None,
+        }
+    }
+
+    pub fn search(&self) -> &SearchPath {
+        &self.search
+    }
+
+    pub fn load(
+        &mut self,
+        path: impl AsRef<Path>,
+        loader: impl FnOnce(&Path) -> Result<T, String>,
+    ) -> Result<Handle<T>, String> {
+        let path = self.search.resolve(path)?;
+
+        if let Some(index) = self.handles.get(&path) {
+            return Ok(Handle {
+                index: *index,
+                kind: PhantomData,
+            });
+        }
+
+        let entry = Entry {
+            modified: modified(&path),
+            asset: loader(&path)?,
+            path: path.clone(),
+        };
+
+        let index = self.entries.len();
+        self.entries.push(entry);
+        self.handles.insert(path, index);
+
+        Ok(Handle {
+            index,
+            kind: PhantomData,
+        })
+    }
+
+    pub fn get(&self, handle: Handle<T>) -> &T {
+        &self.entries[handle.index].asset
+    }
+
+    pub fn get_mut(&mut self, handle: Handle<T>) -> &mut T {
+        &mut self.entries[handle.index].asset
+    }
+
+    // File the asset was loaded from
+    pub fn path(&self, handle: Handle<T>) -> &Path {
+        &self.entries[handle.index].path
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Loads again the files that have changed since the last check. It can be
+    // called every frame, because the files are only checked a couple of times
+    // per second. Returns the handles of the assets that have been reloaded
+    pub fn poll(&mut self, loader: impl FnMut(&Path) -> Result<T, String>) -> Vec<Handle<T>> {
+        let now = Instant::now();
+        if self
+            .last_poll
+            .is_some_and(|last| now - last < POLL_INTERVAL)
+        {
+            return Vec::new();
+        }
+        self.last_poll = Some(now);
+
+        self.reload_changed(loader)
+    }
+
+    // Loads again the files that have changed, without waiting for the poll
+    // interval. A file that can't be loaded, for example because it is being
+    // written, keeps the old asset until it changes again
+    pub fn reload_changed(
+        &mut self,
+        mut loader: impl FnMut(&Path) -> Result<T, String>,
+    ) -> Vec<Handle<T>> {
+        let mut reloaded = Vec::new();
+
+        for (index, entry) in self.entries.iter_mut().enumerate() {
+            let current = modified(&entry.path);
+            if current.is_none() || current == entry.modified {
+                continue;
+            }
+            entry.modified = current;
+
+            match loader(&entry.path) {
+                Ok(asset) => {
+                    entry.asset = asset;
+                    reloaded.push(Handle {
+                        index,
+                        kind: PhantomData,
+                    });
+                }
+                Err(e) => println!("Unable to reload {}: {}", entry.path.display(), e),
+            }
+        }
+
+        reloaded
+    }
+}
+
+// Loaders for the usual kinds of assets
+
+pub fn load_text(path: &Path) -> Result<String, String> {
+    fs::read_to_string(path).map_err(|e| e.to_string())
+}
+
+pub fn load_bytes(path: &Path) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|e| e.to_string())
+}
+
+pub fn load_bmp(path: &Path) -> Result<Surface<'static>, String> {
+    Surface::load_bmp(path)
+}
+
+// Loads an image of any format. The BMP files are read by SDL itself, and the
+// PNG, JPEG, TGA and other files need SDL2_image, which is only linked when the
+// crate is built with the `image` feature
+pub fn load_surface(path: &Path) -> Result<Surface<'static>, String> {
+    let bmp = path
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("bmp"));
+
+    if bmp {
+        load_bmp(path)
+    } else {
+        load_image(path)
+    }
+}
+
+#[cfg(feature = "image")]
+fn load_image(path: &Path) -> Result<Surface<'static>, String> {
+    use sdl2::image::LoadSurface;
+
+    Surface::from_file(path)
+}
+
+#[cfg(not(feature = "image"))]
+fn load_image(path: &Path) -> Result<Surface<'static>, String> {
+    Err(format!(
+        "Unable to load {}: only BMP images can be loaded without the image feature",
+        path.display()
+    ))
+}
+
+pub fn load_texture<'a, C>(
+    creator: &'a TextureCreator<C>,
+    path: &Path,
+) -> Result<Texture<'a>, String> {
+    load_keyed_texture(creator, path, None)
+}
+
+// Like load_texture, but the pixels of the color key are transparent. The old
+// sheets are drawn over a background color instead of having an alpha channel,
+// so they can be used without converting them
+pub fn load_keyed_texture<'a, C>(
+    creator: &'a TextureCreator<C>,
+    path: &Path,
+    color_key: Option<Color>,
+) -> Result<Texture<'a>, String> {
+    let mut surface = load_surface(path)?;
+
+    if let Some(color) = color_key {
+        surface.set_color_key(true, color)?;
+    }
+
+    creator
+        .create_texture_from_surface(&surface)
+        .map_err(|e| e.to_string())
+}
+
+pub fn load_wav(path: &Path) -> Result<AudioSpecWAV, String> {
+    AudioSpecWAV::load_wav(path)
+}
+
+// A color written as its red, green and blue values, like `255 0 255`. It is
+// used by the files that give the color key of an image
+pub fn parse_color(value: &str) -> Result<Color, String> {
+    let values = value
+        .split_whitespace()
+        .map(|part| part.parse::<u8>())
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| format!("Invalid color: {}", value))?;
+
+    match values[..] {
+        [r, g, b] => Ok(Color::RGB(r, g, b)),
+        _ => Err(format!("Invalid color: {}", value)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A folder of the temporary folder for a test, created empty
+    fn test_folder(name: &str) -> PathBuf {
+        let folder = env::temp_dir().join(format!("common_assets_{}", name));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[test]
+    fn test_resolve() {
+        let first = test_folder("resolve_first");
+        let second = test_folder("resolve_second");
+        fs::write(second.join("a.txt"), "second").unwrap();
+
+        let search = SearchPath::new(vec![first.clone(), second.clone()]);
+        assert_eq!(search.resolve("a.txt").unwrap(), second.join("a.txt"));
+        assert!(search.resolve("b.txt").is_err());
+
+        // The first folder wins when both have the file
+        fs::write(first.join("a.txt"), "first").unwrap();
+        assert_eq!(search.resolve("a.txt").unwrap(), first.join("a.txt"));
+
+        let absolute = second.join("b.txt");
+        assert_eq!(search.resolve(&absolute).unwrap(), absolute);
+        assert_eq!(search.find("b.txt"), Path::new("b.txt"));
+    }
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(
+            parse_color("110 170 220").unwrap(),
+            Color::RGB(110, 170, 220)
+        );
+        assert_eq!(
+            parse_color(" 255  0 255 ").unwrap(),
+            Color::RGB(255, 0, 255)
+        );
+        assert!(parse_color("255 0").is_err());
+        assert!(parse_color("255 0 0 0").is_err());
+        assert!(parse_color("256 0 0").is_err());
+        assert!(parse_color("magenta").is_err());
+    }
+
+    #[test]
+    fn test_load_and_reload() {
+        let folder = test_folder("reload");
+        fs::write(folder.join("data.txt"), "one").unwrap();
+
+        let mut assets = Assets::new(SearchPath::new(vec![folder.clone()]));
+        let mut loads = 0;
+        let handle = assets
+            .load("data.txt", |path| {
+                loads += 1;
+                load_text(path)
+            })
+            .unwrap();
+
+        // The second load uses the cache
+        let again = assets
+            .load("data.txt", |_| Err(String::from("cached")))
+            .unwrap();
+        assert_eq!(handle, again);
+        assert_eq!(loads, 1);
+        assert_eq!(assets.get(handle), "one");
+        assert_eq!(assets.path(handle), folder.join("data.txt"));
+
+        // Nothing is loaded again while the file doesn't change
+        assert!(assets.reload_changed(load_text).is_empty());
+
+        fs::write(folder.join("data.txt"), "two").unwrap();
+        assets.entries[0].modified = Some(SystemTime::UNIX_EPOCH);
+        assert_eq!(assets.reload_changed(load_text), vec![handle]);
+        assert_eq!(assets.get(handle), "two");
+
+        // A file that can't be loaded keeps the old asset
+        assets.entries[0].modified = Some(SystemTime::UNIX_EPOCH);
+        assert!(assets
+            .reload_changed(|_| Err(String::from("broken")))
+            .is_empty());
+        assert_eq!(assets.get(handle), "two");
+    }
+}
@@ -0,0 +1,326 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{Canvas, RenderTarget};
+use sdl2::surface::Surface;
+use sdl2::video::Window;
+use sdl2::Sdl;
+
+use crate::screenshot::{Image, ImageFormat};
+
+// Helpers for the rendering tests. A test draws a known state in a canvas that
+// doesn't need a screen, reads the pixels back and compares them with a
+// reference image that is stored with the code of the part. The parts keep their
+// drawing in a function that takes any canvas, so the frame of the window and the
+// frame of the test are drawn by the same code, and the test only has to build
+// the state it draws.
+//
+// When the variable GOLDEN_BLESS is set, the image drawn by the test is saved
+// as the new reference. Otherwise a missing reference fails the test, so a
+// reference that wasn't committed isn't written again on every machine. When
+// the images are different the test fails and writes the drawn image and an
+// image with the differences next to the reference.
+
+// SDL can only be started once at a time, so the tests that start SDL have to
+// hold this lock while they use it
+static SDL_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn sdl_lock() -> MutexGuard<'static, ()> {
+    // A test that panics while holding the lock doesn't break the other tests
+    SDL_LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+// A canvas that draws into a surface in memory with the software renderer. It
+// doesn't need SDL to be started
+pub fn surface_canvas(width: u32, height: u32) -> Result<Canvas<Surface<'static>>, String> {
+    let surface = Surface::new(width, height, PixelFormatEnum::RGB888)?;
+    surface.into_canvas()
+}
+
+// A hidden window with the software renderer, for the code that needs a
+// window canvas. It uses the dummy video driver, so it works without a screen.
+// The SDL context has to be kept alive while the canvas is used, and the lock
+// has to be held by the test
+pub fn dummy_window_canvas(width: u32, height: u32) -> Result<(Sdl, Canvas<Window>), String> {
+    env::set_var("SDL_VIDEODRIVER", "dummy");
+
+    let ctx = sdl2::init()?;
+    let window = ctx
+        .video()?
+        .window("test", width, height)
+        .hidden()
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let canvas = window
+        .into_canvas()
+        .software()
+        .target_texture()
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    Ok((ctx, canvas))
+}
+
+// How different two images can be before the test fails. A pixel is different
+// when any of its channels changes more than the channel tolerance, and the
+// images are different when there are more different pixels than allowed
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tolerance {
+    pub channel: u8,
+    pub pixels: usize,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            channel: 2,
+            pixels: 0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Comparison {
+    pub different_pixels: usize,
+    pub max_difference: u8,
+}
+
+impl Comparison {
+    pub fn passes(&self, tolerance: Tolerance) -> bool {
+        self.different_pixels <= tolerance.pixels
+    }
+}
+
+fn pixel_difference(a: (u8, u8, u8), b: (u8, u8, u8)) -> u8 {
+    let channel = |x: u8, y: u8| (x as i16 - y as i16).unsigned_abs() as u8;
+    channel(a.0, b.0)
+        .max(channel(a.1, b.1))
+        .max(channel(a.2, b.2))
+}
+
+// Compares two images of the same size
+pub fn compare(actual: &Image, expected: &Image, tolerance: Tolerance) -> Comparison {
+    let mut comparison = Comparison {
+        different_pixels: 0,
+        max_difference: 0,
+    };
+
+    for y in 0..actual.height {
+        for x in 0..actual.width {
+            let difference = pixel_difference(actual.pixel(x, y), expected.pixel(x, y));
+            comparison.max_difference = comparison.max_difference.max(difference);
+
+            if difference > tolerance.channel {
+                comparison.different_pixels += 1;
+            }
+        }
+    }
+
+    comparison
+}
+
+// An image that shows the different pixels in red on top of a dark copy of the
+// expected image, so the differences are easy to find
+pub fn diff_image(actual: &Image, expected: &Image, tolerance: Tolerance) -> Image {
+    let mut pixels = Vec::with_capacity(actual.pixels.len());
+
+    for y in 0..actual.height {
+        for x in 0..actual.width {
+            let expected_pixel = expected.pixel(x, y);
+
+            if pixel_difference(actual.pixel(x, y), expected_pixel) > tolerance.channel {
+                pixels.extend_from_slice(&[255, 0, 0]);
+            } else {
+                let (r, g, b) = expected_pixel;
+                pixels.extend_from_slice(&[r / 4, g / 4, b / 4]);
+            }
+        }
+    }
+
+    Image {
+        width: actual.width,
+        height: actual.height,
+        pixels,
+    }
+}
+
+// Reads a BMP file as an image with three bytes per pixel
+pub fn load_image(path: &Path) -> Result<Image, String> {
+    let surface = Surface::load_bmp(path)?.convert_format(PixelFormatEnum::RGB24)?;
+    let (width, height) = (surface.width(), surface.height());
+    let pitch = surface.pitch() as usize;
+
+    let pixels = surface.with_lock(|data| {
+        data.chunks(pitch)
+            .take(height as usize)
+            .flat_map(|row| row[..width as usize * 3].iter().copied())
+            .collect()
+    });
+
+    Ok(Image {
+        width,
+        height,
+        pixels,
+    })
+}
+
+// Path of a reference image in the golden folder of a part. The folder is
+// given with `env!("CARGO_MANIFEST_DIR")` so it doesn't depend on where the
+// tests are run from
+pub fn reference(manifest_dir: &str, name: &str) -> PathBuf {
+    Path::new(manifest_dir)
+        .join("golden")
+        .join(format!("{}.bmp", name))
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!("{}.{}.bmp", stem, suffix))
+}
+
+// Compares the image with its reference and returns an error that explains the
+// difference. The references are written as BMP files because SDL can read them
+pub fn check_image(image: &Image, path: &Path, tolerance: Tolerance) -> Result<(), String> {
+    check_or_bless(
+        image,
+        path,
+        tolerance,
+        env::var_os("GOLDEN_BLESS").is_some(),
+    )
+}
+
+fn check_or_bless(
+    image: &Image,
+    path: &Path,
+    tolerance: Tolerance,
+    bless: bool,
+) -> Result<(), String> {
+    if bless {
+        if let Some(folder) = path.parent() {
+            std::fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+        }
+
+        image.save(path, ImageFormat::Bmp)?;
+        println!("Reference image written to {}", path.display());
+
+        return Ok(());
+    }
+
+    if !path.exists() {
+        if let Some(folder) = path.parent() {
+            std::fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+        }
+        image.save(&with_suffix(path, "actual"), ImageFormat::Bmp)?;
+
+        return Err(format!(
+            "{}: the reference image doesn't exist, run the test with GOLDEN_BLESS=1 to write it",
+            path.display()
+        ));
+    }
+
+    let expected = load_image(path)?;
+    if (expected.width, expected.height) != (image.width, image.height) {
+        image.save(&with_suffix(path, "actual"), ImageFormat::Bmp)?;
+
+        return Err(format!(
+            "{}: the image is {}x{} but the reference is {}x{}",
+            path.display(),
+            image.width,
+            image.height,
+            expected.width,
+            expected.height
+        ));
+    }
+
+    let comparison = compare(image, &expected, tolerance);
+    if !comparison.passes(tolerance) {
+        image.save(&with_suffix(path, "actual"), ImageFormat::Bmp)?;
+        diff_image(image, &expected, tolerance)
+            .save(&with_suffix(path, "diff"), ImageFormat::Bmp)?;
+
+        return Err(format!(
+            "{}: {} pixels are different (the largest difference is {})",
+            path.display(),
+            comparison.different_pixels,
+            comparison.max_difference
+        ));
+    }
+
+    Ok(())
+}
+
+// Reads the pixels of the canvas and compares them with the reference. Panics
+// if the images are different, so it can be used as an assert in the tests
+pub fn assert_golden<T: RenderTarget>(canvas: &Canvas<T>, path: &Path, tolerance: Tolerance) {
+    let image = Image::from_canvas(canvas).expect("Unable to read the canvas");
+
+    if let Err(e) = check_image(&image, path, tolerance) {
+        panic!("{}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn image(pixels: &[u8]) -> Image {
+        Image {
+            width: 2,
+            height: 1,
+            pixels: pixels.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let expected = image(&[0, 0, 0, 100, 100, 100]);
+
+        let close = image(&[1, 0, 2, 100, 98, 100]);
+        let comparison = compare(&close, &expected, Tolerance::default());
+        assert_eq!(comparison.different_pixels, 0);
+        assert_eq!(comparison.max_difference, 2);
+
+        let different = image(&[0, 0, 0, 100, 150, 100]);
+        let comparison = compare(&different, &expected, Tolerance::default());
+        assert_eq!(comparison.different_pixels, 1);
+        assert!(!comparison.passes(Tolerance::default()));
+        assert!(comparison.passes(Tolerance {
+            channel: 2,
+            pixels: 1
+        }));
+
+        let diff = diff_image(&different, &expected, Tolerance::default());
+        assert_eq!(diff.pixels, [0, 0, 0, 255, 0, 0]);
+    }
+
+    #[test]
+    fn test_golden_roundtrip() {
+        let folder = env::temp_dir().join("common_golden_test");
+        let path = folder.join("roundtrip.bmp");
+        let _ = std::fs::remove_dir_all(&folder);
+
+        let mut canvas = surface_canvas(3, 2).unwrap();
+        canvas.set_draw_color(sdl2::pixels::Color::RGB(10, 200, 30));
+        canvas.clear();
+        let image = Image::from_canvas(&canvas).unwrap();
+
+        // A missing reference fails and is only written when blessing
+        assert!(check_or_bless(&image, &path, Tolerance::default(), false).is_err());
+        assert!(!path.exists());
+        assert!(folder.join("roundtrip.actual.bmp").exists());
+
+        check_or_bless(&image, &path, Tolerance::default(), true).unwrap();
+        assert_eq!(load_image(&path).unwrap(), image);
+        check_or_bless(&image, &path, Tolerance::default(), false).unwrap();
+
+        // A different image fails and leaves the images to look at
+        let mut changed = image.clone();
+        changed.pixels[0] = 255;
+        assert!(check_or_bless(&changed, &path, Tolerance::default(), false).is_err());
+        assert!(folder.join("roundtrip.diff.bmp").exists());
+        assert!(folder.join("roundtrip.actual.bmp").exists());
+    }
+}
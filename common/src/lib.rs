@@ -0,0 +1,7 @@
+// Helpers shared by all the parts of the tutorial. Each part adds this crate as a
+// path dependency: `common = { path = "../common" }`
+pub mod assets;
+pub mod golden;
+pub mod random;
+pub mod screenshot;
+pub mod timing;
@@ -0,0 +1,28 @@
+// A small generator of random numbers for the parts that need the same numbers
+// every time they run, like the boxes of a sandbox or the characters of a test.
+// It is a linear congruential generator, which is enough for a game and doesn't
+// need a crate. The seed is the whole state of the generator
+
+// A number between 0 and 1. The seed is changed to give the next number
+pub fn random(seed: &mut u32) -> f32 {
+    *seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+    (*seed >> 8) as f32 / (1 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_random() {
+        // The same seed gives the same numbers, always between 0 and 1
+        let (mut first, mut second) = (7, 7);
+        for _ in 0..1000 {
+            let value = random(&mut first);
+            assert!((0.0..1.0).contains(&value));
+            assert_eq!(value, random(&mut second));
+        }
+
+        assert_ne!(random(&mut 1), random(&mut 2));
+    }
+}
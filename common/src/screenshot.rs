@@ -0,0 +1,385 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::{Canvas, RenderTarget};
+
+// Key used in all the parts to take a screenshot
+pub const SCREENSHOT_KEY: Keycode = Keycode::F12;
+
+pub const SCREENSHOT_FOLDER: &str = "screenshots";
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ImageFormat {
+    Bmp,
+    Png,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Png => "png",
+        }
+    }
+}
+
+// An image with three bytes per pixel (red, green and blue), stored row by row
+// from the top of the image
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    // Reads the pixels that have been drawn in the canvas. It has to be called
+    // before the canvas is presented, because the contents of the canvas are
+    // lost after presenting it
+    pub fn from_canvas<T: RenderTarget>(canvas: &Canvas<T>) -> Result<Self, String> {
+        let (width, height) = canvas.output_size()?;
+        let pixels = canvas.read_pixels(None, PixelFormatEnum::RGB24)?;
+
+        Ok(Image {
+            width,
+            height,
+            pixels,
+        })
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> (u8, u8, u8) {
+        let index = ((y * self.width + x) * 3) as usize;
+        (
+            self.pixels[index],
+            self.pixels[index + 1],
+            self.pixels[index + 2],
+        )
+    }
+
+    pub fn encode(&self, format: ImageFormat) -> Vec<u8> {
+        match format {
+            ImageFormat::Bmp => self.encode_bmp(),
+            ImageFormat::Png => self.encode_png(),
+        }
+    }
+
+    // A BMP file has a header followed by the rows of pixels from the bottom
+    // of the image. Each pixel is stored as blue, green and red, and each row is
+    // padded to a multiple of four bytes
+    pub fn encode_bmp(&self) -> Vec<u8> {
+        let row_size = (self.width * 3).div_ceil(4) * 4;
+        let data_size = row_size * self.height;
+        let header_size = 14 + 40;
+
+        let mut bytes = Vec::with_capacity((header_size + data_size) as usize);
+
+        // File header
+        bytes.extend_from_slice(b"BM");
+        bytes.extend_from_slice(&(header_size + data_size).to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&header_size.to_le_bytes());
+
+        // Information header
+        bytes.extend_from_slice(&40u32.to_le_bytes());
+        bytes.extend_from_slice(&(self.width as i32).to_le_bytes());
+        bytes.extend_from_slice(&(self.height as i32).to_le_bytes());
+        bytes.extend_from_slice(&1u16.to_le_bytes()); // Planes
+        bytes.extend_from_slice(&24u16.to_le_bytes()); // Bits per pixel
+        bytes.extend_from_slice(&0u32.to_le_bytes()); // No compression
+        bytes.extend_from_slice(&data_size.to_le_bytes());
+        bytes.extend_from_slice(&2835i32.to_le_bytes()); // 72 DPI
+        bytes.extend_from_slice(&2835i32.to_le_bytes());
+        bytes.extend_from_slice(&[0; 8]);
+
+        for y in (0..self.height).rev() {
+            for x in 0..self.width {
+                let (r, g, b) = self.pixel(x, y);
+                bytes.extend_from_slice(&[b, g, r]);
+            }
+            bytes.resize(bytes.len() + (row_size - self.width * 3) as usize, 0);
+        }
+
+        bytes
+    }
+
+    // A PNG file is a list of chunks. The pixels go in the IDAT chunk compressed
+    // with zlib. This encoder doesn't compress them, it stores them in blocks
+    // without compression, which every PNG reader understands
+    pub fn encode_png(&self) -> Vec<u8> {
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+
+        let mut header = Vec::new();
+        header.extend_from_slice(&self.width.to_be_bytes());
+        header.extend_from_slice(&self.height.to_be_bytes());
+        // Bit depth 8, color type 2 (RGB), default compression, filter and no interlace
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+        write_chunk(&mut bytes, b"IHDR", &header);
+
+        // Each row starts with its filter type, and the filter 0 keeps the row as it is
+        let row_size = (self.width * 3) as usize;
+        let mut raw = Vec::with_capacity((row_size + 1) * self.height as usize);
+        for row in self.pixels.chunks(row_size.max(1)) {
+            raw.push(0);
+            raw.extend_from_slice(row);
+        }
+
+        write_chunk(&mut bytes, b"IDAT", &zlib_stored(&raw));
+        write_chunk(&mut bytes, b"IEND", &[]);
+
+        bytes
+    }
+
+    pub fn save(&self, path: &Path, format: ImageFormat) -> Result<(), String> {
+        fs::write(path, self.encode(format)).map_err(|e| e.to_string())
+    }
+}
+
+fn write_chunk(bytes: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    bytes.extend_from_slice(&(data.len() as u32).to_be_bytes());
+
+    // The checksum covers the kind and the data of the chunk
+    let start = bytes.len();
+    bytes.extend_from_slice(kind);
+    bytes.extend_from_slice(data);
+    let crc = crc32(&bytes[start..]);
+
+    bytes.extend_from_slice(&crc.to_be_bytes());
+}
+
+// Wraps the data in a zlib stream with stored (not compressed) deflate blocks.
+// A stored block can hold up to 65535 bytes
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut bytes = vec![0x78, 0x01];
+
+    let mut blocks = data.chunks(65535).peekable();
+    if blocks.peek().is_none() {
+        // An empty stream still needs a final block
+        bytes.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let length = block.len() as u16;
+
+        bytes.push(last as u8);
+        bytes.extend_from_slice(&length.to_le_bytes());
+        bytes.extend_from_slice(&(!length).to_le_bytes());
+        bytes.extend_from_slice(block);
+    }
+
+    bytes.extend_from_slice(&adler32(data).to_be_bytes());
+
+    bytes
+}
+
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+
+    !crc
+}
+
+pub fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+
+    (b << 16) | a
+}
+
+// Date and time in UTC as `YYYYMMDD_HHMMSS_mmm`. The date is computed from the
+// number of days since 1970 using the civil calendar algorithm
+pub fn timestamp(time: SystemTime) -> String {
+    let elapsed = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let seconds = elapsed.as_secs();
+    let days = (seconds / 86400) as i64;
+    let day_seconds = seconds % 86400;
+
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{:04}{:02}{:02}_{:02}{:02}{:02}_{:03}",
+        year,
+        month,
+        day,
+        day_seconds / 3600,
+        day_seconds / 60 % 60,
+        day_seconds % 60,
+        elapsed.subsec_millis()
+    )
+}
+
+// Saves the pixels of the canvas in a new file of the folder, named with the
+// current time. Returns the path of the file
+pub fn save_screenshot<T: RenderTarget>(
+    canvas: &Canvas<T>,
+    folder: &Path,
+    format: ImageFormat,
+) -> Result<PathBuf, String> {
+    let image = Image::from_canvas(canvas)?;
+
+    fs::create_dir_all(folder).map_err(|e| e.to_string())?;
+    let name = format!(
+        "screenshot_{}.{}",
+        timestamp(SystemTime::now()),
+        format.extension()
+    );
+    let path = folder.join(name);
+
+    image.save(&path, format)?;
+
+    Ok(path)
+}
+
+// Helper used by the parts to take screenshots with a key. F12 only asks for
+// the screenshot, and it is taken the next time the frame is ready to be
+// presented, so the image has everything that was drawn in the frame. The
+// default helper saves PNG files in the screenshots folder
+pub struct Screenshots {
+    pub folder: PathBuf,
+    pub format: ImageFormat,
+    requested: bool,
+}
+
+impl Default for Screenshots {
+    fn default() -> Self {
+        Screenshots::new(Path::new(SCREENSHOT_FOLDER), ImageFormat::Png)
+    }
+}
+
+impl Screenshots {
+    pub fn new(folder: &Path, format: ImageFormat) -> Self {
+        Screenshots {
+            folder: folder.to_path_buf(),
+            format,
+            requested: false,
+        }
+    }
+
+    // Returns true if the event is the screenshot key
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::KeyDown {
+                keycode: Some(SCREENSHOT_KEY),
+                repeat: false,
+                ..
+            } => {
+                self.requested = true;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    // Takes the screenshot if it was requested. It has to be called after the
+    // frame is drawn and right before presenting the canvas, because the pixels
+    // of the canvas are not kept once the frame is presented. A screenshot that
+    // can't be saved, in a folder that can't be written or on a full disk, is
+    // reported and the game keeps running
+    pub fn capture<T: RenderTarget>(&mut self, canvas: &Canvas<T>) {
+        if self.requested {
+            self.requested = false;
+
+            match save_screenshot(canvas, &self.folder, self.format) {
+                Ok(path) => println!("Screenshot saved in {}", path.display()),
+                Err(e) => println!("Could not save the screenshot: {}", e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn test_image() -> Image {
+        // A 3x2 image with a different color in each pixel
+        Image {
+            width: 3,
+            height: 2,
+            pixels: (0..18).map(|value| value * 10).collect(),
+        }
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn test_timestamp() {
+        assert_eq!(timestamp(UNIX_EPOCH), "19700101_000000_000");
+
+        let time = UNIX_EPOCH + Duration::from_millis(951_782_400_123 + 3_723_000);
+        assert_eq!(timestamp(time), "20000229_010203_123");
+    }
+
+    #[test]
+    fn test_encode_bmp() {
+        let bytes = test_image().encode_bmp();
+
+        // Each row of 9 bytes is padded to 12 bytes
+        assert_eq!(&bytes[0..2], b"BM");
+        assert_eq!(bytes.len(), 54 + 2 * 12);
+        assert_eq!(
+            u32::from_le_bytes([bytes[2], bytes[3], bytes[4], bytes[5]]),
+            78
+        );
+
+        // The first row in the file is the bottom row, stored as blue, green and red
+        assert_eq!(&bytes[54..57], &[110, 100, 90]);
+        assert_eq!(&bytes[66..69], &[20, 10, 0]);
+    }
+
+    #[test]
+    fn test_encode_png() {
+        let bytes = test_image().encode_png();
+
+        assert_eq!(&bytes[0..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&bytes[12..16], b"IHDR");
+        assert_eq!(&bytes[bytes.len() - 8..bytes.len() - 4], b"IEND");
+
+        // The data chunk has the zlib header, one stored block with the two rows
+        // and their filter bytes, and the checksum of the rows
+        let length = u32::from_be_bytes([bytes[33], bytes[34], bytes[35], bytes[36]]) as usize;
+        let data = &bytes[41..41 + length];
+        assert_eq!(&data[0..2], &[0x78, 0x01]);
+        assert_eq!(&data[2..7], &[1, 20, 0, !20, 0xff]);
+        assert_eq!(data[7], 0);
+        assert_eq!(&data[8..11], &[0, 10, 20]);
+        assert_eq!(length, 2 + 5 + 20 + 4);
+    }
+}
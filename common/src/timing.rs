@@ -0,0 +1,232 @@
+use std::fmt;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Timing helpers for the main loops. The logic of a game is updated at a fixed
+// rate, no matter how fast the frames are drawn, and the time that is left
+// between two updates is given to the drawing as an interpolation value:
+//
+//     let mut timestep = FixedTimestep::new(60);
+//     loop {
+//         for _ in 0..timestep.tick() {
+//             update();
+//         }
+//         draw(timestep.alpha());
+//     }
+
+// The longest frame that is added to the accumulator. After a long pause, like
+// dragging the window, the game continues where it was instead of playing all
+// the missing updates at once
+const MAX_FRAME_TIME: Duration = Duration::from_millis(250);
+
+pub struct FixedTimestep {
+    step: Duration,
+    accumulator: Duration,
+    last: Option<Instant>,
+}
+
+impl FixedTimestep {
+    // Creates a timestep with the number of updates per second
+    pub fn new(rate: u32) -> Self {
+        FixedTimestep {
+            step: Duration::from_secs(1) / rate.max(1),
+            accumulator: Duration::from_secs(0),
+            last: None,
+        }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    // Measures the time since the last call and returns the number of updates
+    // that have to be played. The first call only starts the clock
+    pub fn tick(&mut self) -> u32 {
+        let now = Instant::now();
+        let elapsed = self.last.map_or(Duration::from_secs(0), |last| now - last);
+        self.last = Some(now);
+
+        self.advance(elapsed)
+    }
+
+    // Adds the elapsed time to the accumulator and takes out a step for every
+    // update that has to be played
+    pub fn advance(&mut self, elapsed: Duration) -> u32 {
+        self.accumulator += elapsed.min(MAX_FRAME_TIME);
+
+        let mut updates = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            updates += 1;
+        }
+
+        updates
+    }
+
+    // How far the time is between the last update and the next one, from 0 to 1.
+    // The drawing can use it to place the objects between both positions
+    pub fn alpha(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+// Sleeps at the end of each frame so the frames are not drawn faster than the
+// given rate. It is used when the canvas is created without vsync
+pub struct FrameLimiter {
+    frame: Option<Duration>,
+    next: Instant,
+}
+
+impl FrameLimiter {
+    // A limiter without a rate doesn't wait
+    pub fn new(rate: Option<u32>) -> Self {
+        FrameLimiter {
+            frame: rate.map(|rate| Duration::from_secs(1) / rate.max(1)),
+            next: Instant::now(),
+        }
+    }
+
+    pub fn wait(&mut self) {
+        let frame = match self.frame {
+            Some(frame) => frame,
+            None => return,
+        };
+
+        let now = Instant::now();
+        if self.next > now {
+            thread::sleep(self.next - now);
+            self.next += frame;
+        } else {
+            // The frame was late, so the next one is counted from now
+            self.next = now + frame;
+        }
+    }
+}
+
+// Frame rate and frame times measured during a period of time
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FrameReport {
+    pub fps: f32,
+    pub average: Duration,
+    pub worst: Duration,
+}
+
+impl fmt::Display for FrameReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:.1} fps, {:.1} ms average, {:.1} ms worst",
+            self.fps,
+            self.average.as_secs_f32() * 1000.0,
+            self.worst.as_secs_f32() * 1000.0
+        )
+    }
+}
+
+// Counts the frames that are drawn and makes a report every period, one second
+// by default
+pub struct FpsCounter {
+    period: Duration,
+    last: Option<Instant>,
+    frames: u32,
+    total: Duration,
+    worst: Duration,
+    report: Option<FrameReport>,
+}
+
+impl Default for FpsCounter {
+    fn default() -> Self {
+        FpsCounter::new(Duration::from_secs(1))
+    }
+}
+
+impl FpsCounter {
+    pub fn new(period: Duration) -> Self {
+        FpsCounter {
+            period,
+            last: None,
+            frames: 0,
+            total: Duration::from_secs(0),
+            worst: Duration::from_secs(0),
+            report: None,
+        }
+    }
+
+    // Called once per frame. Returns a new report when a period has passed
+    pub fn frame(&mut self) -> Option<FrameReport> {
+        let now = Instant::now();
+        let last = self.last.replace(now)?;
+
+        self.record(now - last)
+    }
+
+    // Adds the time of a frame to the current period
+    pub fn record(&mut self, frame_time: Duration) -> Option<FrameReport> {
+        self.frames += 1;
+        self.total += frame_time;
+        self.worst = self.worst.max(frame_time);
+
+        if self.total < self.period {
+            return None;
+        }
+
+        let report = FrameReport {
+            fps: self.frames as f32 / self.total.as_secs_f32(),
+            average: self.total / self.frames,
+            worst: self.worst,
+        };
+
+        self.frames = 0;
+        self.total = Duration::from_secs(0);
+        self.worst = Duration::from_secs(0);
+        self.report = Some(report);
+
+        Some(report)
+    }
+
+    // The last report that was made
+    pub fn report(&self) -> Option<FrameReport> {
+        self.report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_timestep() {
+        let mut timestep = FixedTimestep::new(50);
+        assert_eq!(timestep.step(), Duration::from_millis(20));
+
+        assert_eq!(timestep.advance(Duration::from_millis(10)), 0);
+        assert!((timestep.alpha() - 0.5).abs() < 0.001);
+
+        assert_eq!(timestep.advance(Duration::from_millis(55)), 3);
+        assert!((timestep.alpha() - 0.25).abs() < 0.001);
+
+        // A long frame only plays the updates of the longest frame
+        let mut timestep = FixedTimestep::new(100);
+        assert_eq!(timestep.advance(Duration::from_secs(5)), 25);
+    }
+
+    #[test]
+    fn test_fps_counter() {
+        let mut counter = FpsCounter::default();
+
+        for _ in 0..49 {
+            assert_eq!(counter.record(Duration::from_millis(20)), None);
+        }
+        assert_eq!(counter.report(), None);
+
+        // The worst frame is the longest one of the period
+        let report = counter.record(Duration::from_millis(40)).unwrap();
+        assert!((report.fps - 50.0 / 1.02).abs() < 0.01);
+        assert_eq!(report.average, Duration::from_micros(20_400));
+        assert_eq!(report.worst, Duration::from_millis(40));
+        assert_eq!(counter.report(), Some(report));
+
+        // A new period starts after the report
+        assert_eq!(counter.record(Duration::from_millis(20)), None);
+    }
+}
@@ -0,0 +1,592 @@
+use std::fs;
+use std::path::Path;
+
+// A small scene editor. The scene is a list of boxes, and the boxes at the end of
+// the list are drawn over the ones at the start. The mouse selects, moves and resizes
+// the boxes, and every change can be undone. The scene is saved as a text file with
+// a box per line, its position and its size:
+//
+//     # x y width height
+//     20 40 50 50
+//     100 80 120 60
+
+// The smallest width and height of a box
+pub const MIN_SIZE: i32 = 10;
+
+// Size of the squares in the corners of the selected boxes that resize them
+pub const HANDLE_SIZE: i32 = 8;
+
+// Number of changes that can be undone
+const UNDO_LIMIT: usize = 100;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Rectangle {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Rectangle {
+    pub fn new(x: i32, y: i32, width: i32, height: i32) -> Self {
+        Rectangle {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    // A rectangle between two corners given in any order
+    pub fn from_corners(a: (i32, i32), b: (i32, i32)) -> Self {
+        Rectangle::new(a.0.min(b.0), a.1.min(b.1), (a.0 - b.0).abs(), (a.1 - b.1).abs())
+    }
+
+    pub fn right(&self) -> i32 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> i32 {
+        self.y + self.height
+    }
+
+    pub fn contains(&self, point: (i32, i32)) -> bool {
+        point.0 >= self.x && point.0 < self.right() && point.1 >= self.y && point.1 < self.bottom()
+    }
+
+    pub fn intersects(&self, other: &Rectangle) -> bool {
+        self.x < other.right()
+            && other.x < self.right()
+            && self.y < other.bottom()
+            && other.y < self.bottom()
+    }
+
+    pub fn corner(&self, corner: Corner) -> (i32, i32) {
+        match corner {
+            Corner::TopLeft => (self.x, self.y),
+            Corner::TopRight => (self.right(), self.y),
+            Corner::BottomLeft => (self.x, self.bottom()),
+            Corner::BottomRight => (self.right(), self.bottom()),
+        }
+    }
+
+    // The square of the handle of a corner, centered on the corner
+    pub fn handle(&self, corner: Corner) -> Rectangle {
+        let (x, y) = self.corner(corner);
+        Rectangle::new(x - HANDLE_SIZE / 2, y - HANDLE_SIZE / 2, HANDLE_SIZE, HANDLE_SIZE)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+pub const CORNERS: [Corner; 4] = [
+    Corner::TopLeft,
+    Corner::TopRight,
+    Corner::BottomLeft,
+    Corner::BottomRight,
+];
+
+// What the mouse does while its button is held
+#[derive(Clone, Debug, PartialEq)]
+enum Drag {
+    None,
+    // Moves the selected boxes by the distance from the point where the button was
+    // pressed, so they keep their offset from the mouse. The grid snaps the box that
+    // was clicked, and the others move the same distance
+    Move { start: (i32, i32), anchor: usize, before: Vec<Rectangle> },
+    // Moves a corner of a box, and the opposite corner stays in its place
+    Resize { start: (i32, i32), index: usize, corner: Corner, before: Vec<Rectangle> },
+    // The rubber band: the boxes that touch the rectangle are selected
+    Select { start: (i32, i32), current: (i32, i32), additive: bool },
+}
+
+pub struct Editor {
+    pub boxes: Vec<Rectangle>,
+    pub selected: Vec<usize>, // Indices of the selected boxes, sorted
+    pub grid: i32,
+    pub snap: bool,
+    drag: Drag, // Ended by every change of the boxes, because it points into them
+    undo: Vec<Vec<Rectangle>>,
+    redo: Vec<Vec<Rectangle>>,
+}
+
+impl Editor {
+    pub fn new(boxes: Vec<Rectangle>, grid: i32) -> Self {
+        Editor {
+            boxes,
+            selected: Vec::new(),
+            grid: grid.max(1),
+            snap: true,
+            drag: Drag::None,
+            undo: Vec::new(),
+            redo: Vec::new(),
+        }
+    }
+
+    // The box on top at a point, the last one of the list that contains it
+    pub fn hit(&self, point: (i32, i32)) -> Option<usize> {
+        self.boxes.iter().rposition(|rectangle| rectangle.contains(point))
+    }
+
+    // The handle of a selected box at a point. The handles are drawn over the boxes,
+    // so they are found first
+    fn hit_handle(&self, point: (i32, i32)) -> Option<(usize, Corner)> {
+        self.selected.iter().rev().find_map(|index| {
+            CORNERS
+                .iter()
+                .find(|corner| self.boxes[*index].handle(**corner).contains(point))
+                .map(|corner| (*index, *corner))
+        })
+    }
+
+    pub fn is_selected(&self, index: usize) -> bool {
+        self.selected.binary_search(&index).is_ok()
+    }
+
+    // The rectangle of the rubber band while it is dragged
+    pub fn band(&self) -> Option<Rectangle> {
+        match self.drag {
+            Drag::Select { start, current, .. } => Some(Rectangle::from_corners(start, current)),
+            _ => None,
+        }
+    }
+
+    fn snap_value(&self, value: i32) -> i32 {
+        if self.snap {
+            (value + self.grid / 2).div_euclid(self.grid) * self.grid
+        } else {
+            value
+        }
+    }
+
+    // Pressing the button on a handle resizes its box and pressing it on a box moves
+    // the selected boxes. A click on a box that isn't selected selects only that box,
+    // unless the selection is additive (shift is held), which adds or removes the box.
+    // Pressing the button outside of the boxes starts a rubber band
+    pub fn mouse_down(&mut self, point: (i32, i32), additive: bool) {
+        if let Some((index, corner)) = self.hit_handle(point) {
+            self.drag = Drag::Resize { start: point, index, corner, before: self.boxes.clone() };
+            return;
+        }
+
+        match self.hit(point) {
+            Some(index) if additive && self.is_selected(index) => {
+                self.selected.retain(|selected| *selected != index);
+            }
+            Some(index) => {
+                if additive {
+                    self.select(index);
+                } else if !self.is_selected(index) {
+                    self.selected = vec![index];
+                }
+                self.drag = Drag::Move { start: point, anchor: index, before: self.boxes.clone() };
+            }
+            None => {
+                if !additive {
+                    self.selected.clear();
+                }
+                self.drag = Drag::Select { start: point, current: point, additive };
+            }
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        if let Err(position) = self.selected.binary_search(&index) {
+            self.selected.insert(position, index);
+        }
+    }
+
+    pub fn mouse_move(&mut self, point: (i32, i32)) {
+        if let Drag::Select { current, .. } = &mut self.drag {
+            *current = point;
+        }
+
+        match &self.drag {
+            Drag::None | Drag::Select { .. } => {}
+            Drag::Move { start, anchor, before } => {
+                let original = before[*anchor];
+                let x = self.snap_value(original.x + point.0 - start.0);
+                let y = self.snap_value(original.y + point.1 - start.1);
+                let (dx, dy) = (x - original.x, y - original.y);
+
+                for index in self.selected.iter() {
+                    let moved = before[*index];
+                    self.boxes[*index] =
+                        Rectangle::new(moved.x + dx, moved.y + dy, moved.width, moved.height);
+                }
+            }
+            Drag::Resize { start, index, corner, before } => {
+                let (index, corner, original) = (*index, *corner, before[*index]);
+                let (x, y) = original.corner(corner);
+                let x = self.snap_value(x + point.0 - start.0);
+                let y = self.snap_value(y + point.1 - start.1);
+
+                let (mut left, mut top, mut right, mut bottom) =
+                    (original.x, original.y, original.right(), original.bottom());
+                match corner {
+                    Corner::TopLeft | Corner::BottomLeft => left = x.min(right - MIN_SIZE),
+                    Corner::TopRight | Corner::BottomRight => right = x.max(left + MIN_SIZE),
+                }
+                match corner {
+                    Corner::TopLeft | Corner::TopRight => top = y.min(bottom - MIN_SIZE),
+                    Corner::BottomLeft | Corner::BottomRight => bottom = y.max(top + MIN_SIZE),
+                }
+
+                self.boxes[index] = Rectangle::new(left, top, right - left, bottom - top);
+            }
+        }
+    }
+
+    // Finishes the drag. A move or a resize that changed the boxes can be undone
+    pub fn mouse_up(&mut self) {
+        match std::mem::replace(&mut self.drag, Drag::None) {
+            Drag::None => {}
+            Drag::Move { before, .. } | Drag::Resize { before, .. } => {
+                if before != self.boxes {
+                    self.remember(before);
+                }
+            }
+            Drag::Select { start, current, additive } => {
+                let band = Rectangle::from_corners(start, current);
+                if !additive {
+                    self.selected.clear();
+                }
+                for index in 0..self.boxes.len() {
+                    if band.intersects(&self.boxes[index]) {
+                        self.select(index);
+                    }
+                }
+            }
+        }
+    }
+
+    // Keeps the boxes before a change in the history, so the change can be undone
+    fn remember(&mut self, before: Vec<Rectangle>) {
+        self.undo.push(before);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    // Adds a box with its center in a point, on top of the others, and selects it
+    pub fn add_box(&mut self, center: (i32, i32), size: i32) {
+        self.remember(self.boxes.clone());
+        self.drag = Drag::None;
+
+        let x = self.snap_value(center.0 - size / 2);
+        let y = self.snap_value(center.1 - size / 2);
+        self.boxes.push(Rectangle::new(x, y, size, size));
+        self.selected = vec![self.boxes.len() - 1];
+    }
+
+    pub fn delete_selected(&mut self) {
+        if self.selected.is_empty() {
+            return;
+        }
+
+        self.remember(self.boxes.clone());
+        let boxes = std::mem::take(&mut self.boxes);
+        self.boxes = boxes
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !self.is_selected(*index))
+            .map(|(_, rectangle)| rectangle)
+            .collect();
+        self.selected.clear();
+        self.drag = Drag::None;
+    }
+
+    pub fn select_all(&mut self) {
+        self.selected = (0..self.boxes.len()).collect();
+    }
+
+    // Moves the selected boxes by a distance, with the arrow keys
+    pub fn nudge(&mut self, dx: i32, dy: i32) {
+        if self.selected.is_empty() {
+            return;
+        }
+
+        self.remember(self.boxes.clone());
+        self.drag = Drag::None;
+        for index in self.selected.iter() {
+            self.boxes[*index].x += dx;
+            self.boxes[*index].y += dy;
+        }
+    }
+
+    // The selection is cleared because the boxes it points to may not exist anymore
+    pub fn undo(&mut self) {
+        if let Some(boxes) = self.undo.pop() {
+            self.redo.push(std::mem::replace(&mut self.boxes, boxes));
+            self.selected.clear();
+            self.drag = Drag::None;
+        }
+    }
+
+    pub fn redo(&mut self) {
+        if let Some(boxes) = self.redo.pop() {
+            self.undo.push(std::mem::replace(&mut self.boxes, boxes));
+            self.selected.clear();
+            self.drag = Drag::None;
+        }
+    }
+
+    // Replaces the boxes with the ones of a file. Loading can be undone too
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let boxes = parse_scene(&text)?;
+
+        let before = std::mem::replace(&mut self.boxes, boxes);
+        self.remember(before);
+        self.selected.clear();
+        self.drag = Drag::None;
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, scene_text(&self.boxes)).map_err(|e| e.to_string())
+    }
+}
+
+pub fn parse_scene(text: &str) -> Result<Vec<Rectangle>, String> {
+    let mut boxes = Vec::new();
+
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let values = line
+            .split_whitespace()
+            .map(|value| value.parse::<i32>())
+            .collect::<Result<Vec<i32>, _>>()
+            .map_err(|_| format!("Invalid box: {}", line))?;
+
+        match values[..] {
+            [x, y, width, height] if width >= MIN_SIZE && height >= MIN_SIZE => {
+                boxes.push(Rectangle::new(x, y, width, height))
+            }
+            _ => return Err(format!("Invalid box: {}", line)),
+        }
+    }
+
+    Ok(boxes)
+}
+
+pub fn scene_text(boxes: &[Rectangle]) -> String {
+    let mut text = String::from("# x y width height\n");
+    for rectangle in boxes.iter() {
+        text.push_str(&format!(
+            "{} {} {} {}\n",
+            rectangle.x, rectangle.y, rectangle.width, rectangle.height
+        ));
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_editor() -> Editor {
+        let boxes = vec![
+            Rectangle::new(0, 0, 50, 50),
+            Rectangle::new(100, 100, 50, 50),
+            Rectangle::new(120, 120, 50, 50),
+        ];
+        let mut editor = Editor::new(boxes, 10);
+        editor.snap = false;
+        editor
+    }
+
+    #[test]
+    fn test_hit_and_select() {
+        let mut editor = test_editor();
+
+        // The box on top is found where two boxes overlap
+        assert_eq!(editor.hit((130, 130)), Some(2));
+        assert_eq!(editor.hit((105, 105)), Some(1));
+        assert_eq!(editor.hit((300, 300)), None);
+
+        editor.mouse_down((10, 10), false);
+        editor.mouse_up();
+        assert_eq!(editor.selected, vec![0]);
+
+        // Shift adds boxes to the selection and removes the selected ones
+        editor.mouse_down((105, 105), true);
+        editor.mouse_up();
+        assert_eq!(editor.selected, vec![0, 1]);
+        editor.mouse_down((10, 10), true);
+        editor.mouse_up();
+        assert_eq!(editor.selected, vec![1]);
+
+        // A click outside of the boxes clears the selection
+        editor.mouse_down((300, 300), false);
+        editor.mouse_up();
+        assert!(editor.selected.is_empty());
+    }
+
+    #[test]
+    fn test_drag() {
+        let mut editor = test_editor();
+        editor.select_all();
+
+        // All the selected boxes move with the mouse and keep their offset from it
+        editor.mouse_down((10, 10), false);
+        assert_eq!(editor.selected, vec![0, 1, 2]);
+        editor.mouse_move((25, 17));
+        editor.mouse_up();
+        assert_eq!(editor.boxes[0], Rectangle::new(15, 7, 50, 50));
+        assert_eq!(editor.boxes[2], Rectangle::new(135, 127, 50, 50));
+
+        // With the grid the clicked box snaps to it, and the others move the same
+        editor.snap = true;
+        editor.mouse_down((20, 20), false);
+        editor.mouse_move((31, 20));
+        editor.mouse_up();
+        assert_eq!(editor.boxes[0], Rectangle::new(30, 10, 50, 50));
+        assert_eq!(editor.boxes[1], Rectangle::new(130, 110, 50, 50));
+    }
+
+    #[test]
+    fn test_rubber_band() {
+        let mut editor = test_editor();
+
+        // The band selects the boxes it touches, dragged in any direction
+        editor.mouse_down((200, 200), false);
+        editor.mouse_move((110, 60));
+        assert_eq!(editor.band(), Some(Rectangle::new(110, 60, 90, 140)));
+        editor.mouse_up();
+        assert_eq!(editor.selected, vec![1, 2]);
+        assert_eq!(editor.band(), None);
+
+        // With shift the band adds to the selection
+        editor.mouse_down((60, 60), true);
+        editor.mouse_move((40, 40));
+        editor.mouse_up();
+        assert_eq!(editor.selected, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_resize() {
+        let mut editor = test_editor();
+        editor.mouse_down((10, 10), false);
+        editor.mouse_up();
+
+        // Dragging the bottom right handle changes the size
+        editor.mouse_down((51, 49), false);
+        editor.mouse_move((81, 59));
+        editor.mouse_up();
+        assert_eq!(editor.boxes[0], Rectangle::new(0, 0, 80, 60));
+
+        // The top left corner can't go past the opposite corner
+        editor.mouse_down((0, 0), false);
+        editor.mouse_move((200, 200));
+        editor.mouse_up();
+        assert_eq!(editor.boxes[0], Rectangle::new(70, 50, MIN_SIZE, MIN_SIZE));
+
+        // With the grid the corner snaps to it
+        editor.snap = true;
+        editor.mouse_down((80, 60), false);
+        editor.mouse_move((103, 96));
+        editor.mouse_up();
+        assert_eq!(editor.boxes[0], Rectangle::new(70, 50, 30, 50));
+    }
+
+    #[test]
+    fn test_delete_while_resizing() {
+        // Deleting the box ends the resize, so the mouse doesn't change the box that
+        // takes its place and releasing the button adds nothing to the history
+        let mut editor = test_editor();
+        editor.mouse_down((10, 10), false);
+        editor.mouse_up();
+
+        editor.mouse_down((51, 49), false);
+        editor.delete_selected();
+        editor.mouse_move((81, 59));
+        editor.mouse_up();
+        let rest = [Rectangle::new(100, 100, 50, 50), Rectangle::new(120, 120, 50, 50)];
+        assert_eq!(editor.boxes, rest);
+
+        editor.undo();
+        assert_eq!(editor.boxes, test_editor().boxes);
+        assert!(editor.undo.is_empty());
+
+        // The same with the last box of the scene
+        let mut editor = Editor::new(vec![Rectangle::new(0, 0, 50, 50)], 10);
+        editor.mouse_down((10, 10), false);
+        editor.mouse_up();
+        editor.mouse_down((51, 49), false);
+        editor.delete_selected();
+        editor.mouse_move((81, 59));
+        editor.mouse_up();
+        assert!(editor.boxes.is_empty());
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut editor = test_editor();
+        let original = editor.boxes.clone();
+
+        editor.mouse_down((10, 10), false);
+        editor.mouse_move((30, 10));
+        editor.mouse_up();
+        let moved = editor.boxes.clone();
+
+        editor.mouse_down((140, 160), false);
+        editor.mouse_up();
+        editor.delete_selected();
+        assert_eq!(editor.boxes.len(), 2);
+
+        editor.undo();
+        assert_eq!(editor.boxes, moved);
+        editor.undo();
+        assert_eq!(editor.boxes, original);
+        editor.undo();
+        assert_eq!(editor.boxes, original);
+
+        editor.redo();
+        assert_eq!(editor.boxes, moved);
+
+        // A new change forgets the changes that were undone
+        editor.add_box((300, 300), 40);
+        assert_eq!(editor.boxes[3], Rectangle::new(280, 280, 40, 40));
+        assert_eq!(editor.selected, vec![3]);
+        editor.redo();
+        assert_eq!(editor.boxes.len(), 4);
+
+        // A click without moving doesn't add a change
+        editor.mouse_down((300, 300), false);
+        editor.mouse_up();
+        editor.undo();
+        assert_eq!(editor.boxes, moved);
+    }
+
+    #[test]
+    fn test_scene_file() {
+        let editor = test_editor();
+        let text = scene_text(&editor.boxes);
+        assert_eq!(parse_scene(&text).unwrap(), editor.boxes);
+
+        assert!(parse_scene("10 10 50").is_err());
+        assert!(parse_scene("10 10 50 x").is_err());
+        assert!(parse_scene("10 10 5 50").is_err());
+        assert!(parse_scene("# empty\n\n").unwrap().is_empty());
+
+        let path = std::env::temp_dir().join("part_2_scene.txt");
+        editor.save(&path).unwrap();
+        let mut other = Editor::new(Vec::new(), 10);
+        other.load(&path).unwrap();
+        assert_eq!(other.boxes, editor.boxes);
+        other.undo();
+        assert!(other.boxes.is_empty());
+    }
+}
@@ -1,13 +1,83 @@
+mod editor;
+
+use std::path::Path;
+
+use common::screenshot::Screenshots;
+use editor::{Editor, Rectangle, CORNERS};
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
-use sdl2::pixels::{Color, PixelFormatEnum};
+use sdl2::keyboard::{Keycode, Mod};
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::Color;
 use sdl2::rect::Rect;
+use sdl2::render::{Canvas, RenderTarget};
+
+const WINDOW_NAME: &str = "editor";
+const WINDOW_HEIGHT: u32 = 480;
+const WINDOW_WIDTH: u32 = 640;
+const BOX_SIZE: i32 = 50;
+const STEP_SIZE: i32 = 10;
+const GRID_SIZE: i32 = 10;
+
+// The scene is saved to and loaded from this file in the folder where the program runs
+const SCENE_FILE: &str = "scene.txt";
+
+fn to_rect(rectangle: &Rectangle) -> Rect {
+    Rect::new(
+        rectangle.x,
+        rectangle.y,
+        rectangle.width.max(1) as u32,
+        rectangle.height.max(1) as u32)
+}
+
+// The boxes of the scene when the program starts
+fn start_boxes() -> Vec<Rectangle> {
+    vec![
+        Rectangle::new(60, 60, BOX_SIZE, BOX_SIZE),
+        Rectangle::new(200, 120, 120, 80),
+        Rectangle::new(260, 160, BOX_SIZE, BOX_SIZE),
+    ]
+}
+
+// Draws the scene: the grid when the boxes snap to it, the boxes, the outline and
+// the handles of the selected boxes and the rubber band
+fn draw<T: RenderTarget>(canvas: &mut Canvas<T>, editor: &Editor) -> Result<(), String> {
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    canvas.clear();
+
+    // Only every fourth line of the grid is drawn, so the boxes are still easy to see
+    if editor.snap {
+        let spacing = editor.grid * 4;
+        canvas.set_draw_color(Color::RGB(225, 225, 225));
+        for x in (0..WINDOW_WIDTH as i32).step_by(spacing as usize) {
+            canvas.draw_line((x, 0), (x, WINDOW_HEIGHT as i32))?;
+        }
+        for y in (0..WINDOW_HEIGHT as i32).step_by(spacing as usize) {
+            canvas.draw_line((0, y), (WINDOW_WIDTH as i32, y))?;
+        }
+    }
+
+    for (index, rectangle) in editor.boxes.iter().enumerate() {
+        canvas.set_draw_color(Color::RGB(100, 100, 100));
+        canvas.fill_rect(to_rect(rectangle))?;
+        canvas.set_draw_color(Color::RGB(60, 60, 60));
+        canvas.draw_rect(to_rect(rectangle))?;
+
+        if editor.is_selected(index) {
+            canvas.set_draw_color(Color::RGB(0, 120, 255));
+            canvas.draw_rect(to_rect(rectangle))?;
+            for corner in CORNERS.iter() {
+                canvas.fill_rect(to_rect(&rectangle.handle(*corner)))?;
+            }
+        }
+    }
+
+    if let Some(band) = editor.band() {
+        canvas.set_draw_color(Color::RGB(0, 120, 255));
+        canvas.draw_rect(to_rect(&band))?;
+    }
 
-const WINDOW_NAME: &str = "moving";
-const WINDOW_HEIGHT: u32 = 200;
-const WINDOW_WIDTH: u32 = 300;
-const BOX_SIZE: u32 = 50;
-const STEP_SIZE: u32 = 10;
+    Ok(())
+}
 
 fn main() -> Result<(), String> {
     let ctx = sdl2::init()?;
@@ -25,14 +95,10 @@ fn main() -> Result<(), String> {
         .build()
         .map_err(|e| e.to_string())?;
 
-    let creator = canvas.texture_creator();
-
-    let mut box_target = creator
-        .create_texture_target(PixelFormatEnum::RGBA8888, BOX_SIZE, BOX_SIZE)
-        .map_err(|e| e.to_string())?;
+    let mut screenshots = Screenshots::default();
 
-    // Position tuple that will be updated with the arrows or mouse movement
-    let mut position = (0, 0);
+    let mut editor = Editor::new(start_boxes(), GRID_SIZE);
+    let mut title = String::new();
 
     'mainloop: loop {
         // The context event pump contains all the posible events that can
@@ -45,77 +111,105 @@ fn main() -> Result<(), String> {
                 Event::KeyDown { keycode: Some(Keycode::Escape), .. } | Event::Quit { .. } => {
                     break 'mainloop
                 },
-
-                // The next events will decide how to move the object
-                Event::KeyDown { keycode: Some(Keycode::Up), .. } => {
-                    // If the new position is negative then the box
-                    // will be drawn at the egde of the window
-                    let new_y = position.1 as i32 - STEP_SIZE as i32;
-
-                    position.1 = if new_y > 0 { new_y } else { 0 };
+                _ if screenshots.handle_event(&event) => {},
+
+                // A double click outside of the boxes adds a new box, and the other
+                // clicks select, move and resize the boxes. Shift adds to the selection
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, clicks, x, y, .. } => {
+                    if clicks == 2 && editor.hit((x, y)).is_none() {
+                        editor.add_box((x, y), BOX_SIZE);
+                    } else {
+                        let keymod = ctx.keyboard().mod_state();
+                        editor.mouse_down((x, y), keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD));
+                    }
                 },
 
-                Event::KeyDown { keycode: Some(Keycode::Down), .. } => {
-                    // If the new position is larger than the window
-                    // size then it will be drawn at the edge of the window
-                    let new_y = position.1 as i32 + STEP_SIZE as i32;
-
-                    position.1 = if new_y + BOX_SIZE as i32 > WINDOW_HEIGHT as i32 { 
-                        WINDOW_HEIGHT as i32 - BOX_SIZE as i32
-                    } else { new_y };
+                Event::MouseMotion { x, y, .. } => {
+                    editor.mouse_move((x, y));
                 },
 
-                Event::KeyDown { keycode: Some(Keycode::Left), .. } => {
-                    // If the new position is negative then the box
-                    // will be drawn at the egde of the window
-                    let new_x = position.0 as i32 - STEP_SIZE as i32;
-
-                    position.0 = if new_x > 0 { new_x } else { 0 };
+                Event::MouseButtonUp { mouse_btn: MouseButton::Left, .. } => {
+                    editor.mouse_up();
                 },
 
-                Event::KeyDown { keycode: Some(Keycode::Right), .. } => {
-                    // If the new position is larger than the window
-                    // size then it will be drawn at the edge of the window
-                    let new_x = position.0 as i32 + STEP_SIZE as i32;
-
-                    position.0 = if new_x + BOX_SIZE as i32 > WINDOW_WIDTH  as i32 { 
-                        WINDOW_WIDTH as i32 - BOX_SIZE as i32
-                    } else { new_x };
+                Event::KeyDown { keycode: Some(keycode), keymod, .. } => {
+                    let ctrl = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD);
+                    let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+
+                    // The arrows move the selected boxes a step, or a cell of the grid
+                    let step = if editor.snap { editor.grid } else { STEP_SIZE };
+
+                    match keycode {
+                        Keycode::Delete | Keycode::Backspace => editor.delete_selected(),
+                        Keycode::G => editor.snap = !editor.snap,
+                        Keycode::A if ctrl => editor.select_all(),
+                        Keycode::Z if ctrl && shift => editor.redo(),
+                        Keycode::Z if ctrl => editor.undo(),
+                        Keycode::Y if ctrl => editor.redo(),
+                        // The editor keeps running when the file can't be written or
+                        // read, and a file that can't be read leaves the scene as it was
+                        Keycode::S if ctrl => match editor.save(Path::new(SCENE_FILE)) {
+                            Ok(()) => println!("Saved the scene to {}", SCENE_FILE),
+                            Err(e) => println!("Could not save {}: {}", SCENE_FILE, e),
+                        },
+                        Keycode::O if ctrl => match editor.load(Path::new(SCENE_FILE)) {
+                            Ok(()) => println!("Loaded the scene from {}", SCENE_FILE),
+                            Err(e) => println!("Could not load {}: {}", SCENE_FILE, e),
+                        },
+                        Keycode::Up => editor.nudge(0, -step),
+                        Keycode::Down => editor.nudge(0, step),
+                        Keycode::Left => editor.nudge(-step, 0),
+                        Keycode::Right => editor.nudge(step, 0),
+                        _ => {},
+                    }
                 },
-
-                Event::MouseMotion { x: x_val, y: y_val, .. } => {
-                    // Using the MouseMotion enum to extract the position values
-                    // of the mouse
-                    position.0 = x_val;
-                    position.1 = y_val;
-                }
                 _ => {},
             }
         }
 
-        canvas.set_draw_color(Color::RGB(255, 255, 255));
-        canvas.clear();
-
-        canvas
-            .with_texture_canvas(&mut box_target, |texture| {
-                texture.set_draw_color(Color::RGB(100, 100, 100));
-                texture.clear();
-            })
-            .map_err(|e| e.to_string())?;
-
-        canvas
-            .copy(
-                &box_target, 
-                None, 
-                Rect::new(
-                    position.0,
-                    position.1,
-                    BOX_SIZE,
-                    BOX_SIZE)
-                )?;
+        draw(&mut canvas, &editor)?;
 
+        screenshots.capture(&canvas);
         canvas.present();
+
+        // The number of boxes and whether they snap to the grid are shown in the
+        // title of the window. The title is only changed when they change
+        let current = format!(
+            "{} - {} boxes - {} selected - grid {}",
+            WINDOW_NAME,
+            editor.boxes.len(),
+            editor.selected.len(),
+            if editor.snap { "on" } else { "off" });
+        if current != title {
+            canvas
+                .window_mut()
+                .set_title(&current)
+                .map_err(|e| e.to_string())?;
+            title = current;
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::golden::{self, Tolerance};
+
+    #[test]
+    fn test_draw() {
+        // The second box is selected and a rubber band is being dragged
+        let mut canvas = golden::surface_canvas(WINDOW_WIDTH, WINDOW_HEIGHT).unwrap();
+        let mut editor = Editor::new(start_boxes(), GRID_SIZE);
+        editor.mouse_down((210, 130), false);
+        editor.mouse_up();
+        editor.mouse_down((400, 300), true);
+        editor.mouse_move((500, 400));
+
+        draw(&mut canvas, &editor).unwrap();
+
+        let reference = golden::reference(env!("CARGO_MANIFEST_DIR"), "editor");
+        golden::assert_golden(&canvas, &reference, Tolerance::default());
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+
+// The broad phase finds the pairs of boxes that overlap without checking every box
+// against every other box. The boxes are put in cells of the world first, and only
+// the boxes that share a cell are checked against each other. Checking every pair
+// is fine for a few boxes, but with thousands of them it takes most of the frame
+
+// A rectangle of the world given by its sides
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl Aabb {
+    pub fn new(left: f32, top: f32, right: f32, bottom: f32) -> Self {
+        Aabb {
+            left,
+            top,
+            right,
+            bottom,
+        }
+    }
+
+    // The rectangles that only touch don't overlap
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.left < other.right
+            && other.left < self.right
+            && self.top < other.bottom
+            && other.top < self.bottom
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.left <= other.left
+            && other.right <= self.right
+            && self.top <= other.top
+            && other.bottom <= self.bottom
+    }
+
+    // The four quarters of the rectangle
+    fn quarters(&self) -> [Aabb; 4] {
+        let x = (self.left + self.right) / 2.0;
+        let y = (self.top + self.bottom) / 2.0;
+
+        [
+            Aabb::new(self.left, self.top, x, y),
+            Aabb::new(x, self.top, self.right, y),
+            Aabb::new(self.left, y, x, self.bottom),
+            Aabb::new(x, y, self.right, self.bottom),
+        ]
+    }
+}
+
+pub trait BroadPhase {
+    fn name(&self) -> &'static str;
+
+    // The pairs of boxes that overlap, given by their indices, with the first index
+    // smaller than the second one. Each pair is given once, and the pairs are sorted
+    // so every broad phase resolves the collisions in the same order
+    fn pairs(&mut self, boxes: &[Aabb]) -> Vec<(usize, usize)>;
+
+    // The cells used by the last search, to draw them for debugging
+    fn cells(&self) -> Vec<Aabb>;
+}
+
+// The broad phases that can be chosen by name, from the slowest to the fastest. The
+// cell size of the spatial hash should be about the size of the largest box
+pub const NAMES: [&str; 3] = ["brute", "hash", "tree"];
+
+pub fn from_name(name: &str, cell_size: f32) -> Option<Box<dyn BroadPhase>> {
+    match name {
+        "brute" => Some(Box::new(BruteForce)),
+        "hash" => Some(Box::new(SpatialHash::new(cell_size))),
+        "tree" => Some(Box::new(QuadTree::new(QUAD_CAPACITY, QUAD_DEPTH))),
+        _ => None,
+    }
+}
+
+// Every box is checked against every other box
+pub struct BruteForce;
+
+impl BroadPhase for BruteForce {
+    fn name(&self) -> &'static str {
+        "brute"
+    }
+
+    fn pairs(&mut self, boxes: &[Aabb]) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+
+        for a in 0..boxes.len() {
+            for b in a + 1..boxes.len() {
+                if boxes[a].overlaps(&boxes[b]) {
+                    pairs.push((a, b));
+                }
+            }
+        }
+
+        pairs
+    }
+
+    fn cells(&self) -> Vec<Aabb> {
+        Vec::new()
+    }
+}
+
+// The world is split in a grid of square cells of the same size, and each box is
+// put in the cells it touches. Only the cells with boxes are kept, in a hash map,
+// so the world can be as large as needed
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        SpatialHash {
+            cell_size: cell_size.max(1.0),
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, x: f32, y: f32) -> (i32, i32) {
+        ((x / self.cell_size).floor() as i32, (y / self.cell_size).floor() as i32)
+    }
+}
+
+impl BroadPhase for SpatialHash {
+    fn name(&self) -> &'static str {
+        "hash"
+    }
+
+    fn pairs(&mut self, boxes: &[Aabb]) -> Vec<(usize, usize)> {
+        // The lists of the cells are emptied instead of removed, so they don't have to
+        // be allocated again in the next step, and the cells left empty are dropped
+        self.cells.retain(|_, items| !items.is_empty());
+        for items in self.cells.values_mut() {
+            items.clear();
+        }
+
+        for (index, bounds) in boxes.iter().enumerate() {
+            let first = self.cell(bounds.left, bounds.top);
+            let last = self.cell(bounds.right, bounds.bottom);
+
+            for column in first.0..=last.0 {
+                for row in first.1..=last.1 {
+                    self.cells.entry((column, row)).or_default().push(index);
+                }
+            }
+        }
+
+        // A pair that shares several cells is found once in each of them
+        let mut pairs = Vec::new();
+        for items in self.cells.values() {
+            for (position, a) in items.iter().enumerate() {
+                for b in items[position + 1..].iter() {
+                    if boxes[*a].overlaps(&boxes[*b]) {
+                        pairs.push((*a.min(b), *a.max(b)));
+                    }
+                }
+            }
+        }
+
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+
+    fn cells(&self) -> Vec<Aabb> {
+        let size = self.cell_size;
+
+        self.cells
+            .iter()
+            .filter(|(_, items)| !items.is_empty())
+            .map(|((column, row), _)| {
+                let (x, y) = (*column as f32 * size, *row as f32 * size);
+                Aabb::new(x, y, x + size, y + size)
+            })
+            .collect()
+    }
+}
+
+// A quadtree splits a cell in four when it has too many boxes, so the crowded parts
+// of the world have small cells and the empty parts large ones. A box is kept in the
+// smallest cell that contains all of it, so the boxes on the lines between two cells
+// stay in the larger cell above them
+const QUAD_CAPACITY: usize = 8;
+const QUAD_DEPTH: usize = 8;
+
+struct Node {
+    bounds: Aabb,
+    depth: usize,
+    items: Vec<usize>,
+    children: Option<usize>, // Index of the first of the four children
+}
+
+pub struct QuadTree {
+    capacity: usize,
+    max_depth: usize,
+    nodes: Vec<Node>,
+}
+
+impl QuadTree {
+    pub fn new(capacity: usize, max_depth: usize) -> Self {
+        QuadTree {
+            capacity: capacity.max(1),
+            max_depth,
+            nodes: Vec::new(),
+        }
+    }
+
+    fn insert(&mut self, boxes: &[Aabb], node: usize, index: usize) {
+        if let Some(first) = self.nodes[node].children {
+            let child = (first..first + 4)
+                .find(|child| self.nodes[*child].bounds.contains(&boxes[index]));
+            match child {
+                Some(child) => self.insert(boxes, child, index),
+                None => self.nodes[node].items.push(index),
+            }
+            return;
+        }
+
+        self.nodes[node].items.push(index);
+
+        let full = self.nodes[node].items.len() > self.capacity;
+        if full && self.nodes[node].depth < self.max_depth {
+            self.split(boxes, node);
+        }
+    }
+
+    // Adds four children to a cell and moves down the boxes that fit in one of them
+    fn split(&mut self, boxes: &[Aabb], node: usize) {
+        let first = self.nodes.len();
+        let depth = self.nodes[node].depth + 1;
+
+        for bounds in self.nodes[node].bounds.quarters().iter() {
+            self.nodes.push(Node {
+                bounds: *bounds,
+                depth,
+                items: Vec::new(),
+                children: None,
+            });
+        }
+        self.nodes[node].children = Some(first);
+
+        for index in std::mem::take(&mut self.nodes[node].items) {
+            self.insert(boxes, node, index);
+        }
+    }
+
+    // Two boxes that overlap are in the same cell, or one of them is in a cell above
+    // the cell of the other one, because the children of a cell don't overlap. Each
+    // box is checked against the rest of its cell and the boxes of the cells above
+    fn collect(
+        &self,
+        boxes: &[Aabb],
+        node: usize,
+        above: &mut Vec<usize>,
+        pairs: &mut Vec<(usize, usize)>,
+    ) {
+        let items = &self.nodes[node].items;
+
+        for (position, a) in items.iter().enumerate() {
+            for b in above.iter().chain(items[position + 1..].iter()) {
+                if boxes[*a].overlaps(&boxes[*b]) {
+                    pairs.push((*a.min(b), *a.max(b)));
+                }
+            }
+        }
+
+        if let Some(first) = self.nodes[node].children {
+            let count = above.len();
+            above.extend_from_slice(items);
+
+            for child in first..first + 4 {
+                self.collect(boxes, child, above, pairs);
+            }
+
+            above.truncate(count);
+        }
+    }
+}
+
+impl BroadPhase for QuadTree {
+    fn name(&self) -> &'static str {
+        "tree"
+    }
+
+    // The tree is made again in every step, inside of a square that contains all the
+    // boxes
+    fn pairs(&mut self, boxes: &[Aabb]) -> Vec<(usize, usize)> {
+        self.nodes.clear();
+        if boxes.is_empty() {
+            return Vec::new();
+        }
+
+        let mut bounds = boxes[0];
+        for other in boxes.iter() {
+            bounds.left = bounds.left.min(other.left);
+            bounds.top = bounds.top.min(other.top);
+            bounds.right = bounds.right.max(other.right);
+            bounds.bottom = bounds.bottom.max(other.bottom);
+        }
+        let side = (bounds.right - bounds.left).max(bounds.bottom - bounds.top);
+        bounds.right = bounds.left + side;
+        bounds.bottom = bounds.top + side;
+
+        self.nodes.push(Node {
+            bounds,
+            depth: 0,
+            items: Vec::new(),
+            children: None,
+        });
+        for index in 0..boxes.len() {
+            self.insert(boxes, 0, index);
+        }
+
+        let mut pairs = Vec::new();
+        self.collect(boxes, 0, &mut Vec::new(), &mut pairs);
+
+        pairs.sort_unstable();
+        pairs
+    }
+
+    fn cells(&self) -> Vec<Aabb> {
+        self.nodes.iter().map(|node| node.bounds).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Boxes of different sizes spread over the world, many of them overlapping
+    fn test_boxes(count: usize) -> Vec<Aabb> {
+        let mut seed = 1u32;
+        let mut random = move || common::random::random(&mut seed);
+
+        (0..count)
+            .map(|_| {
+                let (x, y, size) = (random() * 500.0, random() * 300.0, 4.0 + random() * 30.0);
+                Aabb::new(x, y, x + size, y + size)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_same_pairs() {
+        let boxes = test_boxes(400);
+        let expected = BruteForce.pairs(&boxes);
+        assert!(expected.len() > 100);
+
+        for name in NAMES.iter() {
+            let mut broad_phase = from_name(name, 34.0).unwrap();
+            assert_eq!(broad_phase.name(), *name);
+            assert_eq!(broad_phase.pairs(&boxes), expected);
+        }
+
+        assert!(from_name("sweep", 34.0).is_none());
+    }
+
+    #[test]
+    fn test_spatial_hash() {
+        // A box on the corner between four cells is in the four of them
+        let mut hash = SpatialHash::new(10.0);
+        let boxes = vec![Aabb::new(5.0, 5.0, 15.0, 15.0), Aabb::new(-5.0, 12.0, -1.0, 14.0)];
+        assert!(hash.pairs(&boxes).is_empty());
+
+        let mut cells = hash.cells();
+        cells.sort_by(|a, b| (a.left, a.top).partial_cmp(&(b.left, b.top)).unwrap());
+        assert_eq!(cells.len(), 5);
+        assert_eq!(cells[0], Aabb::new(-10.0, 10.0, 0.0, 20.0));
+        assert_eq!(cells[4], Aabb::new(10.0, 10.0, 20.0, 20.0));
+
+        // The cells that are left empty are not drawn
+        hash.pairs(&boxes[..1]);
+        assert_eq!(hash.cells().len(), 4);
+    }
+
+    #[test]
+    fn test_quad_tree() {
+        // A few boxes fit in the root, and more boxes split it
+        let mut tree = QuadTree::new(4, 3);
+        let boxes = test_boxes(4);
+        tree.pairs(&boxes);
+        assert_eq!(tree.cells().len(), 1);
+
+        let boxes = test_boxes(200);
+        assert_eq!(tree.pairs(&boxes), BruteForce.pairs(&boxes));
+        assert!(tree.cells().len() > 5);
+        assert!(tree.nodes.iter().all(|node| node.depth <= 3));
+
+        // Every box is in a cell that contains it
+        let mut count = 0;
+        for node in tree.nodes.iter() {
+            for index in node.items.iter() {
+                assert!(node.bounds.contains(&boxes[*index]));
+                count += 1;
+            }
+        }
+        assert_eq!(count, boxes.len());
+
+        assert!(tree.pairs(&[]).is_empty());
+        assert!(tree.cells().is_empty());
+    }
+}
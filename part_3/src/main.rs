@@ -1,24 +1,141 @@
+use std::env;
+
+use common::random::random;
+use common::screenshot::Screenshots;
+use common::timing::{FixedTimestep, FpsCounter, FrameLimiter};
 use sdl2::event::Event;
 use sdl2::keyboard::Keycode;
-use sdl2::pixels::{Color, PixelFormatEnum};
-use sdl2::rect::{Rect, Point};
+use sdl2::mouse::MouseButton;
+use sdl2::pixels::Color;
+use sdl2::rect::{Point, Rect};
+use sdl2::render::{Canvas, RenderTarget};
+
+mod broadphase;
+mod physics;
+mod stress;
+use broadphase::Aabb;
+use physics::World;
 
 const WINDOW_NAME: &str = "auto";
-const WINDOW_HEIGHT: u32 = 200;
-const WINDOW_WIDTH: u32 = 300;
-const BOX_SIZE: u32 = 50;
-const STEP_SIZE: u32 = 1;
-
-// Structure used to keep track of the position of the 
-// box and its velocity direction
-struct Box {
-    x: i32, 
-    y: i32,
-    vel_x: i32,
-    vel_y: i32,
+const WINDOW_HEIGHT: u32 = 480;
+const WINDOW_WIDTH: u32 = 640;
+
+// The world is moved 60 times per second and drawn 60 times per second. Each update
+// moves the boxes by their velocity and the time of a step, and the frames between
+// two updates place the boxes between their last and their next position
+const UPDATE_RATE: u32 = 60;
+const FRAME_RATE: u32 = 60;
+
+// The boxes that are spawned have a size and a speed, in pixels per second, between
+// the smallest and the largest, in a random direction
+const BOX_SIZE: (f32, f32) = (16.0, 48.0);
+const BOX_SPEED: (f32, f32) = (60.0, 240.0);
+const START_BOXES: usize = 12;
+
+// Part of the speed kept by the boxes that bounce off the walls. The up and down
+// arrows change it while the sandbox runs
+const RESTITUTION: f32 = 0.9;
+const RESTITUTION_STEP: f32 = 0.1;
+
+// The broad phase used to find the boxes that collide. The cells of the spatial hash
+// are as large as the largest box, so a box is never in more than four cells
+const BROAD_PHASE: &str = "hash";
+const CELL_SIZE: f32 = BOX_SIZE.1;
+
+// Adds a box of a random size with its center in a point, going in a random direction.
+// The same seed gives the same boxes every time the sandbox starts
+pub fn spawn_box(world: &mut World, seed: &mut u32, center: (f32, f32)) {
+    let size = BOX_SIZE.0 + (BOX_SIZE.1 - BOX_SIZE.0) * random(seed);
+    let speed = BOX_SPEED.0 + (BOX_SPEED.1 - BOX_SPEED.0) * random(seed);
+    let angle = random(seed) * std::f32::consts::TAU;
+
+    world.spawn(center, (speed * angle.cos(), speed * angle.sin()), size);
+}
+
+// Draws the boxes with a line that shows their direction. The boxes are drawn part
+// of the way from their last position to their current one, over the cells of the
+// broad phase when they are given
+fn draw<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    world: &World,
+    alpha: f32,
+    cells: &[Aabb],
+) -> Result<(), String> {
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    canvas.clear();
+
+    canvas.set_draw_color(Color::RGB(150, 200, 255));
+    for cell in cells.iter() {
+        let (left, top) = (cell.left.round() as i32, cell.top.round() as i32);
+        let width = (cell.right - cell.left).round() as u32;
+        let height = (cell.bottom - cell.top).round() as u32;
+        canvas.draw_rect(Rect::new(left, top, width.max(1), height.max(1)))?;
+    }
+
+    for body in world.bodies.iter() {
+        let (x, y) = body.interpolate(alpha);
+        let (x, y) = (x.round() as i32, y.round() as i32);
+        let size = body.size.round() as i32;
+
+        canvas.set_draw_color(Color::RGB(100, 100, 100));
+        canvas.fill_rect(Rect::new(x, y, size as u32, size as u32))?;
+
+        // Drawing a line to show the direction of the box using the
+        // direction velocity. The line starts at the middle of the
+        // box and continues all the way to the end of the box
+        let x_end = if body.velocity.0 > 0.0 { size } else { 0 };
+        let y_end = if body.velocity.1 > 0.0 { size } else { 0 };
+
+        canvas.set_draw_color(Color::RGB(255, 0, 0));
+        canvas.draw_line(
+            Point::new(x + size / 2, y + size / 2),
+            Point::new(x + x_end, y + y_end))?;
+    }
+
+    Ok(())
 }
 
 fn main() -> Result<(), String> {
+    // The option `--broad-phase <name>` selects how the collisions are found: brute,
+    // hash or tree. The option `--headless [count]` moves that many boxes (10000 by
+    // default) without a window and reports the time of the updates, and `--bench`
+    // compares the broad phases with different numbers of boxes
+    let mut broad_phase_name = String::from(BROAD_PHASE);
+    let mut headless = None;
+    let mut bench = false;
+
+    let mut args = env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--broad-phase" => broad_phase_name = args.next().unwrap_or_default(),
+            "--headless" => {
+                let count = match args.next_if(|count| !count.starts_with("--")) {
+                    Some(count) => count
+                        .parse::<usize>()
+                        .map_err(|_| format!("Invalid number of boxes: {}", count))?,
+                    None => stress::STRESS_BOXES,
+                };
+                headless = Some(count);
+            }
+            "--bench" => bench = true,
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+
+    let mut broad_phase = broadphase::from_name(&broad_phase_name, CELL_SIZE)
+        .ok_or(format!("Unknown broad phase: {}", broad_phase_name))?;
+
+    // The stress tests don't use SDL, so they work without a display
+    if bench {
+        stress::bench();
+        return Ok(());
+    }
+
+    if let Some(count) = headless {
+        stress::run(count, broad_phase.as_mut());
+        return Ok(());
+    }
+
     let ctx = sdl2::init()?;
     let video = ctx.video()?;
 
@@ -34,21 +151,24 @@ fn main() -> Result<(), String> {
         .build()
         .map_err(|e| e.to_string())?;
 
-    let creator = canvas.texture_creator();
-
-    let mut box_target = creator
-        .create_texture_target(PixelFormatEnum::RGBA8888, BOX_SIZE, BOX_SIZE)
-        .map_err(|e| e.to_string())?;
+    let mut screenshots = Screenshots::default();
 
-    // Context timer used to extract time information for the loop
-    let mut timer = ctx.timer()?;
+    // The timestep tells how many times the world has to be updated in each frame,
+    // the limiter waits so the frames are not drawn faster than the frame rate and
+    // the counter measures the frames that are actually drawn
+    let mut timestep = FixedTimestep::new(UPDATE_RATE);
+    let mut limiter = FrameLimiter::new(Some(FRAME_RATE));
+    let mut fps = FpsCounter::default();
 
-    let mut box_position = Box {
-        x: 0,
-        y: 0, 
-        vel_x: 1,
-        vel_y: 1,
-    };
+    // The sandbox starts with a few boxes in a row, and a click of the mouse adds a
+    // box where it is clicked
+    let mut world = World::new(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32, RESTITUTION);
+    let mut seed = 0;
+    let mut show_cells = false;
+    for index in 0..START_BOXES {
+        let x = (index as f32 + 0.5) * WINDOW_WIDTH as f32 / START_BOXES as f32;
+        spawn_box(&mut world, &mut seed, (x, WINDOW_HEIGHT as f32 / 2.0));
+    }
 
     'mainloop: loop {
         for event in ctx.event_pump()?.poll_iter() {
@@ -56,84 +176,114 @@ fn main() -> Result<(), String> {
                 Event::KeyDown { keycode: Some(Keycode::Escape), .. } | Event::Quit { .. } => {
                     break 'mainloop
                 },
+                Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => {
+                    spawn_box(&mut world, &mut seed, (x as f32, y as f32));
+                },
+                Event::KeyDown { keycode: Some(Keycode::C), .. } => {
+                    world.bodies.clear();
+                },
+                Event::KeyDown { keycode: Some(Keycode::G), .. } => {
+                    show_cells = !show_cells;
+                },
+                Event::KeyDown { keycode: Some(Keycode::B), .. } => {
+                    // Changes to the next broad phase of the list
+                    let names = broadphase::NAMES;
+                    let index = names
+                        .iter()
+                        .position(|name| *name == broad_phase.name())
+                        .unwrap_or(0);
+                    let name = names[(index + 1) % names.len()];
+                    broad_phase = broadphase::from_name(name, CELL_SIZE)
+                        .ok_or(format!("Unknown broad phase: {}", name))?;
+                },
+                Event::KeyDown { keycode: Some(Keycode::Up), .. } => {
+                    world.restitution = (world.restitution + RESTITUTION_STEP).min(1.0);
+                },
+                Event::KeyDown { keycode: Some(Keycode::Down), .. } => {
+                    world.restitution = (world.restitution - RESTITUTION_STEP).max(0.0);
+                },
+                _ if screenshots.handle_event(&event) => {},
                 _ => {},
             }
         }
 
-        // The timer uses ticks to meassure the time (millisencods) that has 
-        // passed since the loop started
-        // Using this reference one can change the position of an object
-        // in order to simulate movement
-        if timer.ticks() as i32 % 1000 == 0 { 
-            box_position.x += STEP_SIZE as i32 * box_position.vel_x; 
-            box_position.y += STEP_SIZE as i32 * box_position.vel_y; 
-
-            // Changing the movement direction once the object is at the 
-            // horizontal edge of the window
-            if box_position.x < 0 {
-                box_position.vel_x = 1;
-
-            } else if (box_position.x + BOX_SIZE as i32) > WINDOW_WIDTH as i32 {
-                box_position.vel_x = -1;
-            }
+        // The world is updated a fixed number of times per second with the time of a
+        // step, so the speed of the boxes doesn't depend on how fast the frames are
+        // drawn
+        let dt = timestep.step().as_secs_f32();
+        for _ in 0..timestep.tick() {
+            world.step(dt, broad_phase.as_mut());
+        }
 
-            // Changing the movement direction once the object is at the 
-            // vertical edge of the window
-            if box_position.y < 0 {
-                box_position.vel_y = 1;
+        let cells = if show_cells { broad_phase.cells() } else { Vec::new() };
+        draw(&mut canvas, &world, timestep.alpha(), &cells)?;
 
-            } else if (box_position.y + BOX_SIZE as i32) > WINDOW_HEIGHT as i32 {
-                box_position.vel_y = -1;
-            }
+        screenshots.capture(&canvas);
+        canvas.present();
+        limiter.wait();
+
+        // The frame rate, the number of boxes, the restitution of the walls and the
+        // broad phase are shown in the title of the window
+        if let Some(report) = fps.frame() {
+            let title = format!(
+                "{} - {} boxes - restitution {:.1} - {} - {}",
+                WINDOW_NAME,
+                world.bodies.len(),
+                world.restitution,
+                broad_phase.name(),
+                report);
+            canvas
+                .window_mut()
+                .set_title(&title)
+                .map_err(|e| e.to_string())?;
         }
+    }
 
-        canvas.set_draw_color(Color::RGB(255, 255, 255));
-        canvas.clear();
+    Ok(())
+}
 
-        canvas
-            .with_texture_canvas(&mut box_target, |texture| {
-                texture.set_draw_color(Color::RGB(100, 100, 100));
-                texture.clear();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use broadphase::BroadPhase;
+    use common::golden::{self, Tolerance};
 
-                texture.set_draw_color(Color::RGB(255, 0, 0));
+    #[test]
+    fn test_draw() {
+        // Three boxes that have moved for half a second, drawn half way into the
+        // next step
+        let mut canvas = golden::surface_canvas(WINDOW_WIDTH, WINDOW_HEIGHT).unwrap();
 
-                // Drawing a line to show the direction of the box using the
-                // direction velocity. The line starts at the middle of the 
-                // box and continues all the way to the end of the box
-                let x_end = if box_position.vel_x > 0 {
-                    BOX_SIZE
-                } else {
-                    0
-                };
+        let mut world = World::new(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32, RESTITUTION);
+        world.spawn((100.0, 60.0), (-100.0, 50.0), 50.0);
+        world.spawn((400.0, 300.0), (80.0, -120.0), 20.0);
+        world.spawn((600.0, 450.0), (100.0, 100.0), 40.0);
+        world.step(0.5, &mut broadphase::BruteForce);
 
-                let y_end = if box_position.vel_y > 0 {
-                    BOX_SIZE
-                } else {
-                    0
-                };
+        // The cells of the spatial hash are drawn behind the boxes
+        let mut hash = broadphase::SpatialHash::new(CELL_SIZE);
+        let bounds: Vec<Aabb> = world.bodies.iter().map(|body| body.bounds()).collect();
+        hash.pairs(&bounds);
 
-                texture
-                    .draw_line(
-                        Point::new((BOX_SIZE/2) as i32, (BOX_SIZE/2) as i32),
-                        Point::new(x_end as i32, y_end as i32))
-                    .expect("No line drawn");
-
-            })
-            .map_err(|e| e.to_string())?;
-
-        canvas
-            .copy(
-                &box_target, 
-                None, 
-                Rect::new(
-                    box_position.x,
-                    box_position.y,
-                    BOX_SIZE,
-                    BOX_SIZE)
-                )?;
+        draw(&mut canvas, &world, 0.5, &hash.cells()).unwrap();
 
-        canvas.present();
+        let reference = golden::reference(env!("CARGO_MANIFEST_DIR"), "auto");
+        golden::assert_golden(&canvas, &reference, Tolerance::default());
     }
 
-    Ok(())
+    #[test]
+    fn test_spawn_box() {
+        let mut world = World::new(WINDOW_WIDTH as f32, WINDOW_HEIGHT as f32, RESTITUTION);
+        let mut seed = 0;
+        for _ in 0..100 {
+            spawn_box(&mut world, &mut seed, (320.0, 240.0));
+        }
+
+        // The boxes have sizes and speeds between the limits
+        for body in world.bodies.iter() {
+            let speed = body.velocity.0.hypot(body.velocity.1);
+            assert!(body.size >= BOX_SIZE.0 && body.size <= BOX_SIZE.1);
+            assert!(speed >= BOX_SPEED.0 - 0.01 && speed <= BOX_SPEED.1 + 0.01);
+        }
+    }
 }
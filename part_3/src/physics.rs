@@ -0,0 +1,271 @@
+use crate::broadphase::{Aabb, BroadPhase};
+
+// A small physics sandbox: square boxes that move with their velocity, bounce off
+// the walls of the window and off each other. The positions and velocities are
+// kept with decimals and moved with the time of each step, so the boxes move at the
+// same speed no matter how often the world is updated
+
+// The boxes bounce off each other without losing speed (elastic collisions)
+const BOX_RESTITUTION: f32 = 1.0;
+
+// A box of the world. The position is the top left corner, and the mass grows with
+// the area of the box, so the large boxes push the small ones
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Body {
+    pub position: (f32, f32),
+    pub previous: (f32, f32), // Position before the last step, to draw between two steps
+    pub velocity: (f32, f32),
+    pub size: f32,
+}
+
+impl Body {
+    pub fn new(position: (f32, f32), velocity: (f32, f32), size: f32) -> Self {
+        Body {
+            position,
+            previous: position,
+            velocity,
+            size,
+        }
+    }
+
+    pub fn mass(&self) -> f32 {
+        self.size * self.size
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (
+            self.position.0 + self.size / 2.0,
+            self.position.1 + self.size / 2.0,
+        )
+    }
+
+    // The position part of the way from the previous position to the current one
+    pub fn interpolate(&self, alpha: f32) -> (f32, f32) {
+        (
+            self.previous.0 + (self.position.0 - self.previous.0) * alpha,
+            self.previous.1 + (self.position.1 - self.previous.1) * alpha,
+        )
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        Aabb::new(
+            self.position.0,
+            self.position.1,
+            self.position.0 + self.size,
+            self.position.1 + self.size,
+        )
+    }
+
+    // Whether the box overlaps another one. The boxes that only touch don't overlap
+    pub fn overlaps(&self, other: &Body) -> bool {
+        self.bounds().overlaps(&other.bounds())
+    }
+}
+
+// The boxes inside of a rectangle that starts at the origin
+#[derive(Clone, Debug, PartialEq)]
+pub struct World {
+    pub bodies: Vec<Body>,
+    pub width: f32,
+    pub height: f32,
+    // Part of the speed that a box keeps when it bounces off a wall: 1 bounces
+    // without losing speed and 0 stops the box against the wall
+    pub restitution: f32,
+}
+
+impl World {
+    pub fn new(width: f32, height: f32, restitution: f32) -> Self {
+        World {
+            bodies: Vec::new(),
+            width,
+            height,
+            restitution,
+        }
+    }
+
+    // Adds a box with its center in a point. The box is moved inside of the world
+    // when the point is close to a wall
+    pub fn spawn(&mut self, center: (f32, f32), velocity: (f32, f32), size: f32) {
+        let x = (center.0 - size / 2.0).clamp(0.0, (self.width - size).max(0.0));
+        let y = (center.1 - size / 2.0).clamp(0.0, (self.height - size).max(0.0));
+        self.bodies.push(Body::new((x, y), velocity, size));
+    }
+
+    // Moves the world forward by a time in seconds. The broad phase finds the boxes
+    // that collide
+    pub fn step(&mut self, dt: f32, broad_phase: &mut dyn BroadPhase) {
+        for body in self.bodies.iter_mut() {
+            body.previous = body.position;
+            body.position.0 += body.velocity.0 * dt;
+            body.position.1 += body.velocity.1 * dt;
+        }
+
+        self.bounce_walls();
+
+        let bounds: Vec<Aabb> = self.bodies.iter().map(Body::bounds).collect();
+        for (a, b) in broad_phase.pairs(&bounds) {
+            self.collide(a, b);
+        }
+    }
+
+    // The boxes that leave the world are put back against the wall, and their speed
+    // towards the wall is turned around and reduced by the restitution
+    fn bounce_walls(&mut self) {
+        let (width, height, restitution) = (self.width, self.height, self.restitution);
+        let bounce = |position: &mut f32, velocity: &mut f32, size: f32, limit: f32| {
+            if *position < 0.0 {
+                *position = 0.0;
+                if *velocity < 0.0 {
+                    *velocity = -*velocity * restitution;
+                }
+            } else if *position + size > limit {
+                *position = (limit - size).max(0.0);
+                if *velocity > 0.0 {
+                    *velocity = -*velocity * restitution;
+                }
+            }
+        };
+
+        for body in self.bodies.iter_mut() {
+            bounce(&mut body.position.0, &mut body.velocity.0, body.size, width);
+            bounce(&mut body.position.1, &mut body.velocity.1, body.size, height);
+        }
+    }
+
+    // Separates two boxes that overlap along the side where they overlap the least,
+    // and changes their velocities along that side as an elastic collision. The
+    // momentum of the two boxes is kept. The boxes may have been moved apart by the
+    // collisions resolved before, so they are checked again
+    fn collide(&mut self, a: usize, b: usize) {
+        let (first, second) = (self.bodies[a], self.bodies[b]);
+        if !first.overlaps(&second) {
+            return;
+        }
+
+        let (center_a, center_b) = (first.center(), second.center());
+        let (dx, dy) = (center_b.0 - center_a.0, center_b.1 - center_a.1);
+        let reach = (first.size + second.size) / 2.0;
+        let overlap = (reach - dx.abs(), reach - dy.abs());
+
+        // The normal goes from the first box to the second one
+        let (normal, depth) = if overlap.0 < overlap.1 {
+            ((if dx < 0.0 { -1.0 } else { 1.0 }, 0.0), overlap.0)
+        } else {
+            ((0.0, if dy < 0.0 { -1.0 } else { 1.0 }), overlap.1)
+        };
+
+        // Each box is moved a part of the overlap, the light boxes more than the heavy
+        let inverse = (1.0 / first.mass(), 1.0 / second.mass());
+        let total = inverse.0 + inverse.1;
+        let push = (depth * inverse.0 / total, depth * inverse.1 / total);
+
+        let relative = (second.velocity.0 - first.velocity.0) * normal.0
+            + (second.velocity.1 - first.velocity.1) * normal.1;
+
+        let body = &mut self.bodies[a];
+        body.position.0 -= normal.0 * push.0;
+        body.position.1 -= normal.1 * push.0;
+        let body = &mut self.bodies[b];
+        body.position.0 += normal.0 * push.1;
+        body.position.1 += normal.1 * push.1;
+
+        // The boxes that are already going away from each other keep their velocities
+        if relative >= 0.0 {
+            return;
+        }
+
+        let impulse = -(1.0 + BOX_RESTITUTION) * relative / total;
+
+        let body = &mut self.bodies[a];
+        body.velocity.0 -= impulse * inverse.0 * normal.0;
+        body.velocity.1 -= impulse * inverse.0 * normal.1;
+        let body = &mut self.bodies[b];
+        body.velocity.0 += impulse * inverse.1 * normal.0;
+        body.velocity.1 += impulse * inverse.1 * normal.1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::broadphase::BruteForce;
+
+    fn close(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 0.001 && (a.1 - b.1).abs() < 0.001
+    }
+
+    #[test]
+    fn test_integrate() {
+        // A box goes the same distance in one long step as in many short ones
+        let mut world = World::new(1000.0, 1000.0, 1.0);
+        world.spawn((100.0, 100.0), (120.0, -60.0), 20.0);
+        let mut other = world.clone();
+
+        world.step(0.5, &mut BruteForce);
+        for _ in 0..50 {
+            other.step(0.01, &mut BruteForce);
+        }
+
+        assert!(close(world.bodies[0].position, (150.0, 60.0)));
+        assert!(close(other.bodies[0].position, world.bodies[0].position));
+        assert!(close(world.bodies[0].interpolate(0.5), (120.0, 75.0)));
+    }
+
+    #[test]
+    fn test_walls() {
+        let mut world = World::new(100.0, 100.0, 0.5);
+        world.spawn((90.0, 10.0), (100.0, -40.0), 20.0);
+
+        // The box is kept inside of the world and bounces with part of its speed
+        world.step(0.1, &mut BruteForce);
+        let body = world.bodies[0];
+        assert_eq!(body.position, (80.0, 0.0));
+        assert_eq!(body.velocity, (-50.0, 20.0));
+
+        // A box spawned on a wall is moved inside of the world
+        world.spawn((0.0, 100.0), (0.0, 0.0), 20.0);
+        assert_eq!(world.bodies[1].position, (0.0, 80.0));
+    }
+
+    #[test]
+    fn test_collisions() {
+        // Two boxes of the same size that hit head on swap their velocities
+        let mut world = World::new(1000.0, 1000.0, 1.0);
+        world.spawn((100.0, 100.0), (50.0, 0.0), 20.0);
+        world.spawn((125.0, 102.0), (-50.0, 0.0), 20.0);
+        world.step(0.1, &mut BruteForce);
+
+        let (a, b) = (world.bodies[0], world.bodies[1]);
+        assert!(close(a.velocity, (-50.0, 0.0)));
+        assert!(close(b.velocity, (50.0, 0.0)));
+        assert!(!a.overlaps(&b));
+
+        // A heavy box pushes a light one, and the momentum and the energy are kept
+        let mut world = World::new(1000.0, 1000.0, 1.0);
+        world.spawn((100.0, 100.0), (0.0, 80.0), 40.0);
+        world.spawn((100.0, 128.0), (0.0, 0.0), 20.0);
+        let momentum = |world: &World| {
+            world.bodies.iter().map(|body| body.mass() * body.velocity.1).sum::<f32>()
+        };
+        let energy = |world: &World| {
+            world.bodies.iter().map(|body| body.mass() * body.velocity.1.powi(2)).sum::<f32>()
+        };
+        let (before, energy_before) = (momentum(&world), energy(&world));
+        world.step(0.01, &mut BruteForce);
+
+        let (heavy, light) = (world.bodies[0], world.bodies[1]);
+        assert!(light.velocity.1 > heavy.velocity.1);
+        assert!(heavy.velocity.1 > 0.0);
+        assert!((momentum(&world) - before).abs() < 0.01 * before);
+        assert!((energy(&world) - energy_before).abs() < 0.01 * energy_before);
+        assert!(!heavy.overlaps(&light));
+
+        // Boxes that are going away from each other are only separated
+        let mut world = World::new(1000.0, 1000.0, 1.0);
+        world.spawn((100.0, 100.0), (-10.0, 0.0), 20.0);
+        world.spawn((110.0, 100.0), (10.0, 0.0), 20.0);
+        world.step(0.01, &mut BruteForce);
+        assert_eq!(world.bodies[0].velocity, (-10.0, 0.0));
+        assert_eq!(world.bodies[1].velocity, (10.0, 0.0));
+    }
+}
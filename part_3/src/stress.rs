@@ -0,0 +1,128 @@
+use std::time::{Duration, Instant};
+
+use crate::broadphase::{self, BroadPhase};
+use crate::physics::World;
+use crate::{spawn_box, BOX_SIZE, CELL_SIZE, RESTITUTION, UPDATE_RATE};
+
+// The stress tests move many boxes without a window, as fast as possible, and
+// measure the time of the updates. To keep up with the sandbox an update has to take
+// less than the time of a step, 1/60 of a second
+
+pub const STRESS_BOXES: usize = 10_000;
+
+// Seconds of the world moved by the headless mode
+const STRESS_SECONDS: u32 = 10;
+
+// Numbers of boxes of the benchmark, and the updates measured for each one
+const BENCH_COUNTS: [usize; 3] = [1_000, 5_000, 10_000];
+const BENCH_STEPS: u32 = 60;
+
+// A world large enough for a number of boxes. The boxes start in a grid, in cells a
+// little larger than the largest box so they don't overlap, and go in random
+// directions
+pub fn stress_world(count: usize) -> World {
+    let cell = BOX_SIZE.1 + 16.0;
+    let columns = (count as f32).sqrt().ceil().max(1.0) as usize;
+    let side = columns as f32 * cell;
+
+    let mut world = World::new(side, side, RESTITUTION);
+    let mut seed = 0;
+    for index in 0..count {
+        let (column, row) = (index % columns, index / columns);
+        let center = ((column as f32 + 0.5) * cell, (row as f32 + 0.5) * cell);
+        spawn_box(&mut world, &mut seed, center);
+    }
+
+    world
+}
+
+// The average and the longest time of a number of updates
+fn measure(
+    world: &mut World,
+    broad_phase: &mut dyn BroadPhase,
+    steps: u32,
+) -> (Duration, Duration) {
+    let dt = 1.0 / UPDATE_RATE as f32;
+    let mut worst = Duration::from_secs(0);
+    let start = Instant::now();
+
+    for _ in 0..steps {
+        let step_start = Instant::now();
+        world.step(dt, broad_phase);
+        worst = worst.max(step_start.elapsed());
+    }
+
+    (start.elapsed() / steps.max(1), worst)
+}
+
+fn millis(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
+
+// Moves a number of boxes for some seconds of the world and reports whether the
+// updates keep up with the update rate of the sandbox
+pub fn run(count: usize, broad_phase: &mut dyn BroadPhase) {
+    let mut world = stress_world(count);
+    let (average, worst) = measure(&mut world, broad_phase, STRESS_SECONDS * UPDATE_RATE);
+
+    let step = Duration::from_secs(1) / UPDATE_RATE;
+    println!(
+        "{} boxes, {} broad phase: {:.2} ms per update on average, {:.2} ms at worst",
+        count,
+        broad_phase.name(),
+        millis(average),
+        millis(worst)
+    );
+    println!(
+        "{:.0} updates per second, {} {} updates per second",
+        1.0 / average.as_secs_f64(),
+        if average <= step { "keeps up with" } else { "too slow for" },
+        UPDATE_RATE
+    );
+}
+
+// Compares the time of an update with each broad phase, for several numbers of boxes
+pub fn bench() {
+    println!("{:>8} {:>8} {:>12} {:>12}", "boxes", "phase", "average ms", "worst ms");
+
+    for count in BENCH_COUNTS.iter() {
+        for name in broadphase::NAMES.iter() {
+            let mut broad_phase = match broadphase::from_name(name, CELL_SIZE) {
+                Some(broad_phase) => broad_phase,
+                None => continue,
+            };
+
+            let mut world = stress_world(*count);
+            let (average, worst) = measure(&mut world, broad_phase.as_mut(), BENCH_STEPS);
+            println!("{:>8} {:>8} {:>12.2} {:>12.2}", count, name, millis(average), millis(worst));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stress_world() {
+        let world = stress_world(1000);
+        assert_eq!(world.bodies.len(), 1000);
+
+        // The boxes start inside of the world without overlapping
+        let bounds: Vec<_> = world.bodies.iter().map(|body| body.bounds()).collect();
+        assert!(broadphase::BruteForce.pairs(&bounds).is_empty());
+        assert!(world.bodies.iter().all(|body| {
+            body.position.0 >= 0.0
+                && body.position.1 >= 0.0
+                && body.position.0 + body.size <= world.width
+                && body.position.1 + body.size <= world.height
+        }));
+
+        // Every broad phase moves the world the same way
+        let mut other = world.clone();
+        let mut world = world;
+        measure(&mut world, &mut broadphase::BruteForce, 30);
+        measure(&mut other, &mut broadphase::QuadTree::new(8, 8), 30);
+        assert_eq!(world, other);
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use common::assets;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+// A sprite sheet is described by a text file next to its bitmap. The file gives
+// the size of the tiles, the number of characters (one per row of tiles) and the
+// animations, each one with the columns of its frames, the time each frame is
+// shown and whether it starts again after the last frame. The image can be any
+// format that can be loaded, and an old sheet drawn over a background color can
+// give that color as its color key, so the background isn't drawn:
+//
+//     image = characters.bmp
+//     tile = 32
+//     characters = 3
+//     color_key = 255 0 255
+//
+//     [walk]
+//     frames = 0 1 2 3
+//     duration = 100
+//     loop = true
+
+// An animation of the sheet. The frames are columns of the sheet, so the same
+// animation is used by every character
+#[derive(Clone, Debug, PartialEq)]
+pub struct Clip {
+    pub frames: Vec<u32>,
+    pub duration: Duration, // Time each frame is shown
+    pub looping: bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct SpriteSheet {
+    pub image: PathBuf,
+    pub tile_size: u32,
+    pub characters: u32,
+    pub color_key: Option<Color>,
+    pub clips: HashMap<String, Clip>,
+}
+
+impl SpriteSheet {
+    // The image is found in the folder of the descriptor
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut sheet = SpriteSheet::parse(&text)?;
+
+        if let Some(folder) = path.parent() {
+            sheet.image = folder.join(&sheet.image);
+        }
+
+        Ok(sheet)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut image = None;
+        let mut tile_size = None;
+        let mut characters = 1;
+        let mut color_key = None;
+        let mut clips = HashMap::new();
+
+        // Name of the animation of the section being read, and its values
+        let mut section: Option<String> = None;
+        let mut frames = Vec::new();
+        let mut duration = None;
+        let mut looping = true;
+
+        // The lines are read with an empty section at the end, so the last
+        // animation is stored like the others
+        for line in text.lines().map(str::trim).chain(std::iter::once("[]")) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line.starts_with('[') && line.ends_with(']') {
+                if let Some(name) = section.take() {
+                    if frames.is_empty() {
+                        return Err(format!("Animation without frames: {}", name));
+                    }
+
+                    let clip = Clip {
+                        frames: std::mem::take(&mut frames),
+                        duration: duration.take().ok_or(format!("Animation without duration: {}", name))?,
+                        looping,
+                    };
+                    clips.insert(name, clip);
+                    looping = true;
+                }
+
+                let name = line[1..line.len() - 1].trim();
+                if !name.is_empty() {
+                    section = Some(name.to_string());
+                }
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                Some(index) => (line[..index].trim(), line[index + 1..].trim()),
+                None => return Err(format!("Invalid sheet line: {}", line)),
+            };
+
+            match (section.is_some(), key) {
+                (false, "image") => image = Some(PathBuf::from(value)),
+                (false, "tile") => tile_size = Some(parse_number(value)?),
+                (false, "characters") => characters = parse_number(value)?,
+                (false, "color_key") => color_key = Some(assets::parse_color(value)?),
+                (true, "frames") => {
+                    frames = value
+                        .split_whitespace()
+                        .map(parse_number)
+                        .collect::<Result<Vec<u32>, String>>()?
+                }
+                (true, "duration") => {
+                    let millis = parse_number(value)?;
+                    if millis == 0 {
+                        return Err(String::from("The duration of a frame can't be zero"));
+                    }
+                    duration = Some(Duration::from_millis(millis as u64));
+                }
+                (true, "loop") => {
+                    looping = value
+                        .parse::<bool>()
+                        .map_err(|_| format!("Invalid loop value: {}", value))?
+                }
+                _ => return Err(format!("Unknown sheet setting: {}", key)),
+            }
+        }
+
+        Ok(SpriteSheet {
+            image: image.ok_or("The sheet has no image")?,
+            tile_size: tile_size.ok_or("The sheet has no tile size")?,
+            characters: characters.max(1),
+            color_key,
+            clips,
+        })
+    }
+
+    pub fn clip(&self, name: &str) -> Option<&Clip> {
+        self.clips.get(name)
+    }
+
+    // The rectangle of the sheet with a frame of a character
+    pub fn source(&self, character: u32, frame: u32) -> Rect {
+        Rect::new(
+            (frame * self.tile_size) as i32,
+            (character * self.tile_size) as i32,
+            self.tile_size,
+            self.tile_size)
+    }
+}
+
+fn parse_number(value: &str) -> Result<u32, String> {
+    value
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid number: {}", value))
+}
+
+// Plays a clip with the time that has passed, so the frames change at the same
+// speed no matter how often the game is drawn or the keys are pressed
+#[derive(Clone, Debug, PartialEq)]
+pub struct Animation {
+    clip: Clip,
+    elapsed: Duration,
+}
+
+impl Animation {
+    pub fn new(clip: &Clip) -> Self {
+        Animation {
+            clip: clip.clone(),
+            elapsed: Duration::from_secs(0),
+        }
+    }
+
+    // Changes the clip that is played. The clip starts from its first frame
+    // unless it is already being played
+    pub fn play(&mut self, clip: &Clip) {
+        if self.clip != *clip {
+            *self = Animation::new(clip);
+        }
+    }
+
+    pub fn update(&mut self, elapsed: Duration) {
+        self.elapsed += elapsed;
+    }
+
+    // Index of the frame in the clip. A clip that doesn't loop stays in its
+    // last frame
+    fn index(&self) -> usize {
+        let count = self.clip.frames.len();
+        let index = (self.elapsed.as_millis() / self.clip.duration.as_millis()) as usize;
+
+        if self.clip.looping {
+            index % count
+        } else {
+            index.min(count - 1)
+        }
+    }
+
+    // Column of the sheet with the frame that has to be shown
+    pub fn frame(&self) -> u32 {
+        self.clip.frames[self.index()]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = "
+        # A sheet with two animations
+        image = characters.bmp
+        tile = 32
+        characters = 3
+        color_key = 255 0 255
+
+        [idle]
+        frames = 0 1
+        duration = 400
+
+        [attack]
+        frames = 2 3
+        duration = 100
+        loop = false
+    ";
+
+    #[test]
+    fn test_parse() {
+        let sheet = SpriteSheet::parse(SHEET).unwrap();
+        assert_eq!(sheet.image, PathBuf::from("characters.bmp"));
+        assert_eq!(sheet.tile_size, 32);
+        assert_eq!(sheet.characters, 3);
+        assert_eq!(sheet.color_key, Some(Color::RGB(255, 0, 255)));
+        assert_eq!(sheet.clips.len(), 2);
+
+        let attack = sheet.clip("attack").unwrap();
+        assert_eq!(attack.frames, vec![2, 3]);
+        assert_eq!(attack.duration, Duration::from_millis(100));
+        assert!(!attack.looping);
+        assert!(sheet.clip("idle").unwrap().looping);
+
+        assert_eq!(sheet.source(1, 2), Rect::new(64, 32, 32, 32));
+
+        assert!(SpriteSheet::parse("tile = 32").is_err());
+        assert_eq!(SpriteSheet::parse("image = a.png\ntile = 32").unwrap().color_key, None);
+        assert!(SpriteSheet::parse("image = a.bmp\ntile = 32\ncolor_key = 1 2").is_err());
+        assert!(SpriteSheet::parse("image = a.bmp\ntile = 32\n[walk]\nduration = 100").is_err());
+        assert!(SpriteSheet::parse("image = a.bmp\ntile = 32\n[walk]\nframes = 0\nduration = 0").is_err());
+        assert!(SpriteSheet::parse("image = a.bmp\ntile = 32\nframes = 0").is_err());
+    }
+
+    #[test]
+    fn test_animation() {
+        let sheet = SpriteSheet::parse(SHEET).unwrap();
+        let mut animation = Animation::new(sheet.clip("idle").unwrap());
+        assert_eq!(animation.frame(), 0);
+
+        animation.update(Duration::from_millis(399));
+        assert_eq!(animation.frame(), 0);
+        animation.update(Duration::from_millis(1));
+        assert_eq!(animation.frame(), 1);
+
+        // A looping clip starts again after its last frame
+        animation.update(Duration::from_millis(400));
+        assert_eq!(animation.frame(), 0);
+
+        // Playing the same clip doesn't restart it
+        animation.update(Duration::from_millis(400));
+        animation.play(sheet.clip("idle").unwrap());
+        assert_eq!(animation.frame(), 1);
+
+        // A clip that doesn't loop stops in its last frame
+        animation.play(sheet.clip("attack").unwrap());
+        assert_eq!(animation.frame(), 2);
+        animation.update(Duration::from_millis(500));
+        assert_eq!(animation.frame(), 3);
+    }
+}
@@ -0,0 +1,78 @@
+use sdl2::rect::Rect;
+
+// The part of the world that is shown in the window. The world is drawn moved by
+// the position of the camera, so the objects keep their positions in the world
+// and only the camera changes when the player walks
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Camera {
+    pub fn new(width: u32, height: u32) -> Self {
+        Camera {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        }
+    }
+
+    // Puts a point of the world in the center of the camera. The camera is kept
+    // inside the world, so the player is only in the center away from the edges.
+    // A world smaller than the camera is shown in the center of the window
+    pub fn follow(&mut self, target: (f32, f32), world: (u32, u32)) {
+        let axis = |target: f32, view: u32, world: u32| {
+            if world <= view {
+                return -((view - world) as i32 / 2);
+            }
+
+            let position = target.round() as i32 - view as i32 / 2;
+            position.clamp(0, (world - view) as i32)
+        };
+
+        self.x = axis(target.0, self.width, world.0);
+        self.y = axis(target.1, self.height, world.1);
+    }
+
+    // The rectangle of the world that is shown
+    pub fn view(self) -> Rect {
+        Rect::new(self.x, self.y, self.width, self.height)
+    }
+
+    // Moves a rectangle of the world to the window
+    pub fn to_screen(self, rect: Rect) -> Rect {
+        Rect::new(rect.x() - self.x, rect.y() - self.y, rect.width(), rect.height())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_follow() {
+        let mut camera = Camera::new(640, 480);
+
+        // In the middle of the world the target is in the center of the camera
+        camera.follow((1000.0, 500.0), (2560, 960));
+        assert_eq!((camera.x, camera.y), (680, 260));
+        assert_eq!(
+            camera.to_screen(Rect::new(1000, 500, 10, 10)),
+            Rect::new(320, 240, 10, 10)
+        );
+
+        // Near the edges the camera stays inside the world
+        camera.follow((10.0, 950.0), (2560, 960));
+        assert_eq!((camera.x, camera.y), (0, 480));
+        camera.follow((2550.0, 10.0), (2560, 960));
+        assert_eq!((camera.x, camera.y), (1920, 0));
+
+        // A world narrower than the camera is centered
+        camera.follow((100.0, 100.0), (440, 960));
+        assert_eq!(camera.x, -100);
+    }
+}
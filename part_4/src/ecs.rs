@@ -0,0 +1,180 @@
+use crate::animation::Animation;
+use crate::physics::Body;
+
+// A small entity component system. An entity is only a number, and its parts are
+// kept in one storage for each kind of component, at the index of the entity. The
+// systems go through the entities that have the components they need, so the
+// player and the other characters are moved and drawn by the same code:
+//
+//     let npc = world.spawn();
+//     world.bodies.insert(npc, Body::new(position, size));
+//     world.controllers.insert(npc, Controller::Wander { direction: 1.0, timer: 2.0 });
+pub type Entity = usize;
+
+// The components of one kind. An entity without the component has None in its place
+#[derive(Clone, Debug, PartialEq)]
+pub struct Storage<T> {
+    items: Vec<Option<T>>,
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Storage { items: Vec::new() }
+    }
+}
+
+impl<T> Storage<T> {
+    pub fn insert(&mut self, entity: Entity, item: T) {
+        if entity >= self.items.len() {
+            self.items.resize_with(entity + 1, || None);
+        }
+
+        self.items[entity] = Some(item);
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.items.get(entity).and_then(Option::as_ref)
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.items.get_mut(entity).and_then(Option::as_mut)
+    }
+}
+
+// How an entity is drawn: the row of the sheet with its character, how many times
+// larger than the tiles of the sheet it is, and whether it looks to the left. The
+// sprites with a larger z are drawn in front of the others
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sprite {
+    pub character: u32,
+    pub scale: u32,
+    pub flipped: bool,
+    pub z: i32,
+}
+
+// What decides where an entity goes
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Controller {
+    Player, // The keys that are held
+    // Walks to a side or stands still for a while, and turns around at the walls
+    // and the ledges
+    Wander { direction: f32, timer: f32 },
+    // Walks towards another entity until it is closer than a distance
+    Follow { target: Entity, distance: f32 },
+}
+
+// The keys that are held in a frame, or what a controller does as if it held keys
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Input {
+    pub x: f32, // -1 to go to the left, 1 to go to the right
+    pub jump: bool,
+    pub down: bool,
+}
+
+// The state of the jumps of an entity: the time left to jump after leaving the
+// ground and after the jump key has been pressed, whether the key was held in the
+// last frame, to know when it is pressed, and whether the entity is going up in a
+// jump that can still be cut
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Jump {
+    pub coyote: f32,
+    pub buffer: f32,
+    pub held: bool,
+    pub jumping: bool,
+}
+
+// All the entities of the game and their components. The body of an entity keeps
+// its position and its velocity, because the physics needs both of them
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct World {
+    count: usize,
+    pub bodies: Storage<Body>,
+    pub sprites: Storage<Sprite>,
+    pub animations: Storage<Animation>,
+    pub controllers: Storage<Controller>,
+    pub inputs: Storage<Input>,
+    pub jumps: Storage<Jump>,
+    pub starts: Storage<(f32, f32)>, // Where the entity starts again after falling out of the level
+    pub seed: u32,                   // State of the random numbers used by the controllers
+}
+
+impl World {
+    pub fn new() -> Self {
+        World::default()
+    }
+
+    // A new entity without components
+    pub fn spawn(&mut self) -> Entity {
+        self.count += 1;
+        self.count - 1
+    }
+
+    pub fn entities(&self) -> impl Iterator<Item = Entity> {
+        0..self.count
+    }
+
+    // The entities with a sprite and a body in the order they are drawn: by their z,
+    // and the ones with the same z from the top of the screen to the bottom, so the
+    // lower feet are drawn in front
+    pub fn drawing_order(&self) -> Vec<Entity> {
+        let mut order: Vec<(i32, f32, Entity)> = self
+            .entities()
+            .filter_map(|entity| match (self.sprites.get(entity), self.bodies.get(entity)) {
+                (Some(sprite), Some(body)) => Some((sprite.z, body.bottom(), entity)),
+                _ => None,
+            })
+            .collect();
+
+        order.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+        order.into_iter().map(|(_, _, entity)| entity).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sprite(z: i32) -> Sprite {
+        Sprite {
+            character: 0,
+            scale: 1,
+            flipped: false,
+            z,
+        }
+    }
+
+    #[test]
+    fn test_storage() {
+        let mut storage = Storage::default();
+        storage.insert(3, "three");
+        assert_eq!(storage.get(3), Some(&"three"));
+        assert_eq!(storage.get(1), None);
+        assert_eq!(storage.get(10), None);
+
+        *storage.get_mut(3).unwrap() = "changed";
+        assert_eq!(storage.get(3), Some(&"changed"));
+        assert_eq!(storage.get_mut(1), None);
+    }
+
+    #[test]
+    fn test_drawing_order() {
+        let mut world = World::new();
+        let front = world.spawn();
+        let low = world.spawn();
+        let high = world.spawn();
+        let hidden = world.spawn();
+        assert_eq!(world.entities().count(), 4);
+
+        world.sprites.insert(front, sprite(1));
+        world.bodies.insert(front, Body::new((0.0, 0.0), (10.0, 10.0)));
+        world.sprites.insert(low, sprite(0));
+        world.bodies.insert(low, Body::new((0.0, 50.0), (10.0, 10.0)));
+        world.sprites.insert(high, sprite(0));
+        world.bodies.insert(high, Body::new((0.0, 20.0), (10.0, 10.0)));
+
+        // An entity without a sprite is not drawn
+        world.bodies.insert(hidden, Body::new((0.0, 0.0), (10.0, 10.0)));
+
+        assert_eq!(world.drawing_order(), vec![high, low, front]);
+    }
+}
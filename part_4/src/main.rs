@@ -1,220 +1,252 @@
 use std::path::Path;
+use std::time::Instant;
 
+use common::assets::{self, Assets, SearchPath};
+use common::screenshot::Screenshots;
+use common::timing::{FpsCounter, FrameLimiter};
 use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use sdl2::keyboard::{KeyboardState, Keycode, Scancode};
+use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use std::time::Duration;
+use sdl2::render::{Canvas, RenderTarget, Texture, TextureCreator};
+
+mod animation;
+mod camera;
+mod ecs;
+mod physics;
+mod systems;
+mod tilemap;
+use animation::{Animation, SpriteSheet};
+use camera::Camera;
+use ecs::{Controller, Entity, Input, Sprite, World};
+use physics::Body;
+use tilemap::TileMap;
 
 const WINDOW_HEIGHT: u32 = 480;
 const WINDOW_WIDTH: u32 = 640;
+const WINDOW_NAME: &str = "SDL2";
 
-// These constansts should be part of the Player struct, but since they are
-// not going to be changed at run time it makes sense to keep them as const
-const FILE_NAME: &str = "assets/characters.bmp";
-const FRAMES_PER_ANIM: i32 = 4;
-const NUMBER_CHARACTERS: i32 = 3;
-const SPRITE_TILE_SIZE: u32 = 32;
-const STEP_SIZE: i32 = 10;
-
-// This example uses a bitmap located in the folder assets. The bitmap has 12 drawings that
-// corresponds to different characters (NUMBER_CHARACTERS) and their possible animations
-// (FRAMES_PER_ANIM). Each position is bounded by a box os 32 by 32 pixels (SPRITE_TILE_SIZE).
-
-// For this example, all the logic is placed in a Player struct
-// This will allow to move the code outside the main function and start
-// thinking about modules in the game development
-struct Player {
-    // The struct player will store two rectangles that will be used
-    // to track from where to extract the sprites that correspond to the movement
-    // of the player and another rectangle to keep track of the position of the
-    // player within the window. These attributes could be a Rect struct but
-    // it is simpler to keep track of the data using tuples for this example
-    source: (i32, i32),
-    destination: (i32, i32),
-    // The direction attribute is used to indicate if the sprite has to be
-    // flipped. This is one of the attributes that can be used when copying the
-    // texture to the canvas
-    direction: bool,
-    // The size_increment is used to scale the original size of the sprite. This is
-    // done by creating a destination rectangle that is n times larger than the original
-    // source rectangle
-    size_incrememt: i32,
-}
+// The movement of the player depends on the time between frames and not on the
+// number of frames, so the frames are only limited to save work
+const FRAME_RATE: u32 = 60;
 
-impl Player {
-    fn new() -> Self {
-        Player {
-            source: (0, 0), 
-            destination: (0, 100),
-            direction: false,
-            size_incrememt: 4,
-        }
-    }
 
-    fn change_character(&mut self) {
-        // Using the space bar one can change the character that
-        // is shown in the window. Using the number of characters one can cycle
-        // through the different character positions in the bitmap.
+// These constansts are not going to be changed at run time, so it makes sense to
+// keep them as const
+const SHEET_NAME: &str = "assets/characters.sheet";
+const LEVEL_NAME: &str = "assets/forest.level";
 
-        // By updating the y coordinate in the source source box, then a different
-        // section of the texture can be copied to the canvas
-        self.source.1 = (self.source.1 + SPRITE_TILE_SIZE as i32) % (SPRITE_TILE_SIZE as i32 * NUMBER_CHARACTERS);
-    }
+// Color of the parts of the level without tiles
+const SKY: Color = Color::RGB(110, 170, 220);
 
-    fn change_frame(&mut self) {
-        // By using the same logic as change character, the movement frames are
-        // selected by changing the source rectangle horizontaly
-        self.source.0 = (self.source.0 + SPRITE_TILE_SIZE as i32) % (SPRITE_TILE_SIZE as i32 * FRAMES_PER_ANIM);
-    }
+// Position in the level where the player starts, the middle of its feet
+const START: (f32, f32) = (96.0, 576.0);
 
-    fn move_right(&mut self) {
+// The player is drawn four times larger than the tiles of the sheet and in front of
+// the other characters, which are a little smaller
+const PLAYER_SCALE: u32 = 4;
+const NPC_SCALE: u32 = 3;
 
-        // Change to the next frame in animation
-        self.change_frame();
+// The characters that follow the player stop at this distance from it
+const FOLLOW_DISTANCE: f32 = 96.0;
 
-        // The horizontal destination is controlled by changing the horizontal values
-        // in the destination box. In order to avoid a character to be drawn outside
-        // the window, the actual position is compared with the window borders and the 
-        // new value depends on whether the character is outside the window or not.
-        self.destination.0 = if (self.destination.0 + (SPRITE_TILE_SIZE as i32 * self.size_incrememt)) > WINDOW_WIDTH as i32 {
-            (WINDOW_WIDTH - (SPRITE_TILE_SIZE * self.size_incrememt as u32)) as i32
-        } else {
-            self.destination.0 + STEP_SIZE
-        };
+// This example uses a bitmap located in the folder assets. The bitmap has drawings that
+// correspond to different characters (one per row) and the frames of their animations
+// (one per column). The size of the tiles, the number of characters and the animations
+// are read from the sheet file next to the bitmap (SHEET_NAME), so they can be changed
+// without building the game again.
 
-        // Since all the sprites are facing to the right, there is no need to change
-        // the directions
-        self.direction = false;
-    }
-
-    fn move_left(&mut self) {
+// Every character of the level is an entity of the world with a body, a sprite, an
+// animation and a controller, so the player and the characters moved by the computer
+// are moved, animated and drawn by the same systems. The entity of the player is the
+// one with the Player controller, and it is returned to follow it with the camera
+fn spawn_characters(world: &mut World, sheet: &SpriteSheet) -> Result<Entity, String> {
+    let sprite = |character: u32, scale: u32, z: i32| Sprite {
+        character,
+        scale,
+        flipped: false,
+        z,
+    };
 
-        // Change to the next frame in animation
-        self.change_frame();
+    let player = systems::spawn_character(
+        world, sheet, START, sprite(0, PLAYER_SCALE, 1), Controller::Player)?;
 
-        // The horizontal destination is controlled by changing the horizontal values
-        // in the destination box. In order to avoid a character to be drawn outside
-        // the window, the actual position is compared with the window borders and the 
-        // new value depends on whether the character is outside the window or not.
-        self.destination.0 = if (self.destination.0 - self.size_incrememt as i32) < 0 {
-            0
-        } else {
-            self.destination.0 - STEP_SIZE
-        };
-
-        // In this case it is necessary to flip the sprite
-        self.direction = true;
-    }
+    // A friend that walks behind the player, and two characters that walk around the
+    // hill and the end of the level
+    let follow = Controller::Follow { target: player, distance: FOLLOW_DISTANCE };
+    systems::spawn_character(world, sheet, (320.0, 576.0), sprite(1, NPC_SCALE, 0), follow)?;
 
-    fn move_up(&mut self) {
-        self.change_frame();
+    let wander = Controller::Wander { direction: 0.0, timer: 1.0 };
+    systems::spawn_character(world, sheet, (1056.0, 448.0), sprite(2, NPC_SCALE, 0), wander)?;
 
-        // Same logic as moving left or right, only changing the Y coordinate
-        self.destination.1 = if (self.destination.1 - self.size_incrememt as i32) < 0 {
-            0
-        } else {
-            self.destination.1 - STEP_SIZE
-        };
-    }
-
-    fn move_down(&mut self) {
-        self.change_frame();
-
-        // Same logic as moving left or right, only changing the Y coordinate
-        self.destination.1 = if (self.destination.1 + (SPRITE_TILE_SIZE as i32 * self.size_incrememt)) > WINDOW_HEIGHT as i32 {
-            (WINDOW_HEIGHT - (SPRITE_TILE_SIZE * self.size_incrememt as u32)) as i32
-        } else {
-            self.destination.1 + STEP_SIZE
-        };
-    }
-
-    fn create_source(&self) -> Rect {
-        // Creates the source rectangle from where the canvas will extract the
-        // sprites. This rectangle will be linked to the surface that is going to be
-        // created with the bitmap
-        Rect::new(
-            self.source.0, 
-            self.source.1, 
-            SPRITE_TILE_SIZE, 
-            SPRITE_TILE_SIZE)
-    }
-
-    fn create_destination(&self) -> Rect {
-        // Creates the destination rectangle. In this destination rectangle the
-        // source data will be copied. If the destination rectangle is larget than
-        // the source rectangle, then the canvas will fill up the area. By using
-        // this property one can play with different sizes for the same object.
-        Rect::new(
-            self.destination.0, 
-            self.destination.1, 
-            SPRITE_TILE_SIZE * self.size_incrememt as u32, 
-            SPRITE_TILE_SIZE * self.size_incrememt as u32)
-    }
+    let wander = Controller::Wander { direction: 0.0, timer: 2.0 };
+    systems::spawn_character(world, sheet, (2016.0, 576.0), sprite(1, NPC_SCALE, 0), wander)?;
 
-    fn make_larger(&mut self) {
-        // Just playing with the size of the character
-        self.size_incrememt += 1;
-    }
-
-    fn make_smaller(&mut self) {
-        self.size_incrememt = if (self.size_incrememt - 1) < 0 {
-            0
-        } else {
-            self.size_incrememt - 1
-        }
-    }
+    Ok(player)
 }
 
 // Function to manage all the events that happend in the window
 // It makes sense to have an external function that manages these events
 // because it can be linked to a general game object that keeps track of all the
 // ojects and textures in the canvas
-fn manage_events(running: &mut bool, event_pump: &mut sdl2::EventPump, player: &mut Player) {
+fn manage_events(
+    running: &mut bool,
+    event_pump: &mut sdl2::EventPump,
+    world: &mut World,
+    player: Entity,
+    sheet: &SpriteSheet,
+    screenshots: &mut Screenshots,
+) {
     for event in event_pump.poll_iter() {
+        // The keys change the sprite of the player
+        let sprite = match world.sprites.get_mut(player) {
+            Some(sprite) => sprite,
+            None => return,
+        };
+
         match event {
             Event::Quit { .. } | Event::KeyDown { keycode: Some(Keycode::Escape), ..  } => {
                 *running = false;
             },
             Event::KeyDown { keycode: Some(Keycode::Space), ..  } => {
-                player.change_character();
+                // Using the space bar one can change the character that
+                // is shown in the window. Using the number of characters one can cycle
+                // through the different character rows in the bitmap.
+                sprite.character = (sprite.character + 1) % sheet.characters;
 
             },
-            Event::KeyDown { keycode: Some(Keycode::Right), ..  } => {
-                player.move_right();
+            Event::KeyDown { keycode: Some(Keycode::V), ..  } => {
+                // Just playing with the size of the character
+                sprite.scale += 1;
 
             },
-            Event::KeyDown { keycode: Some(Keycode::Left), ..  } => {
-                player.move_left();
+            Event::KeyDown { keycode: Some(Keycode::B), ..  } => {
+                sprite.scale = sprite.scale.saturating_sub(1);
 
             },
-            Event::KeyDown { keycode: Some(Keycode::Up), ..  } => {
-                player.move_up();
+            _ if screenshots.handle_event(&event) => {},
+            _ => {}
+        }
+    }
+}
 
-            },
-            Event::KeyDown { keycode: Some(Keycode::Down), ..  } => {
-                player.move_down();
 
-            },
-            Event::KeyDown { keycode: Some(Keycode::V), ..  } => {
-                player.make_larger();
+// The keyboard is read every frame instead of waiting for the key events, so the
+// player moves while the keys are held and the jump can be held to jump higher.
+// The up arrow and Z jump, and the down arrow drops through the platforms
+fn read_input(keyboard: &KeyboardState) -> Input {
+    let pressed = |key: Scancode| keyboard.is_scancode_pressed(key);
 
-            },
-            Event::KeyDown { keycode: Some(Keycode::B), ..  } => {
-                player.make_smaller();
+    Input {
+        x: pressed(Scancode::Right) as i32 as f32 - pressed(Scancode::Left) as i32 as f32,
+        jump: pressed(Scancode::Up) || pressed(Scancode::Z),
+        down: pressed(Scancode::Down),
+    }
+}
 
-            },
-            _ => {}
+// Loads the image of the sheet or the tileset of the level, leaving out the color key
+// that the file using it gives. The same loader is used when the images change
+fn load_texture<'a, C>(
+    creator: &'a TextureCreator<C>,
+    path: &Path,
+    sheet: &SpriteSheet,
+    map: &TileMap,
+) -> Result<Texture<'a>, String> {
+    let color_key = if path == map.tileset {
+        map.color_key
+    } else {
+        sheet.color_key
+    };
+
+    assets::load_keyed_texture(creator, path, color_key)
+}
+
+// Draws the tiles of the level that can be seen by the camera. The map can be much
+// larger than the window, so the rest of the tiles are not even looked at
+fn draw_map<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    tileset: &Texture,
+    map: &TileMap,
+    camera: &Camera,
+) -> Result<(), String> {
+    let columns = tileset.query().width / map.tile_size;
+    let (visible_columns, visible_rows) = map.visible(camera.view());
+
+    for row in visible_rows {
+        for column in visible_columns.clone() {
+            if let Some(tile) = map.tile(column, row) {
+                canvas.copy(
+                    tileset,
+                    map.source(tile, columns),
+                    camera.to_screen(map.cell_rect(column, row)))?;
+            }
         }
     }
+
+    Ok(())
+}
+
+// Draws the level and the characters in the canvas
+fn draw<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    tileset: &Texture,
+    map: &TileMap,
+    texture: &Texture,
+    sheet: &SpriteSheet,
+    world: &World,
+    camera: &Camera,
+) -> Result<(), String> {
+    canvas.set_draw_color(SKY);
+    canvas.clear();
+
+    draw_map(canvas, tileset, map, camera)?;
+
+    // The characters are drawn in their z order, so the ones in front are drawn last
+    for entity in world.drawing_order() {
+        let (sprite, body) = match (world.sprites.get(entity), world.bodies.get(entity)) {
+            (Some(sprite), Some(body)) => (sprite, body),
+            _ => continue,
+        };
+        let frame = world.animations.get(entity).map_or(0, Animation::frame);
+
+        // copy the texture to the canvas, in the place of the character in the window.
+        // The row of the source is the character and the column is the frame of the
+        // animation
+        canvas.copy_ex(
+            texture,
+            sheet.source(sprite.character, frame),
+            camera.to_screen(create_destination(sprite, body, sheet)),
+            0.0,
+            None,
+            sprite.flipped,
+            false,
+        )?;
+    }
+
+    Ok(())
 }
 
+fn create_destination(sprite: &Sprite, body: &Body, sheet: &SpriteSheet) -> Rect {
+    // Creates the destination rectangle in the world. In this destination
+    // rectangle the source data will be copied. If the destination rectangle is
+    // larget than the source rectangle, then the canvas will fill up the area. By
+    // using this property one can play with different sizes for the same object.
+    // The sprite is drawn around the body, with the same bottom and middle
+    let size = systems::sprite_size(sprite, sheet);
+    let (x, _) = body.center();
+    Rect::new(
+        (x - size as f32 / 2.0).round() as i32,
+        (body.bottom() - size as f32).round() as i32,
+        size,
+        size)
+}
 
 fn main() -> Result<(), String> {
     let ctx = sdl2::init()?;
     let video = ctx.video()?;
 
     let window = video
-        .window("SDL2", WINDOW_WIDTH, WINDOW_HEIGHT)
+        .window(WINDOW_NAME, WINDOW_WIDTH, WINDOW_HEIGHT)
         .position_centered()
         .build()
         .map_err(|e| e.to_string())?;
@@ -227,39 +259,132 @@ fn main() -> Result<(), String> {
 
     // animation sheet and extras are available from
     // https://opengameart.org/content/a-platformer-in-the-forest
-    let temp_surface = sdl2::surface::Surface::load_bmp(Path::new(FILE_NAME))?;
+    //
+    // The sheet and the level are found next to the executable, or in the folders above
+    // it, so the game can be started from any folder. The files are loaded again when
+    // they change while the game is running
+    let mut sheets = Assets::new(SearchPath::from_exe());
+    let sheet = sheets.load(SHEET_NAME, SpriteSheet::load)?;
+
+    let mut levels = Assets::new(SearchPath::from_exe());
+    let level = levels.load(LEVEL_NAME, TileMap::load)?;
+
+    // The layer with the tiles of the level is watched too, so a map exported again
+    // from Tiled is loaded while the game is running
+    let mut layers = Assets::new(SearchPath::from_exe());
+    if let Some(path) = &levels.get(level).layer {
+        layers.load(path, assets::load_text)?;
+    }
 
     let texture_creator = canvas.texture_creator();
-    let texture = texture_creator
-        .create_texture_from_surface(&temp_surface)
-        .map_err(|e| e.to_string())?;
-
-    let mut player = Player::new();
+    let mut textures = Assets::new(SearchPath::from_exe());
+    let loader = |path: &Path| load_texture(&texture_creator, path, sheets.get(sheet), levels.get(level));
+    let texture = textures.load(&sheets.get(sheet).image, loader)?;
+    let tileset = textures.load(&levels.get(level).tileset, loader)?;
+
+    let mut world = World::new();
+    let player = spawn_characters(&mut world, sheets.get(sheet))?;
+    let mut camera = Camera::new(WINDOW_WIDTH, WINDOW_HEIGHT);
     let mut running = true;
     let mut event_pump = ctx.event_pump()?;
 
+    let mut screenshots = Screenshots::default();
+
+    let mut limiter = FrameLimiter::new(Some(FRAME_RATE));
+    let mut fps = FpsCounter::default();
+    let mut last_frame = Instant::now();
+
     while running {
 
-        manage_events(&mut running, &mut event_pump, &mut player);
+        manage_events(&mut running, &mut event_pump, &mut world, player, sheets.get(sheet), &mut screenshots);
 
-        canvas.set_draw_color(sdl2::pixels::Color::RGBA(0, 0, 0, 255));
-        canvas.clear();
+        let keys = read_input(&event_pump.keyboard_state());
 
-        // copy the texture to the canvas
-        canvas.copy_ex(
-            &texture,
-            player.create_source(),
-            player.create_destination(),
-            0.0,
-            None,
-            player.direction,
-            false,
+        // The characters are moved with the time since the last frame, so they move at
+        // the same speed no matter how many frames are drawn per second
+        let now = Instant::now();
+        let map = levels.get(level);
+        systems::update(&mut world, sheets.get(sheet), map, keys, now - last_frame);
+        last_frame = now;
+
+        // The camera follows the player without leaving the level
+        let target = world.bodies.get(player).map_or(START, Body::center);
+        camera.follow(target, map.pixel_size());
+
+        sheets.poll(SpriteSheet::load);
+        levels.poll(TileMap::load);
+        if !layers.poll(assets::load_text).is_empty() {
+            match TileMap::load(levels.path(level)) {
+                Ok(map) => *levels.get_mut(level) = map,
+                Err(e) => println!("Unable to reload {}: {}", levels.path(level).display(), e),
+            }
+        }
+        textures.poll(|path| load_texture(&texture_creator, path, sheets.get(sheet), levels.get(level)));
+
+        draw(
+            &mut canvas,
+            textures.get(tileset),
+            levels.get(level),
+            textures.get(texture),
+            sheets.get(sheet),
+            &world,
+            &camera,
         )?;
 
+        screenshots.capture(&canvas);
         canvas.present();
-
-        std::thread::sleep(Duration::from_millis(100));
+        limiter.wait();
+
+        // The frame rate is shown in the title of the window
+        if let Some(report) = fps.frame() {
+            canvas
+                .window_mut()
+                .set_title(&format!("{} - {}", WINDOW_NAME, report))
+                .map_err(|e| e.to_string())?;
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::golden::{self, Tolerance};
+    use std::time::Duration;
+
+    #[test]
+    fn test_draw() {
+        let mut canvas = golden::surface_canvas(WINDOW_WIDTH, WINDOW_HEIGHT).unwrap();
+        let creator = canvas.texture_creator();
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(SHEET_NAME);
+        let sheet = SpriteSheet::load(&path).unwrap();
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join(LEVEL_NAME);
+        let map = TileMap::load(&path).unwrap();
+        let texture = load_texture(&creator, &sheet.image, &sheet, &map).unwrap();
+        let tileset = load_texture(&creator, &map.tileset, &sheet, &map).unwrap();
+
+        // A player that has changed its character and is walking to the left on the
+        // grass after the hill, in the fourth frame of the walk
+        let mut world = World::new();
+        let player = spawn_characters(&mut world, &sheet).unwrap();
+        world.sprites.get_mut(player).unwrap().character = 1;
+        systems::place(&mut world, &sheet, player, (1500.0, 576.0));
+        let left = Input { x: -1.0, ..Input::default() };
+        for _ in 0..3 {
+            systems::update(&mut world, &sheet, &map, left, Duration::from_millis(100));
+        }
+
+        let body = world.bodies.get(player).unwrap();
+        assert!(body.on_ground && body.velocity.0 < 0.0);
+        assert_eq!(world.animations.get(player).unwrap().frame(), 3);
+
+        let mut camera = Camera::new(WINDOW_WIDTH, WINDOW_HEIGHT);
+        camera.follow(world.bodies.get(player).unwrap().center(), map.pixel_size());
+
+        draw(&mut canvas, &tileset, &map, &texture, &sheet, &world, &camera).unwrap();
+
+        let reference = golden::reference(env!("CARGO_MANIFEST_DIR"), "sprites");
+        golden::assert_golden(&canvas, &reference, Tolerance::default());
+    }
+}
@@ -0,0 +1,362 @@
+use crate::tilemap::{Collision, TileMap};
+
+// Small distance so a body that touches a tile isn't taken as inside of it
+const EPSILON: f32 = 0.01;
+
+// A box that moves in the world and collides with the tiles of a map
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Body {
+    pub position: (f32, f32), // Top left corner of the box
+    pub velocity: (f32, f32),
+    pub size: (f32, f32),
+    pub on_ground: bool,
+}
+
+// The sides of a cell of the map, in the world
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct Cell {
+    left: f32,
+    top: f32,
+    right: f32,
+    bottom: f32,
+}
+
+impl Body {
+    pub fn new(position: (f32, f32), size: (f32, f32)) -> Self {
+        Body {
+            position,
+            velocity: (0.0, 0.0),
+            size,
+            on_ground: false,
+        }
+    }
+
+    pub fn bottom(&self) -> f32 {
+        self.position.1 + self.size.1
+    }
+
+    pub fn center(&self) -> (f32, f32) {
+        (
+            self.position.0 + self.size.0 / 2.0,
+            self.position.1 + self.size.1 / 2.0,
+        )
+    }
+
+    // The cells of the map with a collision that are inside of the box. A cell that
+    // is only touched by a side of the box is not inside
+    fn cells(&self, map: &TileMap, collision: Collision) -> Vec<Cell> {
+        let size = map.cell_size() as f32;
+        let range = |start: f32, length: f32, count: usize| {
+            let first = ((start + EPSILON) / size).floor().max(0.0) as usize;
+            let last = ((start + length - EPSILON) / size).floor().max(-1.0) as isize;
+            first..((last + 1) as usize).min(count)
+        };
+
+        let mut cells = Vec::new();
+        for row in range(self.position.1, self.size.1, map.height) {
+            for column in range(self.position.0, self.size.0, map.width) {
+                if map.collision(column, row) == collision {
+                    cells.push(cell(map, column, row));
+                }
+            }
+        }
+
+        cells
+    }
+}
+
+fn cell(map: &TileMap, column: usize, row: usize) -> Cell {
+    let size = map.cell_size() as f32;
+
+    Cell {
+        left: column as f32 * size,
+        top: row as f32 * size,
+        right: (column + 1) as f32 * size,
+        bottom: (row + 1) as f32 * size,
+    }
+}
+
+// Whether there is a slope under the middle of a body, up to the step height
+// above or below its bottom
+fn on_slope(body: &Body, map: &TileMap, step_height: f32) -> bool {
+    let size = map.cell_size() as f32;
+    let (foot, _) = body.center();
+    if foot < 0.0 {
+        return false;
+    }
+
+    let column = (foot / size) as usize;
+    let first = ((body.bottom() - step_height) / size).floor().max(0.0) as usize;
+    let last = ((body.bottom() + step_height) / size).floor().max(0.0) as usize;
+
+    (first..=last).any(|row| {
+        let collision = map.collision(column, row);
+        collision == Collision::SlopeUp || collision == Collision::SlopeDown
+    })
+}
+
+// The solid cells inside of a body, without the low ones under its sides when
+// it is on a slope
+fn low_tiles_ignored(body: &Body, map: &TileMap, step_height: f32) -> Vec<Cell> {
+    let mut cells = body.cells(map, Collision::Solid);
+
+    if on_slope(body, map, step_height) {
+        cells.retain(|cell| cell.top < body.bottom() - step_height);
+    }
+
+    cells
+}
+
+// Height of the floor of a cell under a point. The cells without a floor, like
+// the empty cells and the platforms, don't have one
+fn floor(cell: Cell, collision: Collision, x: f32) -> Option<f32> {
+    let offset = (x - cell.left).clamp(0.0, cell.right - cell.left);
+
+    match collision {
+        Collision::Solid => Some(cell.top),
+        Collision::SlopeUp => Some(cell.bottom - offset),
+        Collision::SlopeDown => Some(cell.top + offset),
+        _ => None,
+    }
+}
+
+// Moves a body by its velocity and stops it against the tiles of the map. The
+// movement is done one axis at a time, first the horizontal and then the vertical,
+// so a body that hits a tile knows from which side it came and it can slide along
+// the walls and the floors.
+//
+// A body on the ground climbs the steps that are lower than the step height instead
+// of being stopped by them. A body that is going up doesn't stop on the platforms,
+// and on the ground it sticks to the slopes that go down instead of flying off them.
+//
+// A body stands on a slope with its middle, so its sides go into the slope and
+// into the ground at both ends of the slope. While it is on a slope, the tiles
+// under its sides that are lower than the step height are not taken as walls or
+// floors, so it walks up and down the slope without jumping at its ends
+pub fn move_body(body: &mut Body, map: &TileMap, dt: f32, drop_through: bool, step_height: f32) {
+    // A fast body is moved in several steps of less than half a cell, so it can't
+    // go through a tile between two frames
+    let distance = body.velocity.0.abs().max(body.velocity.1.abs()) * dt;
+    let steps = (distance / (map.cell_size() as f32 / 2.0)).ceil().max(1.0);
+
+    for _ in 0..steps as usize {
+        move_step(body, map, dt / steps, drop_through, step_height);
+    }
+}
+
+fn move_step(body: &mut Body, map: &TileMap, dt: f32, drop_through: bool, step_height: f32) {
+    let was_on_ground = body.on_ground;
+    body.on_ground = false;
+
+    // Horizontal movement. The body is stopped against the side of the walls, unless
+    // it can step over them
+    body.position.0 += body.velocity.0 * dt;
+
+    let walls = low_tiles_ignored(body, map, step_height);
+    if !walls.is_empty() {
+        let top = walls.iter().map(|cell| cell.top).fold(f32::MAX, f32::min);
+        let mut raised = *body;
+        raised.position.1 = top - body.size.1;
+
+        if was_on_ground && body.bottom() - top <= step_height && raised.cells(map, Collision::Solid).is_empty() {
+            *body = raised;
+            body.on_ground = true;
+        } else if body.velocity.0 > 0.0 {
+            let left = walls.iter().map(|cell| cell.left).fold(f32::MAX, f32::min);
+            body.position.0 = left - body.size.0;
+            body.velocity.0 = 0.0;
+        } else if body.velocity.0 < 0.0 {
+            let right = walls.iter().map(|cell| cell.right).fold(f32::MIN, f32::max);
+            body.position.0 = right;
+            body.velocity.0 = 0.0;
+        }
+    }
+
+    // Vertical movement. The body lands on the floors and hits its head against the
+    // ceilings
+    let previous_bottom = body.bottom();
+    body.position.1 += body.velocity.1 * dt;
+
+    let floors = low_tiles_ignored(body, map, step_height);
+    if !floors.is_empty() {
+        if body.velocity.1 > 0.0 {
+            let top = floors.iter().map(|cell| cell.top).fold(f32::MAX, f32::min);
+            body.position.1 = top - body.size.1;
+            body.velocity.1 = 0.0;
+            body.on_ground = true;
+        } else if body.velocity.1 < 0.0 {
+            let bottom = floors.iter().map(|cell| cell.bottom).fold(f32::MIN, f32::max);
+            body.position.1 = bottom;
+            body.velocity.1 = 0.0;
+        }
+    }
+
+    // The platforms only stop a body that was above them before this movement, so
+    // it can jump through them from below
+    if body.velocity.1 >= 0.0 && !drop_through {
+        let platform = body
+            .cells(map, Collision::Platform)
+            .into_iter()
+            .filter(|cell| previous_bottom <= cell.top + EPSILON)
+            .map(|cell| cell.top)
+            .fold(None, |highest: Option<f32>, top| Some(highest.map_or(top, |highest| highest.min(top))));
+
+        if let Some(top) = platform {
+            body.position.1 = top - body.size.1;
+            body.velocity.1 = 0.0;
+            body.on_ground = true;
+        }
+    }
+
+    // The slopes are only checked under the middle of the body, so it goes into
+    // them until its center is on the floor. A body that was on the ground follows
+    // the floor under its middle down as far as the step height, so it doesn't fly
+    // off the slopes that go down or the steps at their bottom. The highest floor
+    // that is not more than the step height above the bottom of the body is used
+    if body.velocity.1 >= 0.0 {
+        let size = map.cell_size() as f32;
+        let (foot, _) = body.center();
+        let snap = if was_on_ground && !body.on_ground { step_height } else { 0.0 };
+
+        if foot >= 0.0 {
+            let column = (foot / size) as usize;
+            let first = ((body.bottom() - step_height) / size).floor().max(0.0) as usize;
+            let last = ((body.bottom() + snap) / size).floor().max(0.0) as usize;
+
+            for row in first..=last {
+                let floor = match floor(cell(map, column, row), map.collision(column, row), foot) {
+                    Some(floor) if floor >= body.bottom() - step_height => floor,
+                    _ => continue,
+                };
+
+                let under = body.bottom() > floor;
+                if under || (!body.on_ground && body.bottom() >= floor - snap) {
+                    body.position.1 = floor - body.size.1;
+                    body.velocity.1 = 0.0;
+                    body.on_ground = true;
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Cells of 10 pixels: a wall on the left, a platform, a slope going up to the
+    // flat ground on the right and a ceiling on top
+    const LEVEL: &str = "
+        tileset = forest.bmp
+        tile = 10
+        solid = 1
+        platform = 4
+        slope_up = 9
+        data:
+        1,1,1,1,1,1,1,1,1,1
+        1,0,0,0,0,0,0,0,0,0
+        1,0,0,0,0,0,0,0,0,0
+        1,0,0,0,4,4,0,0,0,0
+        1,0,0,0,0,0,0,0,0,0
+        1,0,0,0,0,0,0,0,0,0
+        1,0,0,0,0,0,0,0,9,1
+        1,1,1,1,1,1,1,1,1,1
+        end
+    ";
+
+    const STEP: f32 = 5.0;
+
+    fn fall(body: &mut Body, map: &TileMap, frames: usize) {
+        for _ in 0..frames {
+            body.velocity.1 += 10.0;
+            move_body(body, map, 0.1, false, STEP);
+        }
+    }
+
+    #[test]
+    fn test_walls_and_floors() {
+        let map = TileMap::parse(LEVEL).unwrap();
+
+        // The body falls until it lands on the floor
+        let mut body = Body::new((12.0, 42.0), (6.0, 8.0));
+        fall(&mut body, &map, 20);
+        assert!(body.on_ground);
+        assert_eq!(body.bottom(), 70.0);
+
+        // It stops against the wall on the left
+        body.velocity.0 = -50.0;
+        move_body(&mut body, &map, 0.1, false, STEP);
+        assert_eq!(body.position.0, 10.0);
+        assert_eq!(body.velocity.0, 0.0);
+        assert!(body.on_ground);
+
+        // It hits its head against the ceiling
+        body.velocity.1 = -1000.0;
+        move_body(&mut body, &map, 0.1, false, STEP);
+        assert_eq!(body.position.1, 10.0);
+        assert_eq!(body.velocity.1, 0.0);
+        assert!(!body.on_ground);
+    }
+
+    #[test]
+    fn test_platforms() {
+        let map = TileMap::parse(LEVEL).unwrap();
+
+        // Going up, the body goes through the platform
+        let mut body = Body::new((42.0, 52.0), (6.0, 8.0));
+        body.velocity.1 = -300.0;
+        move_body(&mut body, &map, 0.1, false, STEP);
+        assert_eq!(body.position.1, 22.0);
+
+        // Falling, it lands on top of the platform
+        fall(&mut body, &map, 10);
+        assert!(body.on_ground);
+        assert_eq!(body.bottom(), 30.0);
+
+        // And it falls through when it drops down
+        body.velocity.1 = 10.0;
+        move_body(&mut body, &map, 0.1, true, STEP);
+        assert!(!body.on_ground);
+        assert_eq!(body.bottom(), 31.0);
+    }
+
+    #[test]
+    fn test_slopes() {
+        let map = TileMap::parse(LEVEL).unwrap();
+
+        let mut body = Body::new((70.0, 62.0), (6.0, 8.0));
+        fall(&mut body, &map, 5);
+        assert!(body.on_ground);
+
+        // Walking to the right, the body goes up the slope once its center is on
+        // the slope, and then it walks to the flat ground at the top of the slope
+        body.velocity.0 = 20.0;
+        for _ in 0..4 {
+            move_body(&mut body, &map, 0.1, false, STEP);
+            assert!(body.on_ground);
+        }
+        assert_eq!(body.center().0, 81.0);
+        assert_eq!(body.bottom(), 69.0);
+
+        for _ in 0..4 {
+            move_body(&mut body, &map, 0.1, false, STEP);
+            assert!(body.on_ground);
+        }
+        assert_eq!(body.bottom(), 61.0);
+
+        move_body(&mut body, &map, 0.1, false, STEP);
+        assert!(body.on_ground);
+        assert_eq!(body.bottom(), 60.0);
+
+        // Walking back, it follows the slope down and the step at its bottom
+        // instead of falling
+        body.velocity.0 = -20.0;
+        for _ in 0..8 {
+            move_body(&mut body, &map, 0.1, false, STEP);
+            assert!(body.on_ground);
+        }
+        assert_eq!(body.center().0, 75.0);
+        assert_eq!(body.bottom(), 70.0);
+    }
+}
@@ -0,0 +1,538 @@
+use std::time::Duration;
+
+use common::random::random;
+
+use crate::animation::{Animation, SpriteSheet};
+use crate::ecs::{Controller, Entity, Input, Jump, Sprite, World};
+use crate::physics::{self, Body};
+use crate::tilemap::{Collision, TileMap};
+
+// The systems of the game. Each one goes through the entities with the components
+// it needs, every frame and in this order: the controllers decide the input of the
+// entities, the movement moves their bodies in the level and the animation picks
+// the frames of their sprites. The sprites are drawn by the game in their z order
+
+// The speeds are in pixels per second and the accelerations in pixels per second
+// squared. A character gets to its top speed in a fifth of a second and stops in
+// a quarter of a second after the keys are released
+pub const MAX_SPEED: f32 = 300.0;
+pub const ACCELERATION: f32 = 1500.0;
+const DECELERATION: f32 = 1200.0;
+
+// A jump goes up about two and a half tiles of the level. Releasing the jump key
+// while going up cuts the speed, so a short press makes a short jump
+const GRAVITY: f32 = 2400.0;
+const JUMP_SPEED: f32 = 900.0;
+const JUMP_CUT: f32 = 0.5;
+const MAX_FALL_SPEED: f32 = 1200.0;
+
+// Time after walking off a ledge when a character can still jump, and time before
+// landing when a jump key that is pressed is remembered. Both make the jumps feel
+// fair when the key is pressed a little too late or too early
+const COYOTE_TIME: f32 = 0.1;
+const JUMP_BUFFER: f32 = 0.12;
+
+// The box that collides with the level is narrower than the sprite, because the
+// drawings don't fill their tiles
+const BODY_WIDTH: f32 = 0.375;
+
+// The longest time that is integrated in one frame. After a long pause, like
+// dragging the window, the characters don't jump across the screen
+const MAX_ELAPSED: Duration = Duration::from_millis(100);
+
+// The characters that wander walk at half the speed of the player, for a time
+// between the shortest and the longest, and stand still between two walks
+const WANDER_SPEED: f32 = 0.5;
+const WANDER_TIME: (f32, f32) = (1.0, 3.0);
+
+// Names of the animations of the sheet used by the characters
+const IDLE: &str = "idle";
+const WALK: &str = "walk";
+const JUMP: &str = "jump";
+
+// Adds a character to the world with its middle of the feet in a point of the
+// level. It starts again in the same point when it falls out of the level
+pub fn spawn_character(
+    world: &mut World,
+    sheet: &SpriteSheet,
+    feet: (f32, f32),
+    sprite: Sprite,
+    controller: Controller,
+) -> Result<Entity, String> {
+    let idle = sheet
+        .clip(IDLE)
+        .ok_or(format!("The sheet has no {} animation", IDLE))?;
+
+    let entity = world.spawn();
+    world.bodies.insert(entity, Body::new((0.0, 0.0), (0.0, 0.0)));
+    world.sprites.insert(entity, sprite);
+    world.animations.insert(entity, Animation::new(idle));
+    world.controllers.insert(entity, controller);
+    world.inputs.insert(entity, Input::default());
+    world.jumps.insert(entity, Jump::default());
+    world.starts.insert(entity, feet);
+    place(world, sheet, entity, feet);
+
+    Ok(entity)
+}
+
+// Puts an entity with the middle of its feet in a point of the level, standing
+// still and without a jump going on
+pub fn place(world: &mut World, sheet: &SpriteSheet, entity: Entity, feet: (f32, f32)) {
+    fit_body(world, sheet, entity);
+
+    if let Some(body) = world.bodies.get_mut(entity) {
+        body.position = (feet.0 - body.size.0 / 2.0, feet.1 - body.size.1);
+        body.velocity = (0.0, 0.0);
+        body.on_ground = false;
+    }
+
+    if let Some(jump) = world.jumps.get_mut(entity) {
+        *jump = Jump::default();
+    }
+}
+
+// Size of the sprite of an entity in the world
+pub fn sprite_size(sprite: &Sprite, sheet: &SpriteSheet) -> u32 {
+    sheet.tile_size * sprite.scale
+}
+
+// Makes the box of the body fit the size of the sprite, keeping the middle of its
+// feet in the same place. The sheet may have been loaded again or the sprite may
+// have changed its size
+fn fit_body(world: &mut World, sheet: &SpriteSheet, entity: Entity) {
+    let (sprite, body) = match (world.sprites.get(entity), world.bodies.get_mut(entity)) {
+        (Some(sprite), Some(body)) => (sprite, body),
+        _ => return,
+    };
+
+    let size = sprite_size(sprite, sheet) as f32;
+    let body_size = (size * BODY_WIDTH, size);
+    if body.size == body_size {
+        return;
+    }
+
+    let (x, _) = body.center();
+    let bottom = body.bottom();
+    body.size = body_size;
+    body.position = (x - body_size.0 / 2.0, bottom - body_size.1);
+}
+
+// Runs the systems for the time since the last frame. The keys are the input of
+// the entities controlled by the player
+pub fn update(world: &mut World, sheet: &SpriteSheet, map: &TileMap, keys: Input, elapsed: Duration) {
+    let elapsed = elapsed.min(MAX_ELAPSED);
+    let dt = elapsed.as_secs_f32();
+
+    control(world, map, keys, dt);
+    movement(world, sheet, map, dt);
+    animate(world, sheet, elapsed);
+}
+
+// Decides the input of the entities with a controller. The characters moved by the
+// computer do what a player would do with the keys, so they move with the same rules
+fn control(world: &mut World, map: &TileMap, keys: Input, dt: f32) {
+    for entity in world.entities() {
+        let (controller, body) = match (world.controllers.get_mut(entity), world.bodies.get(entity)) {
+            (Some(controller), Some(body)) => (controller, body),
+            _ => continue,
+        };
+        let last = world.inputs.get(entity).copied().unwrap_or_default();
+
+        // A character that wanted to walk but has no speed has hit a wall
+        let blocked = last.x != 0.0 && body.velocity.0 == 0.0;
+
+        let input = match controller {
+            Controller::Player => keys,
+            Controller::Wander { direction, timer } => {
+                // When the time is over the character stops, or it starts walking
+                // to a side if it was standing
+                *timer -= dt;
+                if *timer <= 0.0 {
+                    *direction = if *direction != 0.0 {
+                        0.0
+                    } else if random(&mut world.seed) < 0.5 {
+                        -WANDER_SPEED
+                    } else {
+                        WANDER_SPEED
+                    };
+                    *timer = WANDER_TIME.0 + (WANDER_TIME.1 - WANDER_TIME.0) * random(&mut world.seed);
+                }
+
+                let turn = blocked || ledge_ahead(body, map, *direction);
+                if *direction != 0.0 && body.on_ground && turn {
+                    *direction = -*direction;
+                }
+
+                Input { x: *direction, ..Input::default() }
+            }
+            Controller::Follow { target, distance } => {
+                // The character walks towards the target and jumps over the walls and
+                // the holes in its way. The jump key is held while it goes up, so it
+                // makes a high jump
+                let x = match world.bodies.get(*target) {
+                    Some(other) if *target != entity => {
+                        let dx = other.center().0 - body.center().0;
+                        if dx.abs() > *distance { dx.signum() } else { 0.0 }
+                    }
+                    _ => 0.0,
+                };
+
+                let jump = if body.on_ground {
+                    x != 0.0 && (blocked || ledge_ahead(body, map, x))
+                } else {
+                    body.velocity.1 < 0.0
+                };
+
+                Input { x, jump, down: false }
+            }
+        };
+
+        world.inputs.insert(entity, input);
+    }
+}
+
+// Whether there is no ground in front of the feet of a body that walks to a side.
+// A step down of less than half a tile is not a ledge. The borders of the level are
+// ledges too, because the cells outside of the map are empty, so the characters
+// that wander turn around before leaving the level
+fn ledge_ahead(body: &Body, map: &TileMap, direction: f32) -> bool {
+    let cell = map.cell_size() as f32;
+    let front = if direction < 0.0 {
+        body.position.0 - 1.0
+    } else {
+        body.position.0 + body.size.0 + 1.0
+    };
+
+    if front < 0.0 {
+        return true;
+    }
+
+    let column = (front / cell) as usize;
+    let empty = |y: f32| map.collision(column, (y / cell) as usize) == Collision::Empty;
+
+    empty(body.bottom() + 1.0) && empty(body.bottom() + cell / 2.0)
+}
+
+// Moves the entities with a body by their input, the gravity and the tiles of the
+// level. Every entity uses the same rules as the player
+fn movement(world: &mut World, sheet: &SpriteSheet, map: &TileMap, dt: f32) {
+    for entity in world.entities() {
+        fit_body(world, sheet, entity);
+
+        let (body, jump) = match (world.bodies.get_mut(entity), world.jumps.get_mut(entity)) {
+            (Some(body), Some(jump)) => (body, jump),
+            _ => continue,
+        };
+        let input = world.inputs.get(entity).copied().unwrap_or_default();
+
+        // The horizontal velocity goes towards the speed of the keys that are held.
+        // Without keys the character slows down until it stops
+        let rate = if input.x == 0.0 { DECELERATION } else { ACCELERATION };
+        body.velocity.0 = approach(body.velocity.0, input.x * MAX_SPEED, rate * dt);
+
+        // A jump starts when the jump key has been pressed a moment ago and the
+        // character is on the ground or has just left it
+        if input.jump && !jump.held {
+            jump.buffer = JUMP_BUFFER;
+        }
+        jump.held = input.jump;
+
+        if body.on_ground {
+            jump.coyote = COYOTE_TIME;
+            jump.jumping = false;
+        }
+
+        if jump.buffer > 0.0 && jump.coyote > 0.0 {
+            body.velocity.1 = -JUMP_SPEED;
+            jump.buffer = 0.0;
+            jump.coyote = 0.0;
+            jump.jumping = true;
+        }
+
+        if jump.jumping && !input.jump && body.velocity.1 < 0.0 {
+            body.velocity.1 *= JUMP_CUT;
+            jump.jumping = false;
+        }
+
+        jump.buffer -= dt;
+        jump.coyote -= dt;
+
+        body.velocity.1 = (body.velocity.1 + GRAVITY * dt).min(MAX_FALL_SPEED);
+
+        // The characters climb steps of half a tile, and holding down drops them
+        // through the platforms
+        let step_height = map.cell_size() as f32 / 2.0;
+        physics::move_body(body, map, dt, input.down, step_height);
+
+        // The characters can't walk out of the level: the position is kept inside the
+        // borders of the world and they stop when they hit one
+        let (width, height) = map.pixel_size();
+        let max_x = (width as f32 - body.size.0).max(0.0);
+        if body.position.0 < 0.0 || body.position.0 > max_x {
+            body.position.0 = body.position.0.clamp(0.0, max_x);
+            body.velocity.0 = 0.0;
+        }
+
+        // A character that falls out of the bottom of the level starts again
+        if body.position.1 > height as f32 {
+            if let Some(start) = world.starts.get(entity).copied() {
+                place(world, sheet, entity, start);
+            }
+        }
+    }
+}
+
+// Picks the animation of the entities from what their bodies do, and turns their
+// sprites to the side they walk to
+fn animate(world: &mut World, sheet: &SpriteSheet, elapsed: Duration) {
+    for entity in world.entities() {
+        let (sprite, animation, body) = match (
+            world.sprites.get_mut(entity),
+            world.animations.get_mut(entity),
+            world.bodies.get(entity),
+        ) {
+            (Some(sprite), Some(animation), Some(body)) => (sprite, animation, body),
+            _ => continue,
+        };
+
+        // The sheet may have been loaded again with less characters
+        sprite.character %= sheet.characters;
+
+        // Since all the sprites are facing to the right, the sprite is only flipped
+        // when the entity goes to the left. Without input it keeps looking to the
+        // same side
+        let x = world.inputs.get(entity).map_or(0.0, |input| input.x);
+        if x < 0.0 {
+            sprite.flipped = true;
+        } else if x > 0.0 {
+            sprite.flipped = false;
+        }
+
+        // The frames of the animation change with the time that has passed, so the
+        // character keeps moving while it stands still and it walks at the same pace
+        // at any frame rate. The clips are taken from the sheet every frame, and a
+        // sheet without a jump animation uses the walk in the air
+        let name = if !body.on_ground && sheet.clip(JUMP).is_some() {
+            JUMP
+        } else if body.velocity.0 != 0.0 {
+            WALK
+        } else {
+            IDLE
+        };
+        if let Some(clip) = sheet.clip(name) {
+            animation.play(clip);
+        }
+
+        animation.update(elapsed);
+    }
+}
+
+// Moves a value towards a target by a step, without going past the target
+fn approach(value: f32, target: f32, step: f32) -> f32 {
+    if value < target {
+        (value + step).min(target)
+    } else {
+        (value - step).max(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    // A level of cells of 64 pixels with the ground on the left and a hole on the
+    // right, so the characters can walk off the ledge
+    const LEDGE: &str = "
+        tileset = forest.bmp
+        tile = 32
+        scale = 2
+        solid = 1
+        data:
+        0,0,0,0,0,0,0,0
+        0,0,0,0,0,0,0,0
+        0,0,0,0,0,0,0,0
+        0,0,0,0,0,0,0,0
+        1,1,1,0,0,0,0,0
+        0,0,0,0,0,0,0,0
+        0,0,0,0,0,0,0,0
+        0,0,0,0,0,0,0,0
+        0,0,0,0,0,0,0,0
+        0,0,0,0,0,0,0,0
+        end
+    ";
+
+    const FRAME: Duration = Duration::from_millis(10);
+
+    fn sprite(character: u32) -> Sprite {
+        Sprite {
+            character,
+            scale: 4,
+            flipped: false,
+            z: 0,
+        }
+    }
+
+    // A world with a player standing on the ledge
+    fn test_world() -> (SpriteSheet, TileMap, World, Entity) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/characters.sheet");
+        let sheet = SpriteSheet::load(&path).unwrap();
+        let map = TileMap::parse(LEDGE).unwrap();
+        let mut world = World::new();
+        let player = spawn_character(&mut world, &sheet, (96.0, 256.0), sprite(0), Controller::Player)
+            .unwrap();
+        update(&mut world, &sheet, &map, Input::default(), FRAME);
+        assert!(body(&world, player).on_ground);
+
+        (sheet, map, world, player)
+    }
+
+    fn body(world: &World, entity: Entity) -> Body {
+        *world.bodies.get(entity).unwrap()
+    }
+
+    // Plays some frames with the same keys and returns the highest position of the
+    // bottom of an entity
+    fn play(
+        world: &mut World,
+        sheet: &SpriteSheet,
+        map: &TileMap,
+        frames: usize,
+        keys: Input,
+        entity: Entity,
+    ) -> f32 {
+        let mut highest = body(world, entity).bottom();
+        for _ in 0..frames {
+            update(world, sheet, map, keys, FRAME);
+            highest = highest.min(body(world, entity).bottom());
+        }
+
+        highest
+    }
+
+    #[test]
+    fn test_movement() {
+        let (sheet, map, mut world, player) = test_world();
+        let right = Input { x: 1.0, ..Input::default() };
+
+        // The player speeds up until its top speed, in the same time at any frame rate
+        play(&mut world, &sheet, &map, 10, right, player);
+        assert!((body(&world, player).velocity.0 - ACCELERATION * 0.1).abs() < 0.01);
+
+        let (_, _, mut other, _) = test_world();
+        update(&mut other, &sheet, &map, right, FRAME * 10);
+        assert!((body(&other, player).velocity.0 - body(&world, player).velocity.0).abs() < 0.01);
+
+        update(&mut world, &sheet, &map, right, Duration::from_secs(1));
+        assert_eq!(body(&world, player).velocity.0, MAX_SPEED);
+        assert!(!world.sprites.get(player).unwrap().flipped);
+
+        // It slows down and stops when the keys are released
+        play(&mut world, &sheet, &map, 30, Input::default(), player);
+        assert_eq!(body(&world, player).velocity, (0.0, 0.0));
+        assert!(body(&world, player).on_ground);
+
+        // The player stops at the borders of the world
+        play(&mut world, &sheet, &map, 100, Input { x: -1.0, ..Input::default() }, player);
+        assert_eq!(body(&world, player).position.0, 0.0);
+        assert!(world.sprites.get(player).unwrap().flipped);
+    }
+
+    #[test]
+    fn test_jump() {
+        let jump = Input { jump: true, ..Input::default() };
+
+        // Holding the jump key makes a higher jump than tapping it
+        let (sheet, map, mut world, player) = test_world();
+        let high = play(&mut world, &sheet, &map, 100, jump, player);
+        assert!(body(&world, player).on_ground);
+
+        let (_, _, mut world, _) = test_world();
+        play(&mut world, &sheet, &map, 5, jump, player);
+        let low = play(&mut world, &sheet, &map, 100, Input::default(), player);
+        assert!(low > high);
+        assert!(256.0 - high > map.cell_size() as f32 * 2.0);
+
+        // A jump that is pressed just before landing starts when the player lands,
+        // but not one that is pressed too long before
+        let (_, _, mut world, _) = test_world();
+        place(&mut world, &sheet, player, (96.0, 200.0));
+        play(&mut world, &sheet, &map, 16, Input::default(), player);
+        assert!(!body(&world, player).on_ground);
+        play(&mut world, &sheet, &map, 1, jump, player);
+        play(&mut world, &sheet, &map, 10, Input::default(), player);
+        assert!(body(&world, player).velocity.1 < 0.0);
+
+        let (_, _, mut world, _) = test_world();
+        place(&mut world, &sheet, player, (96.0, 200.0));
+        play(&mut world, &sheet, &map, 1, jump, player);
+        play(&mut world, &sheet, &map, 40, Input::default(), player);
+        assert!(body(&world, player).on_ground);
+    }
+
+    #[test]
+    fn test_coyote_time() {
+        let right = Input { x: 1.0, ..Input::default() };
+        let jump = Input { jump: true, ..Input::default() };
+
+        // Walks off the ledge and jumps a moment after leaving the ground
+        let (sheet, map, mut world, player) = test_world();
+        while body(&world, player).on_ground {
+            play(&mut world, &sheet, &map, 1, right, player);
+        }
+        play(&mut world, &sheet, &map, 5, Input::default(), player);
+        play(&mut world, &sheet, &map, 1, jump, player);
+        assert!(body(&world, player).velocity.1 < 0.0);
+
+        // Too late, the player keeps falling
+        let (_, _, mut world, _) = test_world();
+        while body(&world, player).on_ground {
+            play(&mut world, &sheet, &map, 1, right, player);
+        }
+        play(&mut world, &sheet, &map, 20, Input::default(), player);
+        play(&mut world, &sheet, &map, 1, jump, player);
+        assert!(body(&world, player).velocity.1 > 0.0);
+    }
+
+    #[test]
+    fn test_wander() {
+        let (sheet, map, mut world, player) = test_world();
+        let wander = Controller::Wander { direction: WANDER_SPEED, timer: 2.0 };
+        let npc = spawn_character(&mut world, &sheet, (100.0, 256.0), sprite(1), wander).unwrap();
+
+        // The character walks on its own but never falls from the ledge or leaves the
+        // level, while the player stands still
+        let mut positions = Vec::new();
+        for _ in 0..2000 {
+            play(&mut world, &sheet, &map, 1, Input::default(), npc);
+            positions.push(body(&world, npc).position.0);
+            assert!(body(&world, npc).bottom() <= 256.0);
+        }
+
+        let moved = positions.iter().any(|x| (x - positions[0]).abs() > 50.0);
+        assert!(moved);
+        assert!(body(&world, npc).on_ground);
+        assert_eq!(body(&world, player).velocity, (0.0, 0.0));
+    }
+
+    #[test]
+    fn test_follow() {
+        let (sheet, map, mut world, player) = test_world();
+        let follow = Controller::Follow { target: player, distance: 40.0 };
+        let npc = spawn_character(&mut world, &sheet, (170.0, 256.0), sprite(2), follow).unwrap();
+
+        // The character walks to the player and stops close to it
+        play(&mut world, &sheet, &map, 100, Input::default(), npc);
+        let dx = body(&world, player).center().0 - body(&world, npc).center().0;
+        assert!(dx.abs() <= 40.0);
+        assert_eq!(body(&world, npc).velocity.0, 0.0);
+        assert!(world.sprites.get(npc).unwrap().flipped);
+
+        // It follows the player when the player walks away
+        place(&mut world, &sheet, player, (20.0, 256.0));
+        play(&mut world, &sheet, &map, 100, Input::default(), npc);
+        let dx = body(&world, player).center().0 - body(&world, npc).center().0;
+        assert!(dx.abs() <= 40.0);
+    }
+}
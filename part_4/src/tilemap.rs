@@ -0,0 +1,369 @@
+use std::collections::HashMap;
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use common::assets;
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+
+// How the bodies collide with a tile
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Collision {
+    Empty,
+    Solid,     // Stops the bodies from every side
+    Platform,  // Only stops the bodies that fall on its top
+    SlopeUp,   // The floor goes up from the bottom left to the top right
+    SlopeDown, // The floor goes down from the top left to the bottom right
+}
+
+// A level is a grid of tiles taken from a tileset image. The level file gives the
+// tileset, the size of its tiles and the scale they are drawn with, and how the
+// player collides with each tile. The tiles that are not given are only decoration.
+// A tileset drawn over a background color can give that color as its color key.
+// The tiles are counted from 1 in the tileset, row by row, and 0 is an empty cell,
+// the same numbers as a TMX file of Tiled with a single tileset.
+//
+// The tiles of the map are in a CSV file next to the level, exported from a tile
+// layer in Tiled with File > Export As > CSV. Tiled counts the tiles of those files
+// from 0 and writes -1 in the empty cells, so their numbers are one less than the
+// ones of the level:
+//
+//     tileset = forest.bmp
+//     tile = 32
+//     scale = 2
+//     color_key = 110 170 220
+//     solid = 1 2
+//     platform = 4
+//     layer = forest.csv
+//
+// A small level can have its tiles after a `data:` line instead, with the numbers
+// of the level:
+//
+//     data:
+//     0,0,6,6,0
+//     1,1,1,1,1
+//     end
+#[derive(Clone, Debug, PartialEq)]
+pub struct TileMap {
+    pub tileset: PathBuf,
+    pub layer: Option<PathBuf>, // The CSV file with the tiles, if they are not in the level
+    pub tile_size: u32, // Size of the tiles in the tileset
+    pub scale: u32,
+    pub color_key: Option<Color>,
+    pub width: usize, // Size of the map in tiles
+    pub height: usize,
+    tiles: Vec<u32>,
+    collisions: HashMap<u32, Collision>,
+}
+
+impl TileMap {
+    // The tileset and the layer are found in the folder of the level
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut map = TileMap::parse(&text)?;
+
+        if let Some(folder) = path.parent() {
+            map.tileset = folder.join(&map.tileset);
+            map.layer = map.layer.map(|layer| folder.join(layer));
+        }
+
+        if let Some(layer) = &map.layer {
+            let csv = fs::read_to_string(layer).map_err(|e| format!("{}: {}", layer.display(), e))?;
+            map.read_layer(&csv)?;
+        }
+
+        Ok(map)
+    }
+
+    // Reads the tiles of a CSV file exported by Tiled, where the tiles are counted
+    // from 0 and the empty cells are -1
+    pub fn read_layer(&mut self, csv: &str) -> Result<(), String> {
+        let mut rows = Vec::new();
+
+        for line in csv.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let row = line
+                .trim_end_matches(',')
+                .split(',')
+                .map(|tile| match tile.trim().parse::<i64>() {
+                    Ok(-1) => Ok(0),
+                    Ok(tile) if (0..u32::MAX as i64).contains(&tile) => Ok(tile as u32 + 1),
+                    _ => Err(format!("Invalid tile: {}", tile)),
+                })
+                .collect::<Result<Vec<u32>, String>>()?;
+            rows.push(row);
+        }
+
+        self.set_rows(rows)
+    }
+
+    fn set_rows(&mut self, rows: Vec<Vec<u32>>) -> Result<(), String> {
+        let width = rows.first().map_or(0, Vec::len);
+        if width == 0 || rows.iter().any(|row| row.len() != width) {
+            return Err(String::from("The rows of the level must have the same number of tiles"));
+        }
+
+        self.width = width;
+        self.height = rows.len();
+        self.tiles = rows.concat();
+        Ok(())
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut tileset = None;
+        let mut tile_size = None;
+        let mut scale = 1;
+        let mut color_key = None;
+        let mut layer = None;
+        let mut rows: Vec<Vec<u32>> = Vec::new();
+        let mut collisions = HashMap::new();
+        let mut in_data = false;
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if in_data {
+                if line == "end" {
+                    in_data = false;
+                    continue;
+                }
+
+                // The rows exported by Tiled end with a comma, except the last one
+                let row = line
+                    .trim_end_matches(',')
+                    .split(',')
+                    .map(|tile| {
+                        tile.trim()
+                            .parse::<u32>()
+                            .map_err(|_| format!("Invalid tile: {}", tile))
+                    })
+                    .collect::<Result<Vec<u32>, String>>()?;
+                rows.push(row);
+                continue;
+            }
+
+            if line == "data:" {
+                in_data = true;
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                Some(index) => (line[..index].trim(), line[index + 1..].trim()),
+                None => return Err(format!("Invalid level line: {}", line)),
+            };
+
+            match key {
+                "tileset" => tileset = Some(PathBuf::from(value)),
+                "layer" => layer = Some(PathBuf::from(value)),
+                "tile" => tile_size = Some(parse_number(value)?),
+                "scale" => scale = parse_number(value)?.max(1),
+                "color_key" => color_key = Some(assets::parse_color(value)?),
+                "solid" | "platform" | "slope_up" | "slope_down" => {
+                    let collision = match key {
+                        "solid" => Collision::Solid,
+                        "platform" => Collision::Platform,
+                        "slope_up" => Collision::SlopeUp,
+                        _ => Collision::SlopeDown,
+                    };
+
+                    for tile in value.split_whitespace() {
+                        collisions.insert(parse_number(tile)?, collision);
+                    }
+                }
+                _ => return Err(format!("Unknown level setting: {}", key)),
+            }
+        }
+
+        if in_data {
+            return Err(String::from("The data of the level has no end"));
+        }
+
+        let mut map = TileMap {
+            tileset: tileset.ok_or("The level has no tileset")?,
+            layer,
+            tile_size: tile_size.ok_or("The level has no tile size")?,
+            scale,
+            color_key,
+            width: 0,
+            height: 0,
+            tiles: Vec::new(),
+            collisions,
+        };
+
+        // The tiles of a layer file are read when the level is loaded
+        match (&map.layer, rows.is_empty()) {
+            (Some(_), true) => {}
+            (Some(_), false) => return Err(String::from("The level has a layer and its own tiles")),
+            (None, _) => map.set_rows(rows)?,
+        }
+
+        Ok(map)
+    }
+
+    // Size of a tile of the map in the world, in pixels
+    pub fn cell_size(&self) -> u32 {
+        self.tile_size * self.scale
+    }
+
+    // Size of the map in the world, in pixels
+    pub fn pixel_size(&self) -> (u32, u32) {
+        (self.width as u32 * self.cell_size(), self.height as u32 * self.cell_size())
+    }
+
+    // The tile in a cell of the map. Empty cells and cells outside of the map
+    // don't have a tile
+    pub fn tile(&self, column: usize, row: usize) -> Option<u32> {
+        if column >= self.width || row >= self.height {
+            return None;
+        }
+
+        match self.tiles[row * self.width + column] {
+            0 => None,
+            tile => Some(tile),
+        }
+    }
+
+    // How a cell collides. The cells outside of the map are empty, so the bodies
+    // can fall out of the map
+    pub fn collision(&self, column: usize, row: usize) -> Collision {
+        self.tile(column, row)
+            .and_then(|tile| self.collisions.get(&tile).copied())
+            .unwrap_or(Collision::Empty)
+    }
+
+    // The columns and rows of the cells that can be seen in a rectangle of the
+    // world. Only these cells are drawn, no matter how large the map is
+    pub fn visible(&self, view: Rect) -> (Range<usize>, Range<usize>) {
+        let cell = self.cell_size() as i32;
+        let range = |start: i32, length: u32, count: usize| {
+            let first = (start.max(0) / cell) as usize;
+            let last = ((start + length as i32 + cell - 1).max(0) / cell) as usize;
+            first.min(count)..last.min(count)
+        };
+
+        (
+            range(view.x(), view.width(), self.width),
+            range(view.y(), view.height(), self.height),
+        )
+    }
+
+    // The rectangle of the tileset with a tile. The number of columns of the
+    // tileset comes from the width of its image
+    pub fn source(&self, tile: u32, columns: u32) -> Rect {
+        let index = tile - 1;
+        let columns = columns.max(1);
+
+        Rect::new(
+            ((index % columns) * self.tile_size) as i32,
+            ((index / columns) * self.tile_size) as i32,
+            self.tile_size,
+            self.tile_size)
+    }
+
+    // The rectangle of the world covered by a cell
+    pub fn cell_rect(&self, column: usize, row: usize) -> Rect {
+        let cell = self.cell_size();
+
+        Rect::new(
+            column as i32 * cell as i32,
+            row as i32 * cell as i32,
+            cell,
+            cell)
+    }
+}
+
+fn parse_number(value: &str) -> Result<u32, String> {
+    value
+        .parse::<u32>()
+        .map_err(|_| format!("Invalid number: {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL: &str = "
+        # A small level
+        tileset = forest.bmp
+        tile = 32
+        scale = 2
+        color_key = 110 170 220
+        solid = 1 2
+        platform = 6
+        data:
+        0,0,6,0,
+        0,0,5,0,
+        1,1,1,2
+        end
+    ";
+
+    #[test]
+    fn test_parse() {
+        let map = TileMap::parse(LEVEL).unwrap();
+        assert_eq!(map.tileset, PathBuf::from("forest.bmp"));
+        assert_eq!((map.width, map.height), (4, 3));
+        assert_eq!(map.cell_size(), 64);
+        assert_eq!(map.color_key, Some(Color::RGB(110, 170, 220)));
+        assert_eq!(map.pixel_size(), (256, 192));
+
+        assert_eq!(map.tile(2, 0), Some(6));
+        assert_eq!(map.tile(3, 2), Some(2));
+        assert_eq!(map.tile(0, 0), None);
+        assert_eq!(map.tile(4, 0), None);
+
+        assert_eq!(map.collision(2, 0), Collision::Platform);
+        assert_eq!(map.collision(3, 2), Collision::Solid);
+        assert_eq!(map.collision(2, 1), Collision::Empty);
+        assert_eq!(map.collision(0, 5), Collision::Empty);
+
+        // Tile 6 is the second tile of the second row of a tileset of 4 columns
+        assert_eq!(map.source(6, 4), Rect::new(32, 32, 32, 32));
+        assert_eq!(map.cell_rect(1, 2), Rect::new(64, 128, 64, 64));
+
+        assert!(TileMap::parse("tile = 32\ndata:\n1,1\nend").is_err());
+        assert!(TileMap::parse("tileset = a.bmp\ntile = 32\ndata:\n1,1\n1\nend").is_err());
+        assert!(TileMap::parse("tileset = a.bmp\ntile = 32\ndata:\n1,1\n").is_err());
+        assert!(TileMap::parse("tileset = a.bmp\ntile = 32\ndata:\n1,x\nend").is_err());
+        assert!(TileMap::parse("tileset = a.bmp\ntile = 32\nlayer = a.csv\ndata:\n1\nend").is_err());
+    }
+
+    #[test]
+    fn test_layer() {
+        let level = "tileset = forest.bmp\ntile = 32\nsolid = 1\nlayer = forest.csv";
+        let mut map = TileMap::parse(level).unwrap();
+        assert_eq!(map.layer, Some(PathBuf::from("forest.csv")));
+
+        // The tiles of Tiled are counted from 0, and -1 is an empty cell
+        map.read_layer("-1,5,-1\n0,0,1\n").unwrap();
+        assert_eq!((map.width, map.height), (3, 2));
+        assert_eq!(map.tile(0, 0), None);
+        assert_eq!(map.tile(1, 0), Some(6));
+        assert_eq!(map.tile(2, 1), Some(2));
+        assert_eq!(map.collision(0, 1), Collision::Solid);
+
+        assert!(map.read_layer("0,1\n0").is_err());
+        assert!(map.read_layer("0,-2").is_err());
+        assert!(map.read_layer("").is_err());
+
+        // The layer is found next to the level
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("assets/forest.level");
+        let map = TileMap::load(&path).unwrap();
+        assert_eq!(map.layer, Some(path.with_file_name("forest.csv")));
+        assert_eq!((map.width, map.height), (40, 12));
+        assert_eq!(map.tile(0, 9), Some(1));
+    }
+
+    #[test]
+    fn test_visible() {
+        let map = TileMap::parse(LEVEL).unwrap();
+
+        assert_eq!(map.visible(Rect::new(0, 0, 64, 64)), (0..1, 0..1));
+        assert_eq!(map.visible(Rect::new(10, 70, 64, 64)), (0..2, 1..3));
+
+        // The cells outside of the map are never visible
+        assert_eq!(map.visible(Rect::new(-100, 100, 1000, 1000)), (0..4, 1..3));
+        assert_eq!(map.visible(Rect::new(500, 0, 64, 64)), (4..4, 0..1));
+    }
+}
@@ -0,0 +1,165 @@
+pub mod synth;
+
+use sdl2::audio::{AudioDevice, AudioSpecDesired};
+use sdl2::AudioSubsystem;
+
+use crate::settings::Settings;
+use crate::tetris::GameEvent;
+use synth::{midi_freq, Note, Synth, Wave};
+
+const SAMPLE_RATE: i32 = 44100;
+
+// The audio manager opens the audio device and translates the events of the
+// game into sounds. All the sounds are made by the synthesizer, so the game
+// doesn't need any sound file
+pub struct AudioManager {
+    device: AudioDevice<Synth>,
+}
+
+impl AudioManager {
+    pub fn new(audio: &AudioSubsystem) -> Result<Self, String> {
+        let desired = AudioSpecDesired {
+            freq: Some(SAMPLE_RATE),
+            channels: Some(1),
+            samples: Some(512),
+        };
+
+        let device = audio.open_playback(None, &desired, |spec| Synth::new(spec.freq))?;
+        device.resume();
+
+        Ok(AudioManager { device })
+    }
+
+    pub fn apply_settings(&mut self, settings: &Settings) {
+        let mut synth = self.device.lock();
+
+        synth.set_volume(settings.volume as f32 / 100.0);
+        synth.set_muted(settings.muted);
+
+        match settings.music {
+            true => synth.start_music(),
+            false => synth.stop_music(),
+        }
+    }
+
+    // The game over stops the music, so it is started again with each new game
+    pub fn start_game(&mut self, settings: &Settings) {
+        if settings.music && !settings.muted {
+            self.device.lock().start_music();
+        }
+    }
+
+    pub fn is_music_playing(&mut self) -> bool {
+        self.device.lock().is_music_playing()
+    }
+
+    // The music gets faster with the level, up to twice its starting tempo
+    pub fn set_level(&mut self, level: u32) {
+        let tempo = (120 + level * 8).min(240);
+        self.device.lock().set_tempo(tempo as f32);
+    }
+
+    pub fn play_events(&mut self, events: &[GameEvent]) {
+        if events.is_empty() {
+            return;
+        }
+
+        let mut synth = self.device.lock();
+
+        for event in events {
+            if *event == GameEvent::GameOver {
+                synth.stop_music();
+            }
+
+            synth.play(&event_sound(*event));
+        }
+    }
+}
+
+// Notes of an arpeggio that starts at a MIDI note, each one after the other
+fn arpeggio(wave: Wave, notes: &[u8], step: f32, volume: f32) -> Vec<Note> {
+    notes
+        .iter()
+        .enumerate()
+        .map(|(i, note)| {
+            Note::new(wave, midi_freq(*note), step * 1.5, volume).after(step * i as f32)
+        })
+        .collect()
+}
+
+pub fn event_sound(event: GameEvent) -> Vec<Note> {
+    match event {
+        GameEvent::Move => vec![Note::new(Wave::Square(0.5), 220.0, 0.03, 0.3)],
+        GameEvent::Rotate => vec![Note::new(Wave::Square(0.25), 330.0, 0.05, 0.3).slide(660.0)],
+        GameEvent::Hold => vec![Note::new(Wave::Triangle, 440.0, 0.08, 0.5).slide(330.0)],
+        GameEvent::Lock => vec![Note::new(Wave::Noise, 2000.0, 0.08, 0.5).slide(500.0)],
+        // Four lines at once is a tetris, and it has a longer and higher arpeggio
+        GameEvent::LineClear(4) => arpeggio(Wave::Square(0.5), &[72, 76, 79, 84, 88], 0.06, 0.4),
+        GameEvent::LineClear(_) => arpeggio(Wave::Square(0.5), &[72, 76, 79], 0.05, 0.4),
+        GameEvent::LevelUp(_) => {
+            vec![Note::new(Wave::Square(0.125), 440.0, 0.3, 0.4).slide(1760.0)]
+        }
+        GameEvent::GameOver => arpeggio(Wave::Triangle, &[69, 65, 62, 57], 0.2, 0.6),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sdl2::audio::AudioCallback;
+
+    #[test]
+    fn test_synth_render() {
+        let mut synth = Synth::new(SAMPLE_RATE);
+        let mut buffer = vec![0.0; 1024];
+
+        // Without any note the output is silent
+        synth.callback(&mut buffer);
+        assert!(buffer.iter().all(|sample| *sample == 0.0));
+
+        // Every event makes some sound, and the sound ends after a while
+        for event in [GameEvent::Move, GameEvent::Lock, GameEvent::LineClear(4)].iter() {
+            synth.play(&event_sound(*event));
+            synth.callback(&mut buffer);
+            assert!(buffer.iter().any(|sample| *sample != 0.0));
+            assert!(buffer.iter().all(|sample| sample.abs() <= 1.0));
+        }
+
+        let mut long_buffer = vec![0.0; SAMPLE_RATE as usize];
+        synth.callback(&mut long_buffer);
+        assert!(synth.is_silent());
+
+        // The muted synthesizer keeps playing the notes without any output
+        synth.set_muted(true);
+        synth.play(&event_sound(GameEvent::Rotate));
+        synth.callback(&mut buffer);
+        assert!(buffer.iter().all(|sample| *sample == 0.0));
+    }
+
+    #[test]
+    fn test_dummy_device() {
+        // The dummy driver plays the sound without any audio hardware
+        let _lock = common::golden::sdl_lock();
+        std::env::set_var("SDL_AUDIODRIVER", "dummy");
+
+        let ctx = sdl2::init().unwrap();
+        let mut audio = AudioManager::new(&ctx.audio().unwrap()).unwrap();
+
+        let mut settings = Settings::load(std::path::Path::new("missing_settings.cfg")).unwrap();
+        settings.volume = 50;
+        audio.apply_settings(&settings);
+        audio.set_level(5);
+        assert!(audio.is_music_playing());
+        audio.play_events(&[GameEvent::LineClear(2), GameEvent::GameOver]);
+        assert!(!audio.is_music_playing());
+
+        // The next game has music again, unless the sound is muted
+        audio.start_game(&settings);
+        assert!(audio.is_music_playing());
+
+        audio.play_events(&[GameEvent::GameOver]);
+        settings.muted = true;
+        audio.start_game(&settings);
+        assert!(!audio.is_music_playing());
+    }
+}
@@ -0,0 +1,290 @@
+use sdl2::audio::AudioCallback;
+
+// Shape of the wave produced by a voice. The square wave stores its duty
+// cycle, which is the fraction of the period where the wave is high
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Wave {
+    Square(f32),
+    Triangle,
+    Noise,
+}
+
+// A note is a sound with a wave, a frequency that can slide from the start to
+// the end of the note, a duration in seconds and a volume from 0 to 1. The delay
+// is the time in seconds to wait before the note starts, and it is used to play
+// several notes in sequence with a single call
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Note {
+    pub wave: Wave,
+    pub freq: f32,
+    pub end_freq: f32,
+    pub duration: f32,
+    pub volume: f32,
+    pub delay: f32,
+}
+
+impl Note {
+    pub fn new(wave: Wave, freq: f32, duration: f32, volume: f32) -> Self {
+        Note {
+            wave,
+            freq,
+            end_freq: freq,
+            duration,
+            volume,
+            delay: 0.0,
+        }
+    }
+
+    pub fn slide(self, end_freq: f32) -> Self {
+        Note { end_freq, ..self }
+    }
+
+    pub fn after(self, delay: f32) -> Self {
+        Note { delay, ..self }
+    }
+}
+
+// Frequency of a MIDI note number. The note 69 is the A at 440 Hz
+pub fn midi_freq(note: u8) -> f32 {
+    440.0 * 2f32.powf((note as f32 - 69.0) / 12.0)
+}
+
+// A voice plays a single note. It keeps the phase of the wave and the state
+// of the noise generator between samples
+struct Voice {
+    note: Note,
+    phase: f32,
+    sample: u32,      // Samples played since the voice was created
+    delay: u32,       // Samples to wait before the note starts
+    length: u32,      // Samples of the note without the delay
+    noise: u16,       // Linear feedback shift register used for the noise
+    noise_level: f32, // Current value of the noise, it changes once per period
+}
+
+impl Voice {
+    fn new(note: Note, sample_rate: f32) -> Self {
+        Voice {
+            note,
+            phase: 0.0,
+            sample: 0,
+            delay: (note.delay * sample_rate) as u32,
+            length: ((note.duration * sample_rate) as u32).max(1),
+            noise: 1,
+            noise_level: 1.0,
+        }
+    }
+
+    fn finished(&self) -> bool {
+        self.sample >= self.delay + self.length
+    }
+
+    fn next(&mut self, sample_rate: f32) -> f32 {
+        self.sample += 1;
+
+        if self.sample <= self.delay || self.finished() {
+            return 0.0;
+        }
+
+        // The progress goes from 0 to 1 during the note. It is used to slide the
+        // frequency and to fade the volume so the notes don't end with a click
+        let progress = (self.sample - self.delay) as f32 / self.length as f32;
+        let freq = self.note.freq + (self.note.end_freq - self.note.freq) * progress;
+        let envelope = 1.0 - progress;
+
+        let previous_phase = self.phase;
+        self.phase = (self.phase + freq / sample_rate).fract();
+
+        let value = match self.note.wave {
+            Wave::Square(duty) => {
+                if self.phase < duty {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Wave::Triangle => 1.0 - 4.0 * (self.phase - 0.5).abs(),
+            Wave::Noise => {
+                // The noise changes its value every time a period is completed,
+                // so the frequency of the note controls the pitch of the noise
+                if self.phase < previous_phase {
+                    let bit = (self.noise ^ (self.noise >> 1)) & 1;
+                    self.noise = (self.noise >> 1) | (bit << 14);
+                    self.noise_level = if self.noise & 1 == 1 { 1.0 } else { -1.0 };
+                }
+                self.noise_level
+            }
+        };
+
+        value * envelope * self.note.volume
+    }
+}
+
+// Melody and bass line of the music. Each value is a MIDI note and its length in
+// eighth notes, and the note 0 is a rest. The tune is the folk song Korobeiniki
+#[rustfmt::skip]
+const MELODY: [(u8, u32); 40] = [
+    (76, 2), (71, 1), (72, 1), (74, 2), (72, 1), (71, 1),
+    (69, 2), (69, 1), (72, 1), (76, 2), (74, 1), (72, 1),
+    (71, 3), (72, 1), (74, 2), (76, 2),
+    (72, 2), (69, 2), (69, 2), (0, 2),
+    (0, 1), (74, 2), (77, 1), (81, 2), (79, 1), (77, 1),
+    (76, 3), (72, 1), (76, 2), (74, 1), (72, 1),
+    (71, 2), (71, 1), (72, 1), (74, 2), (76, 2),
+    (72, 2), (69, 2), (69, 2), (0, 2),
+];
+
+// Root note of the bass for each bar of eight eighth notes
+const BASS: [u8; 8] = [40, 45, 44, 45, 38, 36, 47, 45];
+
+// The sequencer moves through the music one eighth note at a time and starts
+// the voices of the melody and the bass
+struct Sequencer {
+    tempo: f32,     // Beats per minute
+    countdown: f32, // Samples left for the current eighth note
+    eighth: u32,    // Number of eighth notes played since the start of the loop
+    melody_index: usize,
+    melody_left: u32, // Eighth notes left for the current melody note
+}
+
+impl Sequencer {
+    fn new() -> Self {
+        Sequencer {
+            tempo: 120.0,
+            countdown: 0.0,
+            eighth: 0,
+            melody_index: 0,
+            melody_left: 0,
+        }
+    }
+
+    fn eighth_duration(&self) -> f32 {
+        // Seconds of an eighth note, half of a beat
+        30.0 / self.tempo
+    }
+
+    fn next(&mut self, sample_rate: f32, voices: &mut Vec<Voice>) {
+        self.countdown -= 1.0;
+        if self.countdown > 0.0 {
+            return;
+        }
+
+        let eighth = self.eighth_duration();
+        self.countdown += eighth * sample_rate;
+
+        // Starting a new melody note once the previous one has finished
+        if self.melody_left == 0 {
+            let (note, length) = MELODY[self.melody_index];
+            self.melody_index = (self.melody_index + 1) % MELODY.len();
+            self.melody_left = length;
+
+            if note != 0 {
+                let duration = eighth * length as f32 * 0.9;
+                let note = Note::new(Wave::Square(0.25), midi_freq(note), duration, 0.25);
+                voices.push(Voice::new(note, sample_rate));
+            }
+        }
+        self.melody_left -= 1;
+
+        // The bass alternates between the root of the bar and its octave
+        let bar = (self.eighth / 8) as usize % BASS.len();
+        let root = BASS[bar] + (self.eighth % 2) as u8 * 12;
+        let bass = Note::new(Wave::Triangle, midi_freq(root), eighth * 0.8, 0.35);
+        voices.push(Voice::new(bass, sample_rate));
+
+        self.eighth = (self.eighth + 1) % (8 * BASS.len() as u32);
+    }
+}
+
+// Software synthesizer used as the audio callback. SDL calls it from the audio
+// thread every time it needs more samples, and the game changes it through the
+// lock of the audio device
+pub struct Synth {
+    sample_rate: f32,
+    volume: f32,
+    muted: bool,
+    effects: Vec<Voice>,
+    music: Vec<Voice>,
+    sequencer: Option<Sequencer>,
+}
+
+impl Synth {
+    pub fn new(sample_rate: i32) -> Self {
+        Synth {
+            sample_rate: sample_rate as f32,
+            volume: 1.0,
+            muted: false,
+            effects: Vec::new(),
+            music: Vec::new(),
+            sequencer: None,
+        }
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn set_muted(&mut self, muted: bool) {
+        self.muted = muted;
+    }
+
+    pub fn play(&mut self, notes: &[Note]) {
+        let sample_rate = self.sample_rate;
+        self.effects
+            .extend(notes.iter().map(|note| Voice::new(*note, sample_rate)));
+    }
+
+    pub fn start_music(&mut self) {
+        if self.sequencer.is_none() {
+            self.sequencer = Some(Sequencer::new());
+        }
+    }
+
+    pub fn is_music_playing(&self) -> bool {
+        self.sequencer.is_some()
+    }
+
+    pub fn stop_music(&mut self) {
+        self.sequencer = None;
+        self.music.clear();
+    }
+
+    pub fn set_tempo(&mut self, tempo: f32) {
+        if let Some(sequencer) = self.sequencer.as_mut() {
+            sequencer.tempo = tempo;
+        }
+    }
+
+    pub fn is_silent(&self) -> bool {
+        self.effects.is_empty() && self.music.is_empty()
+    }
+
+    fn next_sample(&mut self) -> f32 {
+        let sample_rate = self.sample_rate;
+
+        if let Some(sequencer) = self.sequencer.as_mut() {
+            sequencer.next(sample_rate, &mut self.music);
+        }
+
+        let effects: f32 = self.effects.iter_mut().map(|v| v.next(sample_rate)).sum();
+        let music: f32 = self.music.iter_mut().map(|v| v.next(sample_rate)).sum();
+
+        // The music is played lower than the effects so the effects can be heard
+        (effects * 0.5 + music * 0.3).clamp(-1.0, 1.0)
+    }
+}
+
+impl AudioCallback for Synth {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        let gain = if self.muted { 0.0 } else { self.volume };
+
+        for sample in out.iter_mut() {
+            *sample = self.next_sample() * gain;
+        }
+
+        // Removing the voices that have finished after filling the buffer
+        self.effects.retain(|voice| !voice.finished());
+        self.music.retain(|voice| !voice.finished());
+    }
+}
@@ -0,0 +1,197 @@
+use std::path::PathBuf;
+
+use common::assets::{self, Assets, Handle};
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::Keycode;
+use sdl2::video::FullscreenType;
+
+use crate::audio::AudioManager;
+use crate::input::{Gamepads, InputMap};
+use crate::scores::HighScores;
+use crate::settings::Settings;
+use crate::surfaces::{self, debug::DebugOverlay, theme::Theme};
+use crate::tetris::rules::Ruleset;
+
+// Everything the game needs to read the input and to show the game to the player
+pub struct Frontend<'a> {
+    pub event_pump: sdl2::EventPump,
+    pub textures: surfaces::TextureManager<'a>,
+    pub input: InputMap,
+    pub gamepads: Gamepads,
+    pub audio: Option<AudioManager>,
+    pub settings: Settings,
+    pub themes: Vec<Theme>,
+    pub theme_files: Assets<Theme>,
+    pub theme_handles: Vec<(Handle<Theme>, usize)>, // Index in the themes of each theme file
+    pub debug: Option<DebugOverlay>,
+    pub scores: HighScores,
+    pub rules: Ruleset,              // Rules of the normal game
+    pub puzzle_path: PathBuf,        // Pack file or folder of the puzzle mode
+    pub stats_path: Option<PathBuf>, // File where the statistics are saved
+}
+
+impl Frontend<'_> {
+    // Changes the sound settings with the keys M (mute) and -/= (volume). Returns
+    // false if the key is not used for the sound
+    pub fn sound_key(&mut self, keycode: Keycode) -> Result<bool, String> {
+        match keycode {
+            Keycode::M => self.settings.muted = !self.settings.muted,
+            Keycode::Minus => self.settings.volume = self.settings.volume.saturating_sub(10),
+            Keycode::Equals => self.settings.volume = (self.settings.volume + 10).min(100),
+            _ => return Ok(false),
+        }
+
+        if let Some(audio) = self.audio.as_mut() {
+            audio.apply_settings(&self.settings);
+        }
+        self.settings.save()?;
+
+        Ok(true)
+    }
+
+    // Handles the changes of the window: the new sizes, the fullscreen with F11, the
+    // scale mode with F10, the theme with F9, the palette with F8, the patterns
+    // with F7 and the frame rate with F3. Returns false if the event is not used
+    // for the window
+    pub fn window_event(&mut self, event: &Event) -> Result<bool, String> {
+        // F12 takes a screenshot of the next frame
+        if self.textures.screenshots.handle_event(event) {
+            return Ok(true);
+        }
+
+        match event {
+            Event::Window {
+                win_event: WindowEvent::Resized(..) | WindowEvent::SizeChanged(..),
+                ..
+            } => {}
+            Event::KeyDown {
+                keycode: Some(Keycode::F11),
+                repeat: false,
+                ..
+            } => {
+                self.settings.fullscreen = !self.settings.fullscreen;
+                self.apply_window_settings()?;
+                self.settings.save()?;
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F10),
+                repeat: false,
+                ..
+            } => {
+                self.settings.scale = self.settings.scale.toggle();
+                self.settings.save()?;
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F9),
+                repeat: false,
+                ..
+            } => {
+                let current = self.theme_index();
+                let theme = &self.themes[(current + 1) % self.themes.len()];
+
+                self.settings.theme = theme.name.clone();
+                self.load_theme()?;
+                self.settings.save()?;
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F8),
+                repeat: false,
+                ..
+            } => {
+                self.settings.palette = self.settings.palette.next();
+                self.load_theme()?;
+                self.settings.save()?;
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F7),
+                repeat: false,
+                ..
+            } => {
+                self.settings.patterns = !self.settings.patterns;
+                self.load_theme()?;
+                self.settings.save()?;
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::F3),
+                repeat: false,
+                ..
+            } => {
+                self.settings.show_fps = !self.settings.show_fps;
+                self.textures.show_fps = self.settings.show_fps;
+                self.settings.save()?;
+            }
+            _ => return Ok(false),
+        }
+
+        self.textures.update_layout(self.settings.scale)?;
+
+        Ok(true)
+    }
+
+    // The theme of the settings, or the classic theme if it doesn't exist anymore
+    pub fn theme_index(&self) -> usize {
+        self.themes
+            .iter()
+            .position(|theme| theme.name == self.settings.theme)
+            .unwrap_or(0)
+    }
+
+    pub fn apply_window_settings(&mut self) -> Result<(), String> {
+        // The fullscreen uses the size of the desktop, so the game doesn't change
+        // the resolution of the screen
+        let fullscreen = match self.settings.fullscreen {
+            true => FullscreenType::Desktop,
+            false => FullscreenType::Off,
+        };
+
+        self.textures
+            .canvas
+            .window_mut()
+            .set_fullscreen(fullscreen)?;
+        self.textures.update_layout(self.settings.scale)?;
+        self.load_theme()
+    }
+
+    // The theme files and the block images that have changed are loaded again, and
+    // the textures are made again when the current theme or its image are one of them
+    pub fn reload_themes(&mut self) -> Result<(), String> {
+        let current = self.theme_index();
+        let mut changed = false;
+
+        for handle in self.theme_files.poll(Theme::load) {
+            let index = self
+                .theme_handles
+                .iter()
+                .find(|(theme, _)| *theme == handle)
+                .map(|(_, index)| *index);
+
+            if let Some(index) = index {
+                self.themes[index] = self.theme_files.get(handle).clone();
+                changed |= index == current;
+            }
+        }
+
+        for handle in self.textures.images.poll(assets::load_bmp) {
+            let path = self.textures.images.path(handle);
+            changed |= self.themes[current]
+                .image
+                .as_ref()
+                .is_some_and(|image| image.path == path);
+        }
+
+        // The name may have changed in the file
+        if changed {
+            self.settings.theme = self.themes[current].name.clone();
+            self.load_theme()?;
+        }
+
+        Ok(())
+    }
+
+    // Makes the textures again with the theme, the palette and the patterns of
+    // the settings
+    pub fn load_theme(&mut self) -> Result<(), String> {
+        let theme = self.themes[self.theme_index()].with_palette(self.settings.palette);
+        self.textures.load_textures(&theme, self.settings.patterns)
+    }
+}
@@ -0,0 +1,465 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::GameControllerSubsystem;
+
+use crate::tetris::actions::Action;
+
+// The axis of a gamepad has to be pushed further than this value to count
+// as a pressed direction. The values of the axis go from -32768 to 32767
+const AXIS_THRESHOLD: i16 = 16000;
+
+// An input that can be bound to an action. The axis binding stores the
+// direction in which the axis has to be pushed
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(Keycode),
+    Button(Button),
+    Axis(Axis, bool), // true for the positive direction of the axis
+}
+
+impl Binding {
+    // Text used to store the binding in the configuration file, for example
+    // `key:Left`, `button:a` or `axis:leftx-`
+    pub fn to_config(&self) -> String {
+        match self {
+            Binding::Key(keycode) => format!("key:{}", keycode.name()),
+            Binding::Button(button) => format!("button:{}", button.string()),
+            Binding::Axis(axis, positive) => {
+                format!(
+                    "axis:{}{}",
+                    axis.string(),
+                    if *positive { "+" } else { "-" }
+                )
+            }
+        }
+    }
+
+    pub fn from_config(text: &str) -> Option<Binding> {
+        let text = text.trim();
+
+        if let Some(name) = text.strip_prefix("key:") {
+            Keycode::from_name(name).map(Binding::Key)
+        } else if let Some(name) = text.strip_prefix("button:") {
+            Button::from_string(name).map(Binding::Button)
+        } else if let Some(name) = text.strip_prefix("axis:") {
+            let positive = name.ends_with('+');
+            let name = name.trim_end_matches(['+', '-']);
+            Axis::from_string(name).map(|axis| Binding::Axis(axis, positive))
+        } else {
+            None
+        }
+    }
+
+    // Text shown in the controls screen
+    pub fn label(&self) -> String {
+        match self {
+            Binding::Key(keycode) => keycode.name(),
+            Binding::Button(button) => format!("Pad {}", button.string()),
+            Binding::Axis(axis, positive) => {
+                format!("Pad {}{}", axis.string(), if *positive { "+" } else { "-" })
+            }
+        }
+    }
+
+    // The binding that corresponds to an event. This is used while rebinding
+    // an action to capture the next key, button or axis that is used
+    pub fn from_event(event: &Event) -> Option<Binding> {
+        match event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                repeat: false,
+                ..
+            } => Some(Binding::Key(*keycode)),
+            Event::ControllerButtonDown { button, .. } => Some(Binding::Button(*button)),
+            Event::ControllerAxisMotion { axis, value, .. } if value.abs() > AXIS_THRESHOLD => {
+                Some(Binding::Axis(*axis, *value > 0))
+            }
+            _ => None,
+        }
+    }
+}
+
+// The input map translates the keyboard and gamepad events into game actions.
+// Each action can have any number of bindings, and the bindings are stored in a
+// configuration file so they can be changed without modifying the game
+pub struct InputMap {
+    path: PathBuf,
+    bindings: HashMap<Action, Vec<Binding>>,
+    axis_state: HashMap<Axis, i8>, // Direction in which each axis is being pushed
+}
+
+impl InputMap {
+    pub fn default_bindings() -> HashMap<Action, Vec<Binding>> {
+        let mut bindings = HashMap::new();
+
+        bindings.insert(
+            Action::MoveLeft,
+            vec![
+                Binding::Key(Keycode::Left),
+                Binding::Button(Button::DPadLeft),
+                Binding::Axis(Axis::LeftX, false),
+            ],
+        );
+        bindings.insert(
+            Action::MoveRight,
+            vec![
+                Binding::Key(Keycode::Right),
+                Binding::Button(Button::DPadRight),
+                Binding::Axis(Axis::LeftX, true),
+            ],
+        );
+        bindings.insert(
+            Action::SoftDrop,
+            vec![
+                Binding::Key(Keycode::Down),
+                Binding::Button(Button::DPadDown),
+                Binding::Axis(Axis::LeftY, true),
+            ],
+        );
+        bindings.insert(
+            Action::HardDrop,
+            vec![
+                Binding::Key(Keycode::Space),
+                Binding::Button(Button::DPadUp),
+            ],
+        );
+        bindings.insert(
+            Action::RotateCw,
+            vec![
+                Binding::Key(Keycode::Up),
+                Binding::Key(Keycode::X),
+                Binding::Button(Button::A),
+            ],
+        );
+        bindings.insert(
+            Action::RotateCcw,
+            vec![Binding::Key(Keycode::Z), Binding::Button(Button::B)],
+        );
+        bindings.insert(
+            Action::Rotate180,
+            vec![Binding::Key(Keycode::A), Binding::Button(Button::Y)],
+        );
+        bindings.insert(
+            Action::Hold,
+            vec![
+                Binding::Key(Keycode::C),
+                Binding::Button(Button::LeftShoulder),
+            ],
+        );
+        bindings.insert(
+            Action::Pause,
+            vec![Binding::Key(Keycode::P), Binding::Button(Button::Start)],
+        );
+
+        bindings
+    }
+
+    // Loads the bindings from the configuration file. If the file doesn't exist
+    // the default bindings are used, and they will be written when saved
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut input = InputMap {
+            path: path.to_path_buf(),
+            bindings: InputMap::default_bindings(),
+            axis_state: HashMap::new(),
+        };
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(_) => return Ok(input),
+        };
+
+        // The file has one line per action: `action = binding, binding, ...`
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (name, value) = match line.find('=') {
+                Some(index) => (line[..index].trim(), &line[index + 1..]),
+                None => return Err(format!("Invalid controls line: {}", line)),
+            };
+
+            let action =
+                Action::from_name(name).ok_or(format!("Unknown action in controls: {}", name))?;
+
+            let bindings = value
+                .split(',')
+                .filter(|binding| !binding.trim().is_empty())
+                .map(|binding| {
+                    Binding::from_config(binding).ok_or(format!("Invalid binding: {}", binding))
+                })
+                .collect::<Result<Vec<Binding>, String>>()?;
+
+            input.bindings.insert(action, bindings);
+        }
+
+        Ok(input)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let mut text = String::from("# action = binding, binding, ...\n");
+
+        for action in Action::ALL.iter() {
+            let bindings: Vec<String> = self
+                .bindings(*action)
+                .iter()
+                .map(Binding::to_config)
+                .collect();
+
+            text.push_str(&format!("{} = {}\n", action.name(), bindings.join(", ")));
+        }
+
+        fs::write(&self.path, text).map_err(|e| e.to_string())
+    }
+
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings
+            .get(&action)
+            .map(|bindings| bindings.as_slice())
+            .unwrap_or(&[])
+    }
+
+    pub fn bind(&mut self, action: Action, binding: Binding) {
+        // An input can only trigger one action, so it is removed from the
+        // action that was using it before
+        for bindings in self.bindings.values_mut() {
+            bindings.retain(|used| *used != binding);
+        }
+
+        self.bindings.entry(action).or_default().push(binding);
+    }
+
+    pub fn clear(&mut self, action: Action) {
+        self.bindings.remove(&action);
+    }
+
+    pub fn reset(&mut self) {
+        self.bindings = InputMap::default_bindings();
+    }
+
+    fn actions_for(&self, binding: Binding) -> impl Iterator<Item = Action> + '_ {
+        self.bindings
+            .iter()
+            .filter(move |(_, bindings)| bindings.contains(&binding))
+            .map(|(action, _)| *action)
+    }
+
+    // Translates an event into the actions that have been pressed (true) or
+    // released (false). The key repeats from the system are ignored because the
+    // game has its own repetition for the held movements
+    pub fn actions(&mut self, event: &Event) -> Vec<(Action, bool)> {
+        match event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                repeat: false,
+                ..
+            } => self
+                .actions_for(Binding::Key(*keycode))
+                .map(|action| (action, true))
+                .collect(),
+            Event::KeyUp {
+                keycode: Some(keycode),
+                ..
+            } => self
+                .actions_for(Binding::Key(*keycode))
+                .map(|action| (action, false))
+                .collect(),
+            Event::ControllerButtonDown { button, .. } => self
+                .actions_for(Binding::Button(*button))
+                .map(|action| (action, true))
+                .collect(),
+            Event::ControllerButtonUp { button, .. } => self
+                .actions_for(Binding::Button(*button))
+                .map(|action| (action, false))
+                .collect(),
+            Event::ControllerAxisMotion { axis, value, .. } => self.axis_actions(*axis, *value),
+            _ => Vec::new(),
+        }
+    }
+
+    fn axis_actions(&mut self, axis: Axis, value: i16) -> Vec<(Action, bool)> {
+        // The axis works like two buttons, one for each direction. An action is
+        // only pressed or released when the axis changes its direction
+        let direction = if value > AXIS_THRESHOLD {
+            1
+        } else if value < -AXIS_THRESHOLD {
+            -1
+        } else {
+            0
+        };
+
+        let previous = self.axis_state.insert(axis, direction).unwrap_or(0);
+        if previous == direction {
+            return Vec::new();
+        }
+
+        let mut actions = Vec::new();
+
+        if previous != 0 {
+            let released = Binding::Axis(axis, previous > 0);
+            actions.extend(self.actions_for(released).map(|action| (action, false)));
+        }
+
+        if direction != 0 {
+            let pressed = Binding::Axis(axis, direction > 0);
+            actions.extend(self.actions_for(pressed).map(|action| (action, true)));
+        }
+
+        actions
+    }
+}
+
+// The gamepads have to be opened to receive their events, and they stay open
+// while they are stored in this struct
+pub struct Gamepads {
+    subsystem: GameControllerSubsystem,
+    open: Vec<GameController>,
+}
+
+impl Gamepads {
+    pub fn new(subsystem: GameControllerSubsystem) -> Self {
+        Gamepads {
+            subsystem,
+            open: Vec::new(),
+        }
+    }
+
+    pub fn handle_event(&mut self, event: &Event) {
+        // SDL sends an added event for every gamepad that is connected at start,
+        // so there is no need to look for the gamepads when the game starts
+        match event {
+            Event::ControllerDeviceAdded { which, .. } => {
+                if let Ok(controller) = self.subsystem.open(*which) {
+                    self.open.push(controller);
+                }
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                self.open
+                    .retain(|controller| controller.instance_id() != *which);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input_map(text: &str, name: &str) -> InputMap {
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, text).unwrap();
+        InputMap::load(&path).unwrap()
+    }
+
+    #[test]
+    fn test_config() {
+        let bindings = [
+            Binding::Key(Keycode::Left),
+            Binding::Button(Button::DPadUp),
+            Binding::Axis(Axis::LeftY, false),
+        ];
+
+        for binding in bindings.iter() {
+            assert_eq!(Binding::from_config(&binding.to_config()), Some(*binding));
+        }
+
+        assert_eq!(
+            Binding::from_config(" axis:leftx+ "),
+            Some(Binding::Axis(Axis::LeftX, true))
+        );
+        assert_eq!(Binding::from_config("key:NotAKey"), None);
+        assert_eq!(Binding::from_config("mouse:left"), None);
+    }
+
+    #[test]
+    fn test_load() {
+        // The actions in the file replace their default bindings, and the other
+        // actions keep the defaults
+        let input = input_map(
+            "# controls\nhold = key:Q, button:x\n\nhard_drop =\n",
+            "part_5_controls_load.cfg",
+        );
+
+        assert_eq!(
+            input.bindings(Action::Hold),
+            [Binding::Key(Keycode::Q), Binding::Button(Button::X)]
+        );
+        assert!(input.bindings(Action::HardDrop).is_empty());
+        assert_eq!(
+            input.bindings(Action::Pause),
+            InputMap::default_bindings()[&Action::Pause].as_slice()
+        );
+
+        let path = std::env::temp_dir().join("part_5_controls_invalid.cfg");
+        fs::write(&path, "jump = key:Space\n").unwrap();
+        assert!(InputMap::load(&path).is_err());
+        fs::write(&path, "hold = key:NotAKey\n").unwrap();
+        assert!(InputMap::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut input = input_map("", "part_5_controls_save.cfg");
+        input.bind(Action::Hold, Binding::Axis(Axis::RightX, true));
+        input.clear(Action::Rotate180);
+        input.save().unwrap();
+
+        let loaded = InputMap::load(&input.path).unwrap();
+        for action in Action::ALL.iter() {
+            assert_eq!(loaded.bindings(*action), input.bindings(*action));
+        }
+    }
+
+    #[test]
+    fn test_bind() {
+        // A binding moves from the action that had it to the new action
+        let mut input = input_map("", "part_5_controls_bind.cfg");
+        input.bind(Action::Hold, Binding::Key(Keycode::Space));
+
+        assert!(!input
+            .bindings(Action::HardDrop)
+            .contains(&Binding::Key(Keycode::Space)));
+        assert_eq!(
+            input.bindings(Action::Hold).last(),
+            Some(&Binding::Key(Keycode::Space))
+        );
+
+        input.reset();
+        assert!(input
+            .bindings(Action::HardDrop)
+            .contains(&Binding::Key(Keycode::Space)));
+    }
+
+    #[test]
+    fn test_axis_actions() {
+        let mut input = input_map("", "part_5_controls_axis.cfg");
+
+        // Small movements around the center don't press anything
+        assert!(input.axis_actions(Axis::LeftX, 8000).is_empty());
+
+        assert_eq!(
+            input.axis_actions(Axis::LeftX, 20000),
+            [(Action::MoveRight, true)]
+        );
+        assert!(input.axis_actions(Axis::LeftX, 30000).is_empty());
+
+        // Going to the other side releases the first direction and presses the other
+        assert_eq!(
+            input.axis_actions(Axis::LeftX, -20000),
+            [(Action::MoveRight, false), (Action::MoveLeft, true)]
+        );
+        assert_eq!(
+            input.axis_actions(Axis::LeftX, 0),
+            [(Action::MoveLeft, false)]
+        );
+
+        // An axis without bindings changes its state but gives no actions
+        assert!(input.axis_actions(Axis::RightY, 20000).is_empty());
+    }
+}
@@ -1,49 +1,181 @@
+pub mod audio;
+pub mod frontend;
+pub mod input;
+pub mod scenes;
+pub mod scores;
+pub mod settings;
 pub mod surfaces;
+pub mod terminal;
 pub mod tetris;
 
-use std::time::Instant;
+use std::env;
+use std::path::{Path, PathBuf};
+
+use common::assets::{Assets, SearchPath};
+use common::timing::FrameLimiter;
+
+use audio::AudioManager;
+use frontend::Frontend;
+use input::{Gamepads, InputMap};
+use scenes::{Game, Scene};
+use scores::HighScores;
+use settings::Settings;
+use surfaces::theme::{self, Theme};
+use tetris::rules::Ruleset;
 
 const WINDOW_NAME: &str = "Tetris";
 const WINDOW_HEIGHT: u32 = 800;
 const WINDOW_WIDTH: u32 = 600;
+const PUZZLE_FOLDER: &str = "puzzles";
+const PUZZLE_PROGRESS: &str = "puzzle_progress.txt";
+const CONTROLS_FILE: &str = "controls.cfg";
+const SETTINGS_FILE: &str = "settings.cfg";
+const THEME_FOLDER: &str = "themes";
+const HIGH_SCORES: &str = "highscores.txt";
 
 fn main() -> Result<(), String> {
+    // The game starts in the main menu. Running the game with `--puzzle [path]` starts
+    // the puzzle mode instead, and the puzzles of the mode select use the same path.
+    // The path can be a pack file or a folder with pack files. By default the puzzles
+    // folder is used.
+    // The option `--rules <name>` selects the speed of the game: guideline, nes or arcade.
+    // The option `--stats <path>` saves the statistics when a game is over, as CSV if
+    // the path ends with .csv or as JSON otherwise.
+    // The option `--terminal` plays a game in the terminal instead of the window
+    //
+    // The themes and the puzzles that come with the game are searched next to the
+    // executable and in the folders above it, while the files written by the game
+    // are kept in the current folder
+    let search = SearchPath::from_exe();
+    let mut puzzle_path = search.find(PUZZLE_FOLDER);
+    let mut start_puzzles = false;
+    let mut rules = Ruleset::guideline();
+    let mut stats_path = None;
+    let mut use_terminal = false;
+
+    let mut args = env::args().skip(1).peekable();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--puzzle" => {
+                if let Some(path) = args.next_if(|path| !path.starts_with("--")) {
+                    puzzle_path = PathBuf::from(path);
+                }
+                start_puzzles = true;
+            }
+            "--rules" => {
+                let name = args.next().unwrap_or_default();
+                rules = Ruleset::from_name(&name).ok_or(format!("Unknown rules: {}", name))?;
+            }
+            "--stats" => {
+                let path = args.next().ok_or("The option --stats needs a path")?;
+                stats_path = Some(PathBuf::from(path));
+            }
+            "--terminal" => use_terminal = true,
+            _ => return Err(format!("Unknown option: {}", arg)),
+        }
+    }
+
+    // The terminal frontend doesn't use SDL, so it works without a display
+    if use_terminal {
+        return terminal::run(rules, stats_path.as_deref());
+    }
+
     let ctx = sdl2::init()?;
     let video = ctx.video()?;
 
     let window = video
         .window(WINDOW_NAME, WINDOW_WIDTH, WINDOW_HEIGHT)
         .position_centered()
+        .resizable()
         .build()
         .map_err(|e| e.to_string())?;
 
-    let mut canvas = window
-        .into_canvas()
-        .target_texture()
-        .present_vsync()
-        .build()
-        .map_err(|e| e.to_string())?;
+    // The settings are needed to create the canvas, because the vsync can be
+    // switched off to let the frame limit control the frame rate
+    let settings = Settings::load(Path::new(SETTINGS_FILE))?;
+
+    let mut canvas = window.into_canvas().target_texture();
+    if settings.vsync {
+        canvas = canvas.present_vsync();
+    }
+    let mut canvas = canvas.build().map_err(|e| e.to_string())?;
 
     let creator = canvas.texture_creator();
 
     // Texture manager is used to create and load all the textures that will be used
     // in the game.  It will also be used to control everything related to drawing
     // the tetris shapes and the board.
+    // The textures are made from the theme selected in the settings, which is loaded
+    // with the rest of the settings below
     let mut textures = surfaces::TextureManager::new(&mut canvas, &creator);
-    textures.load_textures()?;
+    textures.show_fps = settings.show_fps;
+    if !settings.vsync && settings.frame_limit > 0 {
+        textures.limiter = FrameLimiter::new(Some(settings.frame_limit));
+    }
 
-    // The tetris object will manage all the logic of the game and its events.
-    let mut tetris = tetris::Tetris::new();
+    // The theme files are kept in an asset manager so they are loaded again when
+    // they change. The classic theme is always the first one, and each file
+    // remembers the theme it makes. A theme that can't be loaded is left out, so
+    // the game still starts with the other themes
+    let mut theme_files = Assets::new(search.clone());
+    let mut theme_handles = Vec::new();
+    let mut themes = vec![Theme::classic()];
+    for path in theme::theme_files(&search.find(THEME_FOLDER)) {
+        let handle = match theme_files.load(&path, Theme::load) {
+            Ok(handle) => handle,
+            Err(e) => {
+                println!("Unable to load the theme {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        theme_handles.push((handle, themes.len()));
+        themes.push(theme_files.get(handle).clone());
+    }
+
+    // The input map translates the keys and gamepad buttons into game actions.
+    // The gamepads are opened as soon as SDL reports that they are connected
+    let input = InputMap::load(Path::new(CONTROLS_FILE))?;
+    let gamepads = Gamepads::new(ctx.game_controller()?);
 
-    let mut event_pump = ctx.event_pump()?;
-    let timer = Instant::now();
+    // The game can be played without sound if the audio device can't be opened
+    let mut audio = match ctx.audio().and_then(|audio| AudioManager::new(&audio)) {
+        Ok(audio) => Some(audio),
+        Err(e) => {
+            println!("Playing without sound: {}", e);
+            None
+        }
+    };
 
-    while tetris.running {
-        tetris.manage_events(&mut event_pump, &timer);
-        textures.draw_game(&tetris)?;
+    if let Some(audio) = audio.as_mut() {
+        audio.apply_settings(&settings);
     }
 
-    println!("Game over!\n Your score is: {}", tetris.score);
+    let mut frontend = Frontend {
+        event_pump: ctx.event_pump()?,
+        textures,
+        input,
+        gamepads,
+        audio,
+        settings,
+        themes,
+        theme_files,
+        theme_handles,
+        debug: None,
+        scores: HighScores::load(Path::new(HIGH_SCORES))?,
+        rules,
+        puzzle_path,
+        stats_path,
+    };
+    frontend.apply_window_settings()?;
+
+    // The scenes are kept in a stack with the main menu at the bottom. The puzzle
+    // mode is pushed on top of it, so the main menu is shown when it is left
+    let mut stack = vec![scenes::main_menu()];
+    if start_puzzles {
+        let path = frontend.puzzle_path.clone();
+        let game = Game::puzzles(&path, rules, &mut frontend)?;
+        stack.push(Scene::Game(Box::new(game)));
+    }
 
-    Ok(())
+    scenes::run(stack, &mut frontend)
 }
@@ -0,0 +1,172 @@
+use sdl2::controller::Button;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+use sdl2::mouse::MouseButton;
+
+// Position of the items of the menus in the design. The title is drawn two rows
+// above the first item
+pub const MENU_TOP: i32 = 220;
+pub const MENU_LINE: i32 = 36;
+
+// The input of the menus. The keyboard, the mouse and the gamepads are turned
+// into the same few inputs, so the menus don't care where they come from
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back,
+    Hover(i32, i32), // Position of the mouse in the window
+    Click(i32, i32),
+}
+
+impl MenuInput {
+    pub fn from_event(event: &Event) -> Option<MenuInput> {
+        match event {
+            Event::KeyDown {
+                keycode: Some(keycode),
+                ..
+            } => match keycode {
+                Keycode::Up => Some(MenuInput::Up),
+                Keycode::Down => Some(MenuInput::Down),
+                Keycode::Left => Some(MenuInput::Left),
+                Keycode::Right => Some(MenuInput::Right),
+                Keycode::Return | Keycode::KpEnter | Keycode::Space => Some(MenuInput::Select),
+                Keycode::Escape | Keycode::Backspace => Some(MenuInput::Back),
+                _ => None,
+            },
+            Event::ControllerButtonDown { button, .. } => match button {
+                Button::DPadUp => Some(MenuInput::Up),
+                Button::DPadDown => Some(MenuInput::Down),
+                Button::DPadLeft => Some(MenuInput::Left),
+                Button::DPadRight => Some(MenuInput::Right),
+                Button::A | Button::Start => Some(MenuInput::Select),
+                Button::B | Button::Back => Some(MenuInput::Back),
+                _ => None,
+            },
+            Event::MouseMotion { x, y, .. } => Some(MenuInput::Hover(*x, *y)),
+            Event::MouseButtonDown {
+                mouse_btn, x, y, ..
+            } => match mouse_btn {
+                MouseButton::Left => Some(MenuInput::Click(*x, *y)),
+                MouseButton::Right => Some(MenuInput::Back),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+// What the scene has to do after an input
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MenuAction<T> {
+    None,
+    Activate(T),
+    Change(T, i32), // The value of the item goes down (-1) or up (1)
+    Back,
+}
+
+// A list of items where one of them is selected. The items are usually an enum
+// and the scene decides what each one of them does
+pub struct Menu<T> {
+    pub title: &'static str,
+    pub items: Vec<T>,
+    pub selected: usize,
+}
+
+impl<T: Copy> Menu<T> {
+    pub fn new(title: &'static str, items: Vec<T>) -> Self {
+        Menu {
+            title,
+            items,
+            selected: 0,
+        }
+    }
+
+    pub fn current(&self) -> T {
+        self.items[self.selected]
+    }
+
+    // Item drawn at a vertical position of the design. The items are centered
+    // on their line
+    pub fn item_at(&self, y: i32) -> Option<usize> {
+        let index = (y - MENU_TOP + MENU_LINE / 2).div_euclid(MENU_LINE);
+        Some(index as usize).filter(|_| index >= 0 && (index as usize) < self.items.len())
+    }
+
+    // Moves the selection with the input and tells the scene what to do. The
+    // mouse positions are changed to the design with the given function
+    pub fn handle(
+        &mut self,
+        input: MenuInput,
+        to_design: impl Fn(i32, i32) -> (i32, i32),
+    ) -> MenuAction<T> {
+        let count = self.items.len();
+
+        match input {
+            MenuInput::Up => self.selected = (self.selected + count - 1) % count,
+            MenuInput::Down => self.selected = (self.selected + 1) % count,
+            MenuInput::Left => return MenuAction::Change(self.current(), -1),
+            MenuInput::Right => return MenuAction::Change(self.current(), 1),
+            MenuInput::Select => return MenuAction::Activate(self.current()),
+            MenuInput::Back => return MenuAction::Back,
+            MenuInput::Hover(x, y) | MenuInput::Click(x, y) => {
+                let index = match self.item_at(to_design(x, y).1) {
+                    Some(index) => index,
+                    None => return MenuAction::None,
+                };
+
+                self.selected = index;
+                if let MenuInput::Click(..) = input {
+                    return MenuAction::Activate(self.current());
+                }
+            }
+        }
+
+        MenuAction::None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_menu() {
+        let mut menu = Menu::new("TEST", vec!['a', 'b', 'c']);
+        let same = |x, y| (x, y);
+
+        assert_eq!(menu.handle(MenuInput::Up, same), MenuAction::None);
+        assert_eq!(menu.current(), 'c');
+        assert_eq!(menu.handle(MenuInput::Down, same), MenuAction::None);
+        assert_eq!(
+            menu.handle(MenuInput::Select, same),
+            MenuAction::Activate('a')
+        );
+        assert_eq!(
+            menu.handle(MenuInput::Left, same),
+            MenuAction::Change('a', -1)
+        );
+
+        // The mouse selects the item under it and the click activates it
+        let second = MENU_TOP + MENU_LINE;
+        assert_eq!(
+            menu.handle(MenuInput::Hover(0, second), same),
+            MenuAction::None
+        );
+        assert_eq!(menu.current(), 'b');
+        assert_eq!(
+            menu.handle(MenuInput::Click(0, second + 10), same),
+            MenuAction::Activate('b')
+        );
+        assert_eq!(menu.handle(MenuInput::Click(0, 10), same), MenuAction::None);
+        assert_eq!(menu.current(), 'b');
+
+        // The position is changed to the design before looking for the item
+        let double = |x, y| (x / 2, y / 2);
+        menu.handle(MenuInput::Hover(0, 2 * MENU_TOP), double);
+        assert_eq!(menu.current(), 'a');
+    }
+}
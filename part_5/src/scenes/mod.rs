@@ -0,0 +1,629 @@
+use std::path::Path;
+
+use common::timing::FixedTimestep;
+use sdl2::controller::Button;
+use sdl2::event::Event;
+use sdl2::keyboard::Keycode;
+
+pub mod menu;
+pub mod options;
+use crate::frontend::Frontend;
+use crate::input::Binding;
+use crate::scores::Score;
+use crate::surfaces::debug::DebugOverlay;
+use crate::tetris::actions::Action;
+use crate::tetris::puzzle::{self, Outcome, PuzzleSession};
+use crate::tetris::rules::{Ruleset, FRAME_RATE};
+use crate::tetris::Tetris;
+use crate::PUZZLE_PROGRESS;
+use menu::{Menu, MenuAction, MenuInput};
+use options::Setting;
+
+// Items of all the menus of the game
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Play,
+    Modes,
+    Options,
+    HighScores,
+    Quit,
+    Rules(&'static str), // Normal game with the rules of that name
+    Puzzles,
+    Setting(Setting),
+    Controls,
+    Resume,
+    Restart,
+    MainMenu,
+    Back,
+}
+
+impl Item {
+    fn label(&self, frontend: &Frontend) -> String {
+        let label = match self {
+            Item::Play => "PLAY",
+            Item::Modes => "MODE SELECT",
+            Item::Options => "OPTIONS",
+            Item::HighScores => "HIGH SCORES",
+            Item::Quit => "QUIT",
+            Item::Rules(name) => return format!("MARATHON {}", name.to_uppercase()),
+            Item::Puzzles => "PUZZLES",
+            Item::Setting(setting) => return options::label(*setting, frontend),
+            Item::Controls => "CONTROLS",
+            Item::Resume => "RESUME",
+            Item::Restart => "RESTART",
+            Item::MainMenu => "MAIN MENU",
+            Item::Back => "BACK",
+        };
+
+        label.to_string()
+    }
+}
+
+pub fn main_menu() -> Scene {
+    Scene::Menu(Menu::new(
+        "TETRIS",
+        vec![
+            Item::Play,
+            Item::Modes,
+            Item::Options,
+            Item::HighScores,
+            Item::Quit,
+        ],
+    ))
+}
+
+fn modes_menu() -> Scene {
+    Scene::Menu(Menu::new(
+        "MODE SELECT",
+        vec![
+            Item::Rules("guideline"),
+            Item::Rules("nes"),
+            Item::Rules("arcade"),
+            Item::Puzzles,
+            Item::Back,
+        ],
+    ))
+}
+
+fn options_menu() -> Scene {
+    let mut items: Vec<Item> = Setting::ALL.iter().map(|s| Item::Setting(*s)).collect();
+    items.push(Item::Controls);
+    items.push(Item::Back);
+
+    Scene::Menu(Menu::new("OPTIONS", items))
+}
+
+fn pause_menu() -> Scene {
+    Scene::Pause(Menu::new(
+        "PAUSED",
+        vec![Item::Resume, Item::Restart, Item::Options, Item::MainMenu],
+    ))
+}
+
+// The puzzles are played in order, and a failed puzzle is started again
+enum Mode {
+    Marathon,
+    Puzzles(PuzzleSession),
+}
+
+pub struct Game {
+    pub tetris: Tetris,
+    rules: Ruleset,
+    mode: Mode,
+    timestep: FixedTimestep,
+}
+
+impl Game {
+    pub fn marathon(rules: Ruleset, frontend: &mut Frontend) -> Self {
+        let mut game = Game {
+            tetris: Tetris::with_rules(rules),
+            rules,
+            mode: Mode::Marathon,
+            timestep: FixedTimestep::new(FRAME_RATE),
+        };
+        game.restart(frontend);
+
+        game
+    }
+
+    // The puzzle session selects the puzzles in order and remembers which ones
+    // have been solved
+    pub fn puzzles(path: &Path, rules: Ruleset, frontend: &mut Frontend) -> Result<Self, String> {
+        let packs = puzzle::load_packs(path)?;
+        let progress = puzzle::PuzzleProgress::load(Path::new(PUZZLE_PROGRESS));
+        let session = PuzzleSession::new(packs, progress);
+
+        let mut game = Game {
+            tetris: session.start_game(rules),
+            rules,
+            mode: Mode::Puzzles(session),
+            timestep: FixedTimestep::new(FRAME_RATE),
+        };
+        game.restart(frontend);
+
+        Ok(game)
+    }
+
+    // Starts the game again, or the current puzzle in the puzzle mode. The music
+    // stopped by the last game over is started again
+    fn restart(&mut self, frontend: &mut Frontend) {
+        self.tetris = match &self.mode {
+            Mode::Marathon => Tetris::with_rules(self.rules),
+            Mode::Puzzles(session) => {
+                println!(
+                    "{} - {}: {:?}",
+                    session.current_pack().name,
+                    session.current().name,
+                    session.current().goal
+                );
+                session.start_game(self.rules)
+            }
+        };
+
+        if let Some(audio) = frontend.audio.as_mut() {
+            audio.start_game(&frontend.settings);
+        }
+
+        self.resume(frontend);
+    }
+
+    // Called when the game is shown again after a menu. The time spent in the
+    // menu is not played, and the handling may have changed in the options
+    fn resume(&mut self, frontend: &Frontend) {
+        self.timestep = FixedTimestep::new(FRAME_RATE);
+        self.tetris.handling = frontend.settings.handling;
+    }
+
+    fn handle_event(&mut self, event: &Event, frontend: &mut Frontend) -> Transition {
+        match event {
+            Event::KeyDown {
+                keycode: Some(Keycode::Escape),
+                ..
+            } => return self.pause(),
+            Event::KeyDown {
+                keycode: Some(Keycode::F1),
+                repeat: false,
+                ..
+            } => {
+                self.release_all();
+                return Transition::Push(Scene::Controls {
+                    selected: 0,
+                    waiting: false,
+                });
+            }
+            Event::KeyDown {
+                keycode: Some(Keycode::Backquote),
+                repeat: false,
+                ..
+            } => {
+                // The debug overlay is only created while it is shown
+                frontend.debug = match frontend.debug {
+                    Some(_) => None,
+                    None => Some(DebugOverlay::default()),
+                };
+            }
+            _ => {
+                for (action, pressed) in frontend.input.actions(event) {
+                    match (action, pressed) {
+                        (Action::Pause, true) => return self.pause(),
+                        (_, true) => self.tetris.press(action),
+                        (_, false) => self.tetris.release(action),
+                    }
+                }
+            }
+        }
+
+        Transition::Stay
+    }
+
+    // The held actions are released, because their release would be read by the
+    // pause menu instead of the game
+    fn release_all(&mut self) {
+        for action in Action::ALL.iter() {
+            self.tetris.release(*action);
+        }
+    }
+
+    fn pause(&mut self) -> Transition {
+        self.release_all();
+        Transition::Push(pause_menu())
+    }
+
+    fn update(&mut self, frontend: &mut Frontend) -> Result<Transition, String> {
+        for _ in 0..self.timestep.tick() {
+            if !self.tetris.running {
+                break;
+            }
+
+            self.tetris.update();
+        }
+
+        // The sounds are played after the update so they match the new state
+        let events = self.tetris.take_events();
+        if let Some(audio) = frontend.audio.as_mut() {
+            audio.set_level(self.tetris.level);
+            audio.play_events(&events);
+        }
+
+        if self.tetris.running {
+            return Ok(Transition::Stay);
+        }
+
+        let session = match &mut self.mode {
+            Mode::Marathon => return self.game_over(frontend),
+            Mode::Puzzles(session) => session,
+        };
+
+        match self.tetris.outcome {
+            Some(Outcome::Success) => {
+                println!("Puzzle solved!");
+                session.complete_current()?;
+
+                if !session.advance() {
+                    println!("All the puzzles have been solved!");
+                    return Ok(Transition::Pop);
+                }
+            }
+            _ => println!("Puzzle failed, try again"),
+        }
+
+        self.restart(frontend);
+        Ok(Transition::Stay)
+    }
+
+    // The score is added to the high scores and the statistics are saved before
+    // showing the report of the game
+    fn game_over(&mut self, frontend: &mut Frontend) -> Result<Transition, String> {
+        println!("Game over!\n Your score is: {}", self.tetris.score);
+
+        let mut notes = Vec::new();
+        let score = Score {
+            lines: self.tetris.score,
+            level: self.tetris.level,
+            seconds: self.tetris.stats.seconds() as u32,
+            rules: self.rules.name.to_string(),
+        };
+
+        if let Some(position) = frontend.scores.add(score) {
+            frontend.scores.save()?;
+            notes.push(format!("NEW HIGH SCORE #{}", position + 1));
+        }
+
+        if let Some(path) = frontend.stats_path.as_ref() {
+            self.tetris.stats.export(path)?;
+            notes.push(String::from("STATS SAVED TO"));
+            notes.push(path.display().to_string());
+        }
+
+        let tetris = std::mem::replace(&mut self.tetris, Tetris::with_rules(self.rules));
+        Ok(Transition::Replace(Scene::Report {
+            tetris: Box::new(tetris),
+            notes,
+        }))
+    }
+}
+
+// The screens of the game. They are kept in a stack where only the scene on top
+// receives the input: a menu pushes the next menu or a game, the game pushes
+// the pause menu, and going back pops the scene on top
+pub enum Scene {
+    Menu(Menu<Item>),
+    Pause(Menu<Item>), // Drawn on top of the game below it
+    HighScores,
+    Controls {
+        selected: usize,
+        waiting: bool,
+    },
+    Game(Box<Game>),
+    Report {
+        tetris: Box<Tetris>,
+        notes: Vec<String>,
+    },
+}
+
+pub enum Transition {
+    Stay,
+    Push(Scene),
+    Pop,
+    Replace(Scene),
+    Home, // Back to the first scene of the stack
+    Quit,
+}
+
+fn menu_action(
+    item: Item,
+    frontend: &mut Frontend,
+    below: Option<&mut Scene>,
+) -> Result<Transition, String> {
+    let transition = match item {
+        Item::Play => Transition::Push(Scene::Game(Box::new(Game::marathon(
+            frontend.rules,
+            frontend,
+        )))),
+        Item::Modes => Transition::Push(modes_menu()),
+        Item::Options => Transition::Push(options_menu()),
+        Item::HighScores => Transition::Push(Scene::HighScores),
+        Item::Quit => Transition::Quit,
+        Item::Rules(name) => {
+            // The selected rules are also used by the next games
+            frontend.rules = Ruleset::from_name(name).unwrap_or(frontend.rules);
+            Transition::Replace(Scene::Game(Box::new(Game::marathon(
+                frontend.rules,
+                frontend,
+            ))))
+        }
+        Item::Puzzles => {
+            match Game::puzzles(&frontend.puzzle_path.clone(), frontend.rules, frontend) {
+                Ok(game) => Transition::Replace(Scene::Game(Box::new(game))),
+                Err(e) => {
+                    println!("Unable to start the puzzles: {}", e);
+                    Transition::Stay
+                }
+            }
+        }
+        Item::Setting(setting) => {
+            options::change(setting, 1, frontend)?;
+            Transition::Stay
+        }
+        Item::Controls => Transition::Push(Scene::Controls {
+            selected: 0,
+            waiting: false,
+        }),
+        Item::Resume | Item::Back => Transition::Pop,
+        Item::Restart => {
+            if let Some(Scene::Game(game)) = below {
+                game.restart(frontend);
+            }
+            Transition::Pop
+        }
+        Item::MainMenu => Transition::Home,
+    };
+
+    Ok(transition)
+}
+
+fn controls_event(
+    event: &Event,
+    selected: &mut usize,
+    waiting: &mut bool,
+    frontend: &mut Frontend,
+) -> Result<Transition, String> {
+    // Screen used to change the bindings of the actions. The arrows select an
+    // action and Enter waits for the next key, button or axis to bind it. The
+    // bindings are saved when leaving the screen
+    match event {
+        Event::KeyDown {
+            keycode: Some(Keycode::Escape),
+            ..
+        } if *waiting => *waiting = false,
+        _ if *waiting => {
+            if let Some(binding) = Binding::from_event(event) {
+                frontend.input.bind(Action::ALL[*selected], binding);
+                *waiting = false;
+            }
+        }
+        Event::KeyDown {
+            keycode: Some(Keycode::Escape),
+            ..
+        }
+        | Event::ControllerButtonDown {
+            button: Button::B, ..
+        } => {
+            frontend.input.save()?;
+            return Ok(Transition::Pop);
+        }
+        Event::KeyDown {
+            keycode: Some(Keycode::Up),
+            ..
+        }
+        | Event::ControllerButtonDown {
+            button: Button::DPadUp,
+            ..
+        } => {
+            *selected = (*selected + Action::ALL.len() - 1) % Action::ALL.len();
+        }
+        Event::KeyDown {
+            keycode: Some(Keycode::Down),
+            ..
+        }
+        | Event::ControllerButtonDown {
+            button: Button::DPadDown,
+            ..
+        } => {
+            *selected = (*selected + 1) % Action::ALL.len();
+        }
+        Event::KeyDown {
+            keycode: Some(Keycode::Return),
+            ..
+        }
+        | Event::ControllerButtonDown {
+            button: Button::A, ..
+        } => {
+            *waiting = true;
+        }
+        Event::KeyDown {
+            keycode: Some(Keycode::Backspace),
+            ..
+        }
+        | Event::ControllerButtonDown {
+            button: Button::X, ..
+        } => {
+            frontend.input.clear(Action::ALL[*selected]);
+        }
+        Event::KeyDown {
+            keycode: Some(Keycode::R),
+            ..
+        } => {
+            frontend.input.reset();
+        }
+        _ => {}
+    }
+
+    Ok(Transition::Stay)
+}
+
+impl Scene {
+    fn handle_event(
+        &mut self,
+        event: &Event,
+        frontend: &mut Frontend,
+        below: Option<&mut Scene>,
+    ) -> Result<Transition, String> {
+        match self {
+            Scene::Menu(menu) | Scene::Pause(menu) => {
+                let input = match MenuInput::from_event(event) {
+                    Some(input) => input,
+                    None => return Ok(Transition::Stay),
+                };
+
+                let layout = frontend.textures.layout;
+                match menu.handle(input, |x, y| layout.design_point(x, y)) {
+                    MenuAction::None => Ok(Transition::Stay),
+                    MenuAction::Activate(item) => menu_action(item, frontend, below),
+                    MenuAction::Change(Item::Setting(setting), delta) => {
+                        options::change(setting, delta, frontend)?;
+                        Ok(Transition::Stay)
+                    }
+                    MenuAction::Change(..) => Ok(Transition::Stay),
+                    // Going back from the pause menu resumes the game, and going
+                    // back from the main menu closes the game
+                    MenuAction::Back if below.is_none() => Ok(Transition::Quit),
+                    MenuAction::Back => Ok(Transition::Pop),
+                }
+            }
+            Scene::Controls { selected, waiting } => {
+                controls_event(event, selected, waiting, frontend)
+            }
+            Scene::Game(game) => Ok(game.handle_event(event, frontend)),
+            Scene::HighScores | Scene::Report { .. } => match MenuInput::from_event(event) {
+                Some(MenuInput::Select) | Some(MenuInput::Back) | Some(MenuInput::Click(..)) => {
+                    Ok(Transition::Pop)
+                }
+                _ => Ok(Transition::Stay),
+            },
+        }
+    }
+
+    fn resume(&mut self, frontend: &Frontend) {
+        if let Scene::Game(game) = self {
+            game.resume(frontend);
+        }
+    }
+
+    fn draw(&self, frontend: &mut Frontend, below: Option<&Scene>) -> Result<(), String> {
+        let labels = |menu: &Menu<Item>, frontend: &Frontend| -> Vec<String> {
+            menu.items.iter().map(|item| item.label(frontend)).collect()
+        };
+
+        match self {
+            Scene::Menu(menu) => {
+                let labels = labels(menu, frontend);
+                frontend
+                    .textures
+                    .draw_menu(menu.title, &labels, menu.selected, None)
+            }
+            Scene::Pause(menu) => {
+                let labels = labels(menu, frontend);
+                let game = match below {
+                    Some(Scene::Game(game)) => Some(&game.tetris),
+                    _ => None,
+                };
+                frontend
+                    .textures
+                    .draw_menu(menu.title, &labels, menu.selected, game)
+            }
+            Scene::HighScores => frontend.textures.draw_scores(&frontend.scores),
+            Scene::Controls { selected, waiting } => {
+                frontend
+                    .textures
+                    .draw_controls(&frontend.input, *selected, *waiting)
+            }
+            Scene::Game(game) => frontend
+                .textures
+                .draw_game(&game.tetris, frontend.debug.as_ref()),
+            Scene::Report { tetris, notes } => frontend.textures.draw_report(tetris, notes),
+        }
+    }
+}
+
+// Runs the scenes until the stack is empty or a scene quits the game. The events
+// that change the window and the sound are handled here for all the scenes
+pub fn run(mut stack: Vec<Scene>, frontend: &mut Frontend) -> Result<(), String> {
+    while !stack.is_empty() {
+        let events: Vec<Event> = frontend.event_pump.poll_iter().collect();
+        let mut transitions = Vec::new();
+
+        for event in events.iter() {
+            frontend.gamepads.handle_event(event);
+
+            if let Some(debug) = frontend.debug.as_mut() {
+                debug.handle_event(event);
+            }
+
+            // The controls screen needs all the keys to bind them
+            let binding = matches!(stack.last(), Some(Scene::Controls { .. }));
+
+            match event {
+                Event::Quit { .. } => return Ok(()),
+                _ if frontend.window_event(event)? => {}
+                Event::KeyDown {
+                    keycode: Some(keycode),
+                    repeat: false,
+                    ..
+                } if !binding && frontend.sound_key(*keycode)? => {}
+                _ => {
+                    let (top, rest) = match stack.split_last_mut() {
+                        Some(split) => split,
+                        None => break,
+                    };
+
+                    let transition = top.handle_event(event, frontend, rest.last_mut())?;
+                    if let Transition::Stay = transition {
+                        continue;
+                    }
+
+                    // The rest of the events are dropped, they were meant for the
+                    // scene that is going to change
+                    transitions.push(transition);
+                    break;
+                }
+            }
+        }
+
+        frontend.reload_themes()?;
+
+        // The game isn't updated when an event has already changed the scene, so a
+        // pause and the end of the game can't happen in the same frame and the
+        // game over report never replaces the pause menu
+        if transitions.is_empty() {
+            if let Some(Scene::Game(game)) = stack.last_mut() {
+                transitions.push(game.update(frontend)?);
+            }
+        }
+
+        for transition in transitions {
+            match transition {
+                Transition::Stay => {}
+                Transition::Push(scene) => stack.push(scene),
+                Transition::Pop => {
+                    stack.pop();
+                    if let Some(scene) = stack.last_mut() {
+                        scene.resume(frontend);
+                    }
+                }
+                Transition::Replace(scene) => {
+                    stack.pop();
+                    stack.push(scene);
+                }
+                Transition::Home => stack.truncate(1),
+                Transition::Quit => return Ok(()),
+            }
+        }
+
+        if let Some((top, rest)) = stack.split_last() {
+            top.draw(frontend, rest.last())?;
+        }
+    }
+
+    Ok(())
+}
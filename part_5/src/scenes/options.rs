@@ -0,0 +1,128 @@
+use crate::frontend::Frontend;
+use crate::surfaces::theme::Palette;
+
+// Settings that can be changed in the options screen. Left and right change the
+// value, and selecting the item moves it to the next value
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Setting {
+    Das,
+    Arr,
+    SoftDrop,
+    Volume,
+    Muted,
+    Music,
+    Theme,
+    Palette,
+    Patterns,
+    Scale,
+    Fullscreen,
+}
+
+impl Setting {
+    pub const ALL: [Setting; 11] = [
+        Setting::Das,
+        Setting::Arr,
+        Setting::SoftDrop,
+        Setting::Volume,
+        Setting::Muted,
+        Setting::Music,
+        Setting::Theme,
+        Setting::Palette,
+        Setting::Patterns,
+        Setting::Scale,
+        Setting::Fullscreen,
+    ];
+}
+
+fn on_off(value: bool) -> &'static str {
+    match value {
+        true => "ON",
+        false => "OFF",
+    }
+}
+
+// Moves a number by the delta without leaving its range
+fn step(value: u32, delta: i32, min: u32, max: u32) -> u32 {
+    (value as i32 + delta).clamp(min as i32, max as i32) as u32
+}
+
+// Index of the next item of a list in the direction of the delta
+fn cycle(index: usize, delta: i32, len: usize) -> usize {
+    (index as i32 + delta).rem_euclid(len as i32) as usize
+}
+
+pub fn label(setting: Setting, frontend: &Frontend) -> String {
+    let settings = &frontend.settings;
+
+    match setting {
+        Setting::Das => format!("DAS {}", settings.handling.das),
+        Setting::Arr => format!("ARR {}", settings.handling.arr),
+        Setting::SoftDrop => format!("SOFT DROP {}", settings.handling.soft_drop),
+        Setting::Volume => format!("VOLUME {}", settings.volume),
+        Setting::Muted => format!("MUTE {}", on_off(settings.muted)),
+        Setting::Music => format!("MUSIC {}", on_off(settings.music)),
+        Setting::Theme => format!("THEME {}", settings.theme),
+        Setting::Palette => format!("PALETTE {}", settings.palette.name()),
+        Setting::Patterns => format!("PATTERNS {}", on_off(settings.patterns)),
+        Setting::Scale => format!("SCALE {}", settings.scale.name()),
+        Setting::Fullscreen => format!("FULLSCREEN {}", on_off(settings.fullscreen)),
+    }
+}
+
+// Changes a setting, applies it to the game and saves the settings
+pub fn change(setting: Setting, delta: i32, frontend: &mut Frontend) -> Result<(), String> {
+    let settings = &mut frontend.settings;
+
+    match setting {
+        Setting::Das => settings.handling.das = step(settings.handling.das, delta, 0, 30),
+        Setting::Arr => settings.handling.arr = step(settings.handling.arr, delta, 0, 10),
+        Setting::SoftDrop => {
+            settings.handling.soft_drop = step(settings.handling.soft_drop, delta, 1, 10)
+        }
+        Setting::Volume => settings.volume = step(settings.volume, delta * 10, 0, 100),
+        Setting::Muted => settings.muted = !settings.muted,
+        Setting::Music => settings.music = !settings.music,
+        Setting::Theme => {
+            let index = cycle(frontend.theme_index(), delta, frontend.themes.len());
+            frontend.settings.theme = frontend.themes[index].name.clone();
+        }
+        Setting::Palette => {
+            let index = Palette::ALL
+                .iter()
+                .position(|palette| *palette == settings.palette)
+                .unwrap_or(0);
+            settings.palette = Palette::ALL[cycle(index, delta, Palette::ALL.len())];
+        }
+        Setting::Patterns => settings.patterns = !settings.patterns,
+        Setting::Scale => settings.scale = settings.scale.toggle(),
+        Setting::Fullscreen => settings.fullscreen = !settings.fullscreen,
+    }
+
+    match setting {
+        Setting::Volume | Setting::Muted | Setting::Music => {
+            if let Some(audio) = frontend.audio.as_mut() {
+                audio.apply_settings(&frontend.settings);
+            }
+        }
+        Setting::Theme | Setting::Palette | Setting::Patterns => frontend.load_theme()?,
+        Setting::Scale | Setting::Fullscreen => frontend.apply_window_settings()?,
+        Setting::Das | Setting::Arr | Setting::SoftDrop => {}
+    }
+
+    frontend.settings.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_step() {
+        assert_eq!(step(5, 1, 0, 10), 6);
+        assert_eq!(step(0, -1, 0, 10), 0);
+        assert_eq!(step(100, 10, 0, 100), 100);
+
+        assert_eq!(cycle(0, -1, 4), 3);
+        assert_eq!(cycle(3, 1, 4), 0);
+    }
+}
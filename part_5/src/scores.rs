@@ -0,0 +1,143 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Number of scores kept in the table
+pub const MAX_SCORES: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Score {
+    pub lines: u32,
+    pub level: u32,
+    pub seconds: u32,  // Time played
+    pub rules: String, // Name of the rules of the game
+}
+
+// Best scores of the normal games, sorted from the best to the worst. A game
+// with more lines is better, and a game with the same lines is better if it
+// was faster. They are stored in a text file with one score per line:
+// `lines level seconds rules`
+pub struct HighScores {
+    path: PathBuf,
+    pub scores: Vec<Score>,
+}
+
+impl HighScores {
+    // Loads the scores from a file. A missing file gives an empty table
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let scores = match fs::read_to_string(path) {
+            Ok(text) => HighScores::parse(&text)?,
+            Err(_) => Vec::new(),
+        };
+
+        Ok(HighScores {
+            path: path.to_path_buf(),
+            scores,
+        })
+    }
+
+    fn parse(text: &str) -> Result<Vec<Score>, String> {
+        let mut scores = Vec::new();
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            let number = |index: usize| {
+                parts
+                    .get(index)
+                    .and_then(|part| part.parse::<u32>().ok())
+                    .ok_or(format!("Invalid score: {}", line))
+            };
+
+            scores.push(Score {
+                lines: number(0)?,
+                level: number(1)?,
+                seconds: number(2)?,
+                rules: parts.get(3).unwrap_or(&"guideline").to_string(),
+            });
+        }
+
+        Ok(scores)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let text: String = self
+            .scores
+            .iter()
+            .map(|score| {
+                format!(
+                    "{} {} {} {}\n",
+                    score.lines, score.level, score.seconds, score.rules
+                )
+            })
+            .collect();
+
+        fs::write(&self.path, text).map_err(|e| e.to_string())
+    }
+
+    // Adds a score to the table and returns its position, starting from 0, or
+    // None if it isn't good enough to be in the table
+    pub fn add(&mut self, score: Score) -> Option<usize> {
+        let position = self
+            .scores
+            .iter()
+            .position(|other| {
+                score.lines > other.lines
+                    || (score.lines == other.lines && score.seconds < other.seconds)
+            })
+            .unwrap_or(self.scores.len());
+
+        if position >= MAX_SCORES {
+            return None;
+        }
+
+        self.scores.insert(position, score);
+        self.scores.truncate(MAX_SCORES);
+
+        Some(position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(lines: u32, seconds: u32) -> Score {
+        Score {
+            lines,
+            level: 1,
+            seconds,
+            rules: String::from("nes"),
+        }
+    }
+
+    #[test]
+    fn test_add() {
+        let mut table = HighScores {
+            path: PathBuf::new(),
+            scores: Vec::new(),
+        };
+
+        assert_eq!(table.add(score(10, 100)), Some(0));
+        assert_eq!(table.add(score(20, 100)), Some(0));
+        assert_eq!(table.add(score(10, 50)), Some(1));
+        assert_eq!(table.scores[2], score(10, 100));
+
+        // Once the table is full only the better scores get in
+        for _ in 0..MAX_SCORES {
+            table.add(score(5, 10));
+        }
+        assert_eq!(table.scores.len(), MAX_SCORES);
+        assert_eq!(table.add(score(5, 20)), None);
+        assert_eq!(table.add(score(6, 20)), Some(3));
+    }
+
+    #[test]
+    fn test_parse() {
+        let scores = HighScores::parse("12 2 95 nes\n\n3 1 20 arcade\n").unwrap();
+        assert_eq!(scores.len(), 2);
+        assert_eq!(scores[0].lines, 12);
+        assert_eq!(scores[0].seconds, 95);
+        assert_eq!(scores[1].rules, "arcade");
+
+        assert!(HighScores::parse("12 two 95 nes").is_err());
+    }
+}
@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::surfaces::layout::ScaleMode;
+use crate::surfaces::theme::Palette;
+use crate::tetris::actions::Handling;
+
+// Settings of the game that are kept between sessions. They are stored in a
+// text file with one `key = value` line per setting
+pub struct Settings {
+    path: PathBuf,
+    pub volume: u32, // From 0 to 100
+    pub muted: bool,
+    pub music: bool,
+    pub scale: ScaleMode,
+    pub fullscreen: bool,
+    pub theme: String, // Name of the theme
+    pub palette: Palette,
+    pub patterns: bool,
+    pub vsync: bool,
+    pub frame_limit: u32, // Frames per second when vsync is off, 0 for no limit
+    pub show_fps: bool,
+    pub handling: Handling,
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "on" | "yes" => Ok(true),
+        "false" | "off" | "no" => Ok(false),
+        _ => Err(format!("Invalid value for {}: {}", key, value)),
+    }
+}
+
+fn parse_number(key: &str, value: &str) -> Result<u32, String> {
+    value
+        .parse()
+        .map_err(|_| format!("Invalid value for {}: {}", key, value))
+}
+
+impl Settings {
+    // Loads the settings from a file. A missing file gives the default settings.
+    // Unknown keys are ignored so older versions of the game can read the file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let mut settings = Settings {
+            path: path.to_path_buf(),
+            volume: 70,
+            muted: false,
+            music: true,
+            scale: ScaleMode::Integer,
+            fullscreen: false,
+            theme: String::from("Classic"),
+            palette: Palette::Normal,
+            patterns: false,
+            vsync: true,
+            frame_limit: 0,
+            show_fps: false,
+            handling: Handling::default(),
+        };
+
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(_) => return Ok(settings),
+        };
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                Some(index) => (line[..index].trim(), line[index + 1..].trim()),
+                None => return Err(format!("Invalid settings line: {}", line)),
+            };
+
+            match key {
+                "volume" => settings.volume = parse_number(key, value)?.min(100),
+                "muted" => settings.muted = parse_bool(key, value)?,
+                "music" => settings.music = parse_bool(key, value)?,
+                "scale" => {
+                    settings.scale = ScaleMode::from_name(value)
+                        .ok_or(format!("Invalid value for {}: {}", key, value))?
+                }
+                "fullscreen" => settings.fullscreen = parse_bool(key, value)?,
+                "theme" => settings.theme = value.to_string(),
+                "palette" => {
+                    settings.palette = Palette::from_name(value)
+                        .ok_or(format!("Invalid value for {}: {}", key, value))?
+                }
+                "patterns" => settings.patterns = parse_bool(key, value)?,
+                "vsync" => settings.vsync = parse_bool(key, value)?,
+                "frame_limit" => settings.frame_limit = parse_number(key, value)?,
+                "show_fps" => settings.show_fps = parse_bool(key, value)?,
+                "das" => settings.handling.das = parse_number(key, value)?,
+                "arr" => settings.handling.arr = parse_number(key, value)?,
+                "soft_drop" => settings.handling.soft_drop = parse_number(key, value)?,
+                _ => {}
+            }
+        }
+
+        Ok(settings)
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let text = format!(
+            "volume = {}\nmuted = {}\nmusic = {}\nscale = {}\nfullscreen = {}\ntheme = {}\npalette = {}\npatterns = {}\nvsync = {}\nframe_limit = {}\nshow_fps = {}\ndas = {}\narr = {}\nsoft_drop = {}\n",
+            self.volume,
+            self.muted,
+            self.music,
+            self.scale.name(),
+            self.fullscreen,
+            self.theme,
+            self.palette.name(),
+            self.patterns,
+            self.vsync,
+            self.frame_limit,
+            self.show_fps,
+            self.handling.das,
+            self.handling.arr,
+            self.handling.soft_drop
+        );
+
+        fs::write(&self.path, text).map_err(|e| e.to_string())
+    }
+}
@@ -0,0 +1,138 @@
+use common::timing::FrameReport;
+use sdl2::event::{Event, WindowEvent};
+
+use crate::tetris::{Shapes, Tetris};
+
+// Overlay for developers drawn on top of the game. The frontend keeps it in an
+// Option that is only filled while the overlay is shown, so the game doesn't do
+// any work for it when it is switched off
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub mouse: Option<(i32, i32)>, // Position of the mouse in the window
+}
+
+fn shape_letter(shape: Shapes) -> char {
+    match shape {
+        Shapes::ShapeS => 'S',
+        Shapes::ShapeZ => 'Z',
+        Shapes::ShapeT => 'T',
+        Shapes::ShapeL => 'L',
+        Shapes::ShapeR => 'R',
+        Shapes::ShapeG => 'G',
+        Shapes::ShapeE => '.',
+    }
+}
+
+impl DebugOverlay {
+    // Follows the mouse to show the cell of the board under it
+    pub fn handle_event(&mut self, event: &Event) {
+        match event {
+            Event::MouseMotion { x, y, .. } => self.mouse = Some((*x, *y)),
+            Event::Window {
+                win_event: WindowEvent::Leave,
+                ..
+            } => self.mouse = None,
+            _ => {}
+        }
+    }
+
+    // Lines of text with the state of the game. The cell is the cell of the board
+    // under the mouse, if the mouse is on the board
+    pub fn lines(
+        &self,
+        tetris: &Tetris,
+        report: Option<FrameReport>,
+        cell: Option<(i32, i32)>,
+    ) -> Vec<String> {
+        let piece = &tetris.current_piece;
+        let timers = tetris.timers();
+
+        let mut lines = match report {
+            Some(report) => vec![
+                format!("FPS {:.1}", report.fps),
+                format!("FRAME {:.1} MS", report.average.as_secs_f32() * 1000.0),
+                format!("WORST {:.1} MS", report.worst.as_secs_f32() * 1000.0),
+            ],
+            None => vec![String::from("FPS -")],
+        };
+
+        lines.push(format!("X {} Y {}", piece.x_pos, piece.y_pos));
+        lines.push(format!("STATE {}", piece.current_state));
+        lines.push(format!("GRAVITY {:.3}", timers.gravity));
+        lines.push(format!("LOCK {}/{}", timers.lock_timer, timers.lock_delay));
+        lines.push(format!("ENTRY {}", timers.entry_timer));
+
+        // The next piece is the only one a normal game knows, because its pieces are
+        // made at random. The pieces of a puzzle come in a fixed sequence, and the
+        // ones left after the next piece are shown too
+        lines.push(match &tetris.next_piece {
+            Some(next) => format!("NEXT {}", shape_letter(next.shape())),
+            None => String::from("NEXT -"),
+        });
+        lines.push(match tetris.upcoming() {
+            Some(upcoming) if upcoming.is_empty() => String::from("QUEUE -"),
+            Some(upcoming) => format!(
+                "QUEUE {}",
+                upcoming.into_iter().map(shape_letter).collect::<String>()
+            ),
+            None => String::from("QUEUE RANDOM"),
+        });
+
+        lines.push(match cell {
+            Some((x, y)) => format!("CELL {},{}", x, y),
+            None => String::from("CELL -"),
+        });
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetris::puzzle::PuzzlePack;
+    use crate::tetris::rules::Ruleset;
+    use std::time::Duration;
+
+    #[test]
+    fn test_lines() {
+        let pack =
+            PuzzlePack::parse("[puzzle]\ngoal = lines 1\npieces = T L\nboard:\nGGGG..GGGG\nend\n")
+                .unwrap();
+        let game = Tetris::from_puzzle(&pack.puzzles[0], Ruleset::guideline());
+
+        let mut overlay = DebugOverlay::default();
+        overlay.handle_event(&Event::MouseMotion {
+            timestamp: 0,
+            window_id: 0,
+            which: 0,
+            mousestate: sdl2::mouse::MouseState::from_sdl_state(0),
+            x: 10,
+            y: 20,
+            xrel: 0,
+            yrel: 0,
+        });
+        assert_eq!(overlay.mouse, Some((10, 20)));
+
+        let report = FrameReport {
+            fps: 60.0,
+            average: Duration::from_micros(16_700),
+            worst: Duration::from_millis(20),
+        };
+        let lines = overlay.lines(&game, Some(report), Some((3, 5)));
+
+        assert_eq!(lines[0], "FPS 60.0");
+        assert_eq!(lines[1], "FRAME 16.7 MS");
+        assert_eq!(lines[3], "X 4 Y 0");
+        assert!(lines.contains(&String::from("CELL 3,5")));
+        assert!(lines.contains(&String::from("NEXT L")));
+        assert!(lines.contains(&String::from("QUEUE -")));
+
+        // A normal game shows its next random piece
+        let game = Tetris::new();
+        let next = shape_letter(game.next_piece.as_ref().unwrap().shape());
+        let lines = overlay.lines(&game, None, None);
+        assert!(lines.contains(&format!("NEXT {}", next)));
+        assert!(lines.contains(&String::from("QUEUE RANDOM")));
+    }
+}
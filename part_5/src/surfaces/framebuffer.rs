@@ -0,0 +1,182 @@
+use std::collections::HashMap;
+
+use common::screenshot::Image;
+use sdl2::pixels::Color;
+
+use super::font;
+use super::renderer::Renderer;
+use super::theme::Theme;
+use super::BACKGROUND;
+use crate::tetris::Shapes;
+
+// A renderer that draws into pixels in memory instead of a window, so a game
+// can be drawn without SDL being initialized: in the tests, or to save an image
+// of a game. The pixels have the design size, one pixel per design unit.
+//
+// The cells are drawn flat with the colors of the theme, without the styles,
+// patterns and images of the window textures. Colors with transparency are
+// drawn as if they were opaque
+pub struct Framebuffer {
+    pub image: Image,
+    pub frames: u32, // Number of frames that have been presented
+    colors: HashMap<Shapes, Color>,
+    grid: Color,
+}
+
+impl Framebuffer {
+    pub fn new(width: u32, height: u32, theme: &Theme) -> Self {
+        Framebuffer {
+            image: Image {
+                width,
+                height,
+                pixels: vec![0; (width * height * 3) as usize],
+            },
+            frames: 0,
+            colors: theme.colors.clone(),
+            grid: theme.grid,
+        }
+    }
+
+    pub fn pixel(&self, x: u32, y: u32) -> Color {
+        let (r, g, b) = self.image.pixel(x, y);
+        Color::RGB(r, g, b)
+    }
+
+    // The parts of the rectangle outside of the image are not drawn
+    fn fill(&mut self, x: i32, y: i32, width: u32, height: u32, color: Color) {
+        let left = x.max(0) as u32;
+        let top = y.max(0) as u32;
+        let right = (x + width as i32).clamp(0, self.image.width as i32) as u32;
+        let bottom = (y + height as i32).clamp(0, self.image.height as i32) as u32;
+
+        for row in top..bottom {
+            for col in left..right {
+                let index = ((row * self.image.width + col) * 3) as usize;
+                self.image.pixels[index..index + 3].copy_from_slice(&[color.r, color.g, color.b]);
+            }
+        }
+    }
+
+    // Draws the border of a rectangle, one pixel wide
+    fn outline(&mut self, x: i32, y: i32, width: u32, height: u32, color: Color) {
+        self.fill(x, y, width, 1, color);
+        self.fill(x, y + height as i32 - 1, width, 1, color);
+        self.fill(x, y, 1, height, color);
+        self.fill(x + width as i32 - 1, y, 1, height, color);
+    }
+}
+
+impl Renderer for Framebuffer {
+    fn clear(&mut self) -> Result<(), String> {
+        let (width, height) = (self.image.width, self.image.height);
+        self.fill(0, 0, width, height, BACKGROUND);
+
+        Ok(())
+    }
+
+    fn fill_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        color: Color,
+    ) -> Result<(), String> {
+        self.fill(x, y, width, height, color);
+
+        Ok(())
+    }
+
+    // The empty cells have the border of the grid, like the boxes of the window
+    fn draw_cell(&mut self, shape: Shapes, x: i32, y: i32, size: u32) -> Result<(), String> {
+        let color = self
+            .colors
+            .get(&shape)
+            .copied()
+            .unwrap_or(Color::RGB(255, 255, 255));
+        self.fill(x, y, size, size, color);
+
+        if shape == Shapes::ShapeE && size > 2 {
+            self.outline(x + 1, y + 1, size - 2, size - 2, self.grid);
+        }
+
+        Ok(())
+    }
+
+    fn draw_text(
+        &mut self,
+        text: &str,
+        x: i32,
+        y: i32,
+        scale: u32,
+        color: Color,
+    ) -> Result<(), String> {
+        for rect in font::glyph_rects(text, x, y, scale) {
+            self.fill(rect.x(), rect.y(), rect.width(), rect.height(), color);
+        }
+
+        Ok(())
+    }
+
+    fn present(&mut self) -> Result<(), String> {
+        self.frames += 1;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::surfaces::layout::{DESIGN_HEIGHT, DESIGN_WIDTH};
+    use crate::surfaces::{MARGIN_X, MARGIN_Y, NEXT_AREA_X, NEXT_AREA_Y};
+    use crate::tetris::puzzle::PuzzlePack;
+    use crate::tetris::rules::Ruleset;
+    use crate::tetris::{Tetris, BOARD_HEIGHT};
+
+    #[test]
+    fn test_render_game() {
+        let pack = PuzzlePack::parse(
+            "[puzzle]\ngoal = lines 1\npieces = R L T\nboard:\nGGG..GGTTG\nend\n",
+        )
+        .unwrap();
+        let tetris = Tetris::from_puzzle(&pack.puzzles[0], Ruleset::guideline());
+        let theme = Theme::classic();
+
+        let mut framebuffer = Framebuffer::new(DESIGN_WIDTH, DESIGN_HEIGHT, &theme);
+        framebuffer.render_game(&tetris).unwrap();
+        framebuffer.present().unwrap();
+        assert_eq!(framebuffer.frames, 1);
+
+        // The center of the cells of the last row have the color of their shape,
+        // and the empty cells have the border of the grid
+        let row_y = (MARGIN_Y + 40 * (BOARD_HEIGHT as i32 - 1)) as u32;
+        let column_x = |column: u32| MARGIN_X as u32 + 40 * column;
+        assert_eq!(
+            framebuffer.pixel(column_x(0) + 20, row_y + 20),
+            theme.color(Shapes::ShapeG)
+        );
+        assert_eq!(
+            framebuffer.pixel(column_x(7) + 20, row_y + 20),
+            theme.color(Shapes::ShapeT)
+        );
+        assert_eq!(
+            framebuffer.pixel(column_x(3) + 20, row_y + 20),
+            theme.color(Shapes::ShapeE)
+        );
+        assert_eq!(framebuffer.pixel(column_x(3) + 1, row_y + 20), theme.grid);
+
+        // The next area is drawn with its label, and the background around it
+        assert_eq!(framebuffer.pixel(0, 0), BACKGROUND);
+        let area_x = NEXT_AREA_X as u32;
+        let area_y = NEXT_AREA_Y as u32;
+        assert_eq!(
+            framebuffer.pixel(area_x + 2, area_y + 2),
+            Color::RGB(200, 200, 200)
+        );
+        let label = (0..40)
+            .flat_map(|x| (0..20).map(move |y| (x, y)))
+            .any(|(x, y)| framebuffer.pixel(area_x + x, area_y + y) == Color::RGB(10, 10, 10));
+        assert!(label);
+    }
+}
@@ -0,0 +1,145 @@
+use sdl2::rect::Rect;
+
+// Size of the window the game was designed for. All the positions used to draw
+// the game are given in this size, and the layout moves and scales them to fit
+// the real size of the window
+pub const DESIGN_WIDTH: u32 = 600;
+pub const DESIGN_HEIGHT: u32 = 800;
+
+// The integer scale only uses whole multiples of the design size, so the blocks
+// keep sharp pixels. The aspect scale fills as much of the window as possible.
+// Both of them keep the proportions of the game and fill the rest with bars
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ScaleMode {
+    Integer,
+    Aspect,
+}
+
+impl ScaleMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScaleMode::Integer => "integer",
+            ScaleMode::Aspect => "aspect",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ScaleMode> {
+        match name {
+            "integer" => Some(ScaleMode::Integer),
+            "aspect" => Some(ScaleMode::Aspect),
+            _ => None,
+        }
+    }
+
+    pub fn toggle(&self) -> ScaleMode {
+        match self {
+            ScaleMode::Integer => ScaleMode::Aspect,
+            ScaleMode::Aspect => ScaleMode::Integer,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Layout {
+    pub mode: ScaleMode,
+    pub scale: f32,
+    pub offset_x: i32, // Position of the game area inside the window
+    pub offset_y: i32,
+}
+
+impl Layout {
+    pub fn new(width: u32, height: u32, mode: ScaleMode) -> Self {
+        let fit = (width as f32 / DESIGN_WIDTH as f32).min(height as f32 / DESIGN_HEIGHT as f32);
+
+        // A window smaller than the design can't use an integer scale, so the
+        // game is shrunk to fit it instead
+        let scale = match mode {
+            ScaleMode::Integer if fit >= 1.0 => fit.floor(),
+            _ => fit,
+        };
+
+        Layout {
+            mode,
+            scale,
+            offset_x: (width as i32 - (DESIGN_WIDTH as f32 * scale).round() as i32) / 2,
+            offset_y: (height as i32 - (DESIGN_HEIGHT as f32 * scale).round() as i32) / 2,
+        }
+    }
+
+    pub fn x(&self, x: i32) -> i32 {
+        self.offset_x + (x as f32 * self.scale).round() as i32
+    }
+
+    pub fn y(&self, y: i32) -> i32 {
+        self.offset_y + (y as f32 * self.scale).round() as i32
+    }
+
+    // Position in the design of a point of the window, like the position of the
+    // mouse
+    pub fn design_point(&self, x: i32, y: i32) -> (i32, i32) {
+        (
+            ((x - self.offset_x) as f32 / self.scale).floor() as i32,
+            ((y - self.offset_y) as f32 / self.scale).floor() as i32,
+        )
+    }
+
+    // The rectangle is computed from its scaled corners, so two rectangles that
+    // touch in the design also touch in the window without any gap between them
+    pub fn rect(&self, x: i32, y: i32, width: u32, height: u32) -> Rect {
+        let left = self.x(x);
+        let top = self.y(y);
+        let right = self.x(x + width as i32);
+        let bottom = self.y(y + height as i32);
+
+        Rect::new(
+            left,
+            top,
+            (right - left).max(1) as u32,
+            (bottom - top).max(1) as u32,
+        )
+    }
+
+    // The area of the window used by the game, without the bars
+    pub fn area(&self) -> Rect {
+        self.rect(0, 0, DESIGN_WIDTH, DESIGN_HEIGHT)
+    }
+
+    // The font can only be drawn with whole scales, and never smaller than one
+    pub fn text_scale(&self, scale: u32) -> u32 {
+        ((scale as f32 * self.scale).round() as u32).max(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layout() {
+        // The design size doesn't change anything
+        let layout = Layout::new(DESIGN_WIDTH, DESIGN_HEIGHT, ScaleMode::Integer);
+        assert_eq!(layout.rect(50, 100, 40, 40), Rect::new(50, 100, 40, 40));
+
+        // A wide window keeps the proportions and centers the game
+        let layout = Layout::new(1920, 1080, ScaleMode::Aspect);
+        assert_eq!(layout.scale, 1.35);
+        assert_eq!(layout.area(), Rect::new(555, 0, 810, 1080));
+
+        // The integer scale only uses whole multiples of the design
+        let layout = Layout::new(1920, 1080, ScaleMode::Integer);
+        assert_eq!(layout.scale, 1.0);
+        assert_eq!(layout.area(), Rect::new(660, 140, 600, 800));
+
+        let layout = Layout::new(1300, 1700, ScaleMode::Integer);
+        assert_eq!(layout.scale, 2.0);
+        assert_eq!(layout.text_scale(2), 4);
+        assert_eq!(layout.design_point(250, 450), (100, 200));
+
+        // A small window is shrunk and the blocks still touch each other
+        let layout = Layout::new(300, 400, ScaleMode::Integer);
+        assert_eq!(layout.scale, 0.5);
+        let first = layout.rect(50, 100, 40, 40);
+        let second = layout.rect(90, 100, 40, 40);
+        assert_eq!(first.right(), second.left());
+    }
+}
@@ -1,14 +1,32 @@
 use std::collections::HashMap;
 
+use common::assets::{self, Assets, SearchPath};
+use common::screenshot::Screenshots;
+use common::timing::{FpsCounter, FrameLimiter};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
-use sdl2::render::{Canvas, Texture, TextureCreator};
+use sdl2::render::{BlendMode, Canvas, Texture, TextureCreator};
+use sdl2::surface::Surface;
 use sdl2::video::{Window, WindowContext};
 
+pub mod debug;
+pub mod font;
+pub mod framebuffer;
+pub mod layout;
+pub mod renderer;
 mod textures;
+pub mod theme;
+use crate::input::InputMap;
+use crate::scenes::menu::{MENU_LINE, MENU_TOP};
+use crate::scores::{HighScores, MAX_SCORES};
 use crate::tetris;
+use debug::DebugOverlay;
+use layout::{Layout, ScaleMode, DESIGN_HEIGHT, DESIGN_WIDTH};
+use renderer::Renderer;
+use tetris::actions::Action;
 use tetris::Shapes;
 use textures::BOX_SIZE;
+use theme::{Pattern, Theme};
 
 const MARGIN_X: i32 = 50;
 const MARGIN_Y: i32 = 100;
@@ -21,6 +39,38 @@ const NEXT_AREA_BOX: u32 = 20;
 const NEXT_AREA_PAD_X: i32 = 10;
 const NEXT_AREA_PAD_Y: i32 = 30;
 
+const HOLD_AREA_Y: i32 = 200;
+
+const TEXT_SCALE: u32 = 2;
+const TEXT_LINE: i32 = 24;
+
+const SHAPES: [Shapes; 7] = [
+    Shapes::ShapeE,
+    Shapes::ShapeS,
+    Shapes::ShapeZ,
+    Shapes::ShapeT,
+    Shapes::ShapeL,
+    Shapes::ShapeR,
+    Shapes::ShapeG,
+];
+
+const BACKGROUND: Color = Color::RGB(10, 10, 10);
+const BARS: Color = Color::RGB(0, 0, 0);
+const FPS_COLOR: Color = Color::RGB(160, 160, 160);
+
+// The statistics and the debug overlay are drawn with a small font on the
+// right of the board, under the held piece
+const SIDE_X: i32 = 460;
+const SIDE_LINE: i32 = 12;
+const STATS_Y: i32 = 330;
+const STATS_COLOR: Color = Color::RGB(200, 200, 200);
+const DEBUG_Y: i32 = 500;
+const DEBUG_COLOR: Color = Color::RGB(0, 255, 128);
+const COLLISION_COLOR: Color = Color::RGBA(255, 0, 0, 120);
+
+// The game under the pause menu is darkened with this color
+const MENU_SHADE: Color = Color::RGBA(0, 0, 0, 180);
+
 // Struct to contain all the textures that will be used during the game.
 // The textures are stored like this to avoid computing them all the time
 // and also to practice a bit with lifetimes and references.
@@ -28,10 +78,19 @@ const NEXT_AREA_PAD_Y: i32 = 30;
 // Also, since a mutable reference to the canvas is stored in the TextureManager
 // then all the operations that will be done in the canvas has to be done
 // through the TextureManager, otherwise there will be multiple mut borrows.
+//
+// All the positions are given in the design size of the window, and the layout
+// converts them to the real size of the window when drawing.
 pub struct TextureManager<'a> {
     pub canvas: &'a mut Canvas<Window>,
     pub creator: &'a TextureCreator<WindowContext>,
     pub board_textures: HashMap<tetris::Shapes, Texture<'a>>,
+    pub images: Assets<Surface<'static>>, // Block images of the themes that have been used
+    pub layout: Layout,
+    pub screenshots: Screenshots,
+    pub limiter: FrameLimiter,
+    pub fps: FpsCounter,
+    pub show_fps: bool,
 }
 
 impl<'a> TextureManager<'a> {
@@ -41,139 +100,494 @@ impl<'a> TextureManager<'a> {
         // shapes. Also, since only one texture creator is needed to manage and create all the
         // objects, then a reference is also stored in this struct
 
+        let (width, height) = canvas
+            .output_size()
+            .unwrap_or((DESIGN_WIDTH, DESIGN_HEIGHT));
+
         TextureManager {
             canvas,
             creator,
             board_textures: HashMap::new(),
+            images: Assets::new(SearchPath::from_exe()),
+            layout: Layout::new(width, height, ScaleMode::Integer),
+            screenshots: Screenshots::default(),
+            limiter: FrameLimiter::new(None),
+            fps: FpsCounter::default(),
+            show_fps: false,
         }
     }
 
-    pub fn load_textures(&mut self) -> Result<(), String> {
+    // Computes the layout again for the current size of the window. It has to be
+    // called every time the window changes its size
+    pub fn update_layout(&mut self, mode: ScaleMode) -> Result<(), String> {
+        let (width, height) = self.canvas.output_size()?;
+        self.layout = Layout::new(width, height, mode);
+
+        Ok(())
+    }
+
+    pub fn load_textures(&mut self, theme: &Theme, patterns: bool) -> Result<(), String> {
         // Loading all the textures that will be used in the game this dictionary will be used to
         // avoid creating all the time the textures that corresponds to the figures and all the
         // objects that are going to be drawn in the board. A similar texture manager can be used
         // to store sprites
+        //
+        // The textures are made from the theme, and loading another theme replaces them all,
+        // so the theme can be changed while the game is running. With the patterns each shape
+        // also gets its own pattern, so the shapes can be told apart without their colors
 
-        self.board_textures.insert(
-            Shapes::ShapeE,
-            textures::create_box(self.canvas, self.creator)?,
-        );
-        self.board_textures.insert(
-            Shapes::ShapeS,
-            textures::shape_s(self.canvas, self.creator)?,
-        );
-        self.board_textures.insert(
-            Shapes::ShapeZ,
-            textures::shape_z(self.canvas, self.creator)?,
-        );
-        self.board_textures.insert(
-            Shapes::ShapeT,
-            textures::shape_t(self.canvas, self.creator)?,
-        );
-        self.board_textures.insert(
-            Shapes::ShapeL,
-            textures::shape_l(self.canvas, self.creator)?,
-        );
-        self.board_textures.insert(
-            Shapes::ShapeR,
-            textures::shape_r(self.canvas, self.creator)?,
-        );
+        // The image of the theme is kept in the asset manager, so the blocks can be made
+        // again when it changes, and its texture is only needed while they are made
+        let mut image = match theme.image.as_ref() {
+            Some(image) => {
+                let handle = self
+                    .images
+                    .load(&image.path, assets::load_bmp)
+                    .map_err(|e| format!("{}: {}", image.path.display(), e))?;
+                let texture = self
+                    .creator
+                    .create_texture_from_surface(self.images.get(handle))
+                    .map_err(|e| e.to_string())?;
+                Some((image, texture))
+            }
+            None => None,
+        };
+
+        for shape in SHAPES.iter() {
+            let color = theme.color(*shape);
+
+            let mut texture = match image.as_mut() {
+                // A tile sheet gives the tile of each shape in the order of the theme. The
+                // shapes that are not in the sheet are drawn with the style of the theme
+                Some((image, sheet)) if !image.order.is_empty() => {
+                    match image.order.iter().position(|tile| tile == shape) {
+                        Some(index) => {
+                            let size = image.tile_size;
+                            let source = Rect::new(index as i32 * size as i32, 0, size, size);
+                            textures::image_block(
+                                self.canvas,
+                                self.creator,
+                                sheet,
+                                Some(source),
+                                None,
+                            )?
+                        }
+                        None if *shape == Shapes::ShapeE => {
+                            textures::create_box(self.canvas, self.creator, color, theme.grid)?
+                        }
+                        None => {
+                            textures::create_block(self.canvas, self.creator, theme.style, color)?
+                        }
+                    }
+                }
+                _ if *shape == Shapes::ShapeE => {
+                    textures::create_box(self.canvas, self.creator, color, theme.grid)?
+                }
+                // A single block image is tinted with the color of each shape
+                Some((_, block)) => {
+                    textures::image_block(self.canvas, self.creator, block, None, Some(color))?
+                }
+                None => textures::create_block(self.canvas, self.creator, theme.style, color)?,
+            };
+
+            if let Some(pattern) = Pattern::for_shape(*shape).filter(|_| patterns) {
+                let color = theme::contrast(color);
+                textures::add_pattern(self.canvas, &mut texture, pattern, color)?;
+            }
+
+            self.board_textures.insert(*shape, texture);
+        }
 
         Ok(())
     }
 
-    pub fn draw_game(&mut self, tetris: &tetris::Tetris) -> Result<(), String> {
-        // Drawing all the textures and board accordingly to the status of the game.
-        // Since the board is represented by a matrix (a vector of vectors) and each
-        // cell in the matrix will be drawn with a different texture in order to
-        // give the ilusion of separate figures on the board
+    pub fn draw_game(
+        &mut self,
+        tetris: &tetris::Tetris,
+        debug: Option<&DebugOverlay>,
+    ) -> Result<(), String> {
+        self.render_game(tetris)?;
 
-        self.canvas.set_draw_color(Color::RGB(10, 10, 10));
-        self.canvas.clear();
+        if let Some(debug) = debug {
+            self.draw_debug(tetris, debug)?;
+        }
 
-        // Drawing the area for the next piece
-        // In this rectangle the next piece is going to be shown
-        self.canvas.set_draw_color(Color::RGB(200, 200, 200));
-        self.canvas.fill_rect(Rect::new(
-            NEXT_AREA_X,
-            NEXT_AREA_Y,
-            NEXT_AREA_WIDTH,
-            NEXT_AREA_HEIGHT,
-        ))?;
-
-        // Drawing the next piece in the area for next piece
-        for (i, line) in tetris.next_piece.states[0].iter().enumerate() {
-            for (j, col) in line.iter().enumerate() {
-                if *col == Shapes::ShapeE {
-                    continue;
-                };
-
-                let delta_x = NEXT_AREA_BOX as i32 * j as i32;
-                let delta_y = NEXT_AREA_BOX as i32 * i as i32;
-
-                // Using the Shape enum to select the texture from the hashmap
-                // This texture will be copied to a section of the board
-                self.canvas.copy(
-                    &self.board_textures[col],
-                    None,
-                    Rect::new(
-                        NEXT_AREA_PAD_X + NEXT_AREA_X as i32 + delta_x,
-                        NEXT_AREA_PAD_Y + NEXT_AREA_Y as i32 + delta_y,
-                        NEXT_AREA_BOX,
-                        NEXT_AREA_BOX,
-                    ),
-                )?;
+        self.present()
+    }
+
+    // Cell of the board under a point of the window, if the point is on the board
+    pub fn board_cell(&self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let (x, y) = self.layout.design_point(x, y);
+        let column = (x - MARGIN_X).div_euclid(BOX_SIZE as i32);
+        let row = (y - MARGIN_Y).div_euclid(BOX_SIZE as i32);
+
+        let inside = (0..tetris::BOARD_WIDTH as i32).contains(&column)
+            && (0..tetris::BOARD_HEIGHT as i32).contains(&row);
+        Some((column, row)).filter(|_| inside)
+    }
+
+    fn draw_debug(&mut self, tetris: &tetris::Tetris, debug: &DebugOverlay) -> Result<(), String> {
+        // The cells that stop the current piece are covered with red. The cells
+        // outside the board are not drawn
+        self.canvas.set_blend_mode(BlendMode::Blend);
+        self.canvas.set_draw_color(COLLISION_COLOR);
+        for (x, y) in tetris.blocked_cells() {
+            if x < 0 || y < 0 || x >= tetris::BOARD_WIDTH as i32 || y >= tetris::BOARD_HEIGHT as i32
+            {
+                continue;
             }
+
+            let delta_x = BOX_SIZE as i32 * x;
+            let delta_y = BOX_SIZE as i32 * y;
+            self.canvas.fill_rect(self.layout.rect(
+                MARGIN_X + delta_x,
+                MARGIN_Y + delta_y,
+                BOX_SIZE,
+                BOX_SIZE,
+            ))?;
         }
+        self.canvas.set_blend_mode(BlendMode::None);
 
-        // Drawing the board by checking each element in the matrix. The value is matched
-        // to the enum Shapes in order to draw the correct texture.
-        for (i, line) in tetris.board.iter().enumerate() {
-            for (j, col) in line.iter().enumerate() {
-                let delta_x = BOX_SIZE as i32 * j as i32;
-                let delta_y = BOX_SIZE as i32 * i as i32;
-
-                // Using the Shape enum to select the texture from the hashmap
-                // This texture will be copied to a section of the board
-                self.canvas.copy(
-                    &self.board_textures[col],
-                    None,
-                    Rect::new(MARGIN_X + delta_x, MARGIN_Y + delta_y, BOX_SIZE, BOX_SIZE),
-                )?;
-            }
+        let cell = debug.mouse.and_then(|(x, y)| self.board_cell(x, y));
+        let lines = debug.lines(tetris, self.fps.report(), cell);
+
+        self.draw_side_lines(&lines, DEBUG_Y, DEBUG_COLOR)
+    }
+
+    // Screen shown when the game is over, with all the statistics of the game
+    // The notes are shown under the statistics, like the position of a new high
+    // score or the file where the statistics were saved
+    pub fn draw_report(&mut self, tetris: &tetris::Tetris, notes: &[String]) -> Result<(), String> {
+        self.clear()?;
+
+        let center = DESIGN_WIDTH as i32 / 2;
+        self.draw_centered_text(
+            "GAME OVER",
+            center,
+            MARGIN_Y - 2 * TEXT_LINE,
+            TEXT_SCALE,
+            Color::RGB(255, 255, 255),
+        )?;
+
+        let mut lines = vec![
+            format!("LINES {}", tetris.score),
+            format!("LEVEL {}", tetris.level),
+        ];
+        lines.extend(tetris.stats.panel_lines());
+
+        for (index, line) in lines.iter().enumerate() {
+            let y = MARGIN_Y + index as i32 * TEXT_LINE;
+            self.draw_text(line, MARGIN_X, y, TEXT_SCALE, Color::RGB(200, 200, 200))?;
         }
 
-        // Drawing the current piece on top of the board. The current piece is not "stored"
-        // in the board until it is located in its final position. A piece is stored when its
-        // status values are copied to the board
-        let current_state = tetris.current_piece.current_state;
-
-        // Borrowing the current piece to extract all the information required
-        let current_piece = &tetris.current_piece;
-
-        for (i, line) in current_piece.states[current_state].iter().enumerate() {
-            for (j, col) in line.iter().enumerate() {
-                match col {
-                    Shapes::ShapeE => continue,
-                    _ => {
-                        // Only draw a piece when the value is not empty (ShapeE)
-                        let delta_x = BOX_SIZE as i32 * (j as i32 + current_piece.x_pos as i32);
-                        let delta_y = BOX_SIZE as i32 * (i as i32 + current_piece.y_pos as i32);
-
-                        // Using the Shape enum to select the texture from the hashmap
-                        // This texture will be copied to a section of the board
-                        self.canvas.copy(
-                            &self.board_textures[col],
-                            None,
-                            Rect::new(MARGIN_X + delta_x, MARGIN_Y + delta_y, BOX_SIZE, BOX_SIZE),
-                        )?;
-                    }
-                }
+        let mut y = MARGIN_Y + (lines.len() as i32 + 1) * TEXT_LINE;
+        for note in notes {
+            self.draw_text(note, MARGIN_X, y, TEXT_SCALE, Color::RGB(120, 120, 120))?;
+            y += TEXT_LINE;
+        }
+        if !notes.is_empty() {
+            y += TEXT_LINE;
+        }
+
+        self.draw_text(
+            "ENTER: CLOSE",
+            MARGIN_X,
+            y,
+            TEXT_SCALE,
+            Color::RGB(255, 255, 255),
+        )?;
+
+        self.present()
+    }
+
+    // Draws a menu with its items centered in the window. The pause menu is drawn
+    // on top of the game, which is darkened so the menu can be read
+    pub fn draw_menu(
+        &mut self,
+        title: &str,
+        labels: &[String],
+        selected: usize,
+        game: Option<&tetris::Tetris>,
+    ) -> Result<(), String> {
+        match game {
+            Some(tetris) => {
+                self.render_game(tetris)?;
+
+                self.canvas.set_blend_mode(BlendMode::Blend);
+                self.canvas.set_draw_color(MENU_SHADE);
+                self.canvas.fill_rect(None)?;
+                self.canvas.set_blend_mode(BlendMode::None);
             }
+            None => self.clear()?,
+        }
+
+        let center = DESIGN_WIDTH as i32 / 2;
+        self.draw_centered_text(
+            title,
+            center,
+            MENU_TOP - 2 * MENU_LINE,
+            TEXT_SCALE,
+            Color::RGB(255, 255, 255),
+        )?;
+
+        for (index, label) in labels.iter().enumerate() {
+            let (text, color) = match index == selected {
+                true => (format!("> {} <", label), Color::RGB(255, 255, 0)),
+                false => (label.clone(), Color::RGB(200, 200, 200)),
+            };
+            let y = MENU_TOP + index as i32 * MENU_LINE;
+            self.draw_centered_text(&text, center, y, TEXT_SCALE, color)?;
+        }
+
+        self.present()
+    }
+
+    pub fn draw_scores(&mut self, scores: &HighScores) -> Result<(), String> {
+        self.clear()?;
+
+        let center = DESIGN_WIDTH as i32 / 2;
+        self.draw_centered_text(
+            "HIGH SCORES",
+            center,
+            MARGIN_Y - 2 * TEXT_LINE,
+            TEXT_SCALE,
+            Color::RGB(255, 255, 255),
+        )?;
+
+        if scores.scores.is_empty() {
+            self.draw_text(
+                "NO SCORES YET",
+                MARGIN_X,
+                MARGIN_Y,
+                TEXT_SCALE,
+                Color::RGB(200, 200, 200),
+            )?;
         }
 
+        for (index, score) in scores.scores.iter().enumerate() {
+            let line = format!(
+                "{:>2}. {:>4} L{:<3} {}:{:02} {}",
+                index + 1,
+                score.lines,
+                score.level,
+                score.seconds / 60,
+                score.seconds % 60,
+                score.rules.to_uppercase()
+            );
+            let y = MARGIN_Y + index as i32 * TEXT_LINE;
+            self.draw_text(&line, MARGIN_X, y, TEXT_SCALE, Color::RGB(200, 200, 200))?;
+        }
+
+        let y = MARGIN_Y + (MAX_SCORES as i32 + 1) * TEXT_LINE;
+        self.draw_text(
+            "ENTER: BACK",
+            MARGIN_X,
+            y,
+            TEXT_SCALE,
+            Color::RGB(255, 255, 255),
+        )?;
+
+        self.present()
+    }
+
+    pub fn draw_controls(
+        &mut self,
+        input: &InputMap,
+        selected: usize,
+        waiting: bool,
+    ) -> Result<(), String> {
+        // Drawing the screen used to change the bindings of the actions. Each line
+        // shows an action and all the keys and buttons that are bound to it
+        self.clear()?;
+
+        let max_chars = (DESIGN_WIDTH as usize - 2 * MARGIN_X as usize) / (TEXT_SCALE as usize * 6);
+
+        self.draw_text(
+            "CONTROLS",
+            MARGIN_X,
+            MARGIN_Y - 2 * TEXT_LINE,
+            TEXT_SCALE,
+            Color::RGB(255, 255, 255),
+        )?;
+
+        for (index, action) in Action::ALL.iter().enumerate() {
+            let y = MARGIN_Y + index as i32 * 2 * TEXT_LINE;
+            let color = if index == selected {
+                Color::RGB(255, 255, 0)
+            } else {
+                Color::RGB(200, 200, 200)
+            };
+
+            let name = action.name().replace('_', " ");
+            let marker = if index == selected { "> " } else { "  " };
+            self.draw_text(
+                &format!("{}{}", marker, name),
+                MARGIN_X,
+                y,
+                TEXT_SCALE,
+                color,
+            )?;
+
+            let bindings: Vec<String> = input
+                .bindings(*action)
+                .iter()
+                .map(|binding| binding.label())
+                .collect();
+            let bindings: String = format!("  {}", bindings.join(", "))
+                .chars()
+                .take(max_chars)
+                .collect();
+            self.draw_text(
+                &bindings,
+                MARGIN_X,
+                y + TEXT_LINE,
+                TEXT_SCALE,
+                Color::RGB(120, 120, 120),
+            )?;
+        }
+
+        let help_y = MARGIN_Y + Action::ALL.len() as i32 * 2 * TEXT_LINE + TEXT_LINE;
+        let help = if waiting {
+            vec!["PRESS A KEY OR BUTTON"]
+        } else {
+            vec![
+                "ENTER: ADD  BACKSPACE: CLEAR",
+                "R: RESET  ESC: SAVE AND RETURN",
+            ]
+        };
+
+        for (index, line) in help.iter().enumerate() {
+            let y = help_y + index as i32 * TEXT_LINE;
+            self.draw_text(line, MARGIN_X, y, TEXT_SCALE, Color::RGB(255, 255, 255))?;
+        }
+
+        self.present()
+    }
+}
+
+impl Renderer for TextureManager<'_> {
+    fn present(&mut self) -> Result<(), String> {
+        // The frame rate of the last second is drawn in the corner, on top of
+        // everything else
+        self.fps.frame();
+        if let Some(report) = self.fps.report().filter(|_| self.show_fps) {
+            let text = format!(
+                "{:.0} FPS {:.1} MS",
+                report.fps,
+                report.average.as_secs_f32() * 1000.0
+            );
+            self.draw_text(&text, 10, 10, TEXT_SCALE, FPS_COLOR)?;
+        }
+
+        self.screenshots.capture(self.canvas);
         self.canvas.present();
 
+        // Without vsync the limiter keeps the game from drawing more frames
+        // than needed
+        self.limiter.wait();
+
         Ok(())
     }
+
+    fn clear(&mut self) -> Result<(), String> {
+        // The parts of the window outside of the game area are drawn as bars
+        self.canvas.set_draw_color(BARS);
+        self.canvas.clear();
+        self.canvas.set_draw_color(BACKGROUND);
+        self.canvas.fill_rect(self.layout.area())
+    }
+
+    fn fill_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        color: Color,
+    ) -> Result<(), String> {
+        self.canvas.set_draw_color(color);
+        self.canvas.fill_rect(self.layout.rect(x, y, width, height))
+    }
+
+    // Using the Shape enum to select the texture from the hashmap. This texture
+    // will be copied to a section of the board
+    fn draw_cell(&mut self, shape: Shapes, x: i32, y: i32, size: u32) -> Result<(), String> {
+        self.canvas.copy(
+            &self.board_textures[&shape],
+            None,
+            self.layout.rect(x, y, size, size),
+        )
+    }
+
+    fn draw_text(
+        &mut self,
+        text: &str,
+        x: i32,
+        y: i32,
+        scale: u32,
+        color: Color,
+    ) -> Result<(), String> {
+        let scale = self.layout.text_scale(scale);
+        let (x, y) = (self.layout.x(x), self.layout.y(y));
+
+        font::draw_text(self.canvas, text, x, y, scale, color)
+    }
+
+    fn draw_centered_text(
+        &mut self,
+        text: &str,
+        x: i32,
+        y: i32,
+        scale: u32,
+        color: Color,
+    ) -> Result<(), String> {
+        // The text is centered with its size in the window, because the scale of
+        // the font is rounded and it doesn't match the design size exactly
+        let scale = self.layout.text_scale(scale);
+        let width = font::text_width(text, scale) as i32;
+        let height = (font::GLYPH_HEIGHT * scale) as i32;
+        let (x, y) = (self.layout.x(x), self.layout.y(y));
+
+        font::draw_text(
+            self.canvas,
+            text,
+            x - width / 2,
+            y - height / 2,
+            scale,
+            color,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use common::golden::{self, Tolerance};
+    use tetris::puzzle::PuzzlePack;
+    use tetris::rules::Ruleset;
+
+    #[test]
+    fn test_draw_game() {
+        let _lock = golden::sdl_lock();
+        let (_ctx, mut canvas) = golden::dummy_window_canvas(DESIGN_WIDTH, DESIGN_HEIGHT).unwrap();
+        let creator = canvas.texture_creator();
+
+        let mut textures = TextureManager::new(&mut canvas, &creator);
+        textures.load_textures(&Theme::classic(), true).unwrap();
+
+        // A puzzle gives a game without random pieces, so it is always drawn the same
+        let pack = PuzzlePack::parse(
+            "[puzzle]\ngoal = lines 1\npieces = R L T\nboard:\nGGG..GGTTG\nGSSG.GGGGG\nend\n",
+        )
+        .unwrap();
+        let mut game = tetris::Tetris::from_puzzle(&pack.puzzles[0], Ruleset::guideline());
+        game.action(Action::Hold);
+        game.action(Action::MoveLeft);
+        game.action(Action::Pause);
+
+        textures.render_game(&game).unwrap();
+
+        let reference = golden::reference(env!("CARGO_MANIFEST_DIR"), "game");
+        golden::assert_golden(textures.canvas, &reference, Tolerance::default());
+    }
 }
@@ -0,0 +1,176 @@
+use sdl2::pixels::Color;
+
+use super::font;
+use super::textures::BOX_SIZE;
+use super::{
+    HOLD_AREA_Y, MARGIN_X, MARGIN_Y, NEXT_AREA_BOX, NEXT_AREA_HEIGHT, NEXT_AREA_PAD_X,
+    NEXT_AREA_PAD_Y, NEXT_AREA_WIDTH, NEXT_AREA_X, NEXT_AREA_Y, SIDE_LINE, SIDE_X, STATS_COLOR,
+    STATS_Y, TEXT_SCALE,
+};
+use crate::tetris::pieces::TetrisPiece;
+use crate::tetris::{self, Shapes};
+
+// The drawing operations needed to draw a game. The game is drawn the same way
+// into any backend that implements them: the SDL textures of the window or a
+// framebuffer in memory. All the positions and sizes are given in the design
+// size of the window, and each backend converts them to its own pixels
+pub trait Renderer {
+    fn clear(&mut self) -> Result<(), String>;
+
+    fn fill_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+        color: Color,
+    ) -> Result<(), String>;
+
+    // Draws a cell of the board, or of a piece, with the look of its shape
+    fn draw_cell(&mut self, shape: Shapes, x: i32, y: i32, size: u32) -> Result<(), String>;
+
+    // The scale is the size of the pixels of the font in the design
+    fn draw_text(
+        &mut self,
+        text: &str,
+        x: i32,
+        y: i32,
+        scale: u32,
+        color: Color,
+    ) -> Result<(), String>;
+
+    fn present(&mut self) -> Result<(), String>;
+
+    // Draws a text with its center in the given position
+    fn draw_centered_text(
+        &mut self,
+        text: &str,
+        x: i32,
+        y: i32,
+        scale: u32,
+        color: Color,
+    ) -> Result<(), String> {
+        let width = font::text_width(text, scale) as i32;
+        let height = (font::GLYPH_HEIGHT * scale) as i32;
+
+        self.draw_text(text, x - width / 2, y - height / 2, scale, color)
+    }
+
+    fn draw_preview(
+        &mut self,
+        piece: Option<&TetrisPiece>,
+        y: i32,
+        label: &str,
+    ) -> Result<(), String> {
+        // Drawing an area on the side of the board to show a piece that is not
+        // being played. In this rectangle the piece is going to be shown
+        self.fill_rect(
+            NEXT_AREA_X,
+            y,
+            NEXT_AREA_WIDTH,
+            NEXT_AREA_HEIGHT,
+            Color::RGB(200, 200, 200),
+        )?;
+
+        self.draw_text(
+            label,
+            NEXT_AREA_X + NEXT_AREA_PAD_X,
+            y + NEXT_AREA_PAD_X,
+            TEXT_SCALE,
+            Color::RGB(10, 10, 10),
+        )?;
+
+        let piece = match piece {
+            Some(piece) => piece,
+            None => return Ok(()),
+        };
+
+        for (i, line) in piece.states[0].iter().enumerate() {
+            for (j, col) in line.iter().enumerate() {
+                if *col == Shapes::ShapeE {
+                    continue;
+                };
+
+                let delta_x = NEXT_AREA_BOX as i32 * j as i32;
+                let delta_y = NEXT_AREA_BOX as i32 * i as i32;
+
+                self.draw_cell(
+                    *col,
+                    NEXT_AREA_PAD_X + NEXT_AREA_X + delta_x,
+                    NEXT_AREA_PAD_Y + y + delta_y,
+                    NEXT_AREA_BOX,
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Draws lines of text with the small font on the right of the board
+    fn draw_side_lines(&mut self, lines: &[String], y: i32, color: Color) -> Result<(), String> {
+        for (i, line) in lines.iter().enumerate() {
+            self.draw_text(line, SIDE_X, y + i as i32 * SIDE_LINE, 1, color)?;
+        }
+
+        Ok(())
+    }
+
+    // Draws the game without presenting it, so the tests can read the pixels
+    fn render_game(&mut self, tetris: &tetris::Tetris) -> Result<(), String> {
+        // Drawing all the cells and board accordingly to the status of the game.
+        // Since the board is represented by a matrix (a vector of vectors) and each
+        // cell in the matrix will be drawn with a different look in order to
+        // give the ilusion of separate figures on the board
+
+        self.clear()?;
+
+        // Drawing the areas for the next piece and the held piece. A puzzle that
+        // is playing its last piece doesn't have a next piece to show
+        self.draw_preview(tetris.next_piece.as_ref(), NEXT_AREA_Y, "NEXT")?;
+        self.draw_preview(tetris.hold_piece.as_ref(), HOLD_AREA_Y, "HOLD")?;
+
+        // Drawing the board by checking each element in the matrix. The value is matched
+        // to the enum Shapes in order to draw the correct cell.
+        for (i, line) in tetris.board.iter().enumerate() {
+            for (j, col) in line.iter().enumerate() {
+                let delta_x = BOX_SIZE as i32 * j as i32;
+                let delta_y = BOX_SIZE as i32 * i as i32;
+
+                self.draw_cell(*col, MARGIN_X + delta_x, MARGIN_Y + delta_y, BOX_SIZE)?;
+            }
+        }
+
+        // Drawing the current piece on top of the board. The current piece is not "stored"
+        // in the board until it is located in its final position. A piece is stored when its
+        // status values are copied to the board
+        let current_piece = &tetris.current_piece;
+        let current_state = current_piece.current_state;
+
+        // The current piece is hidden while it waits for the entry delay
+        if !tetris.is_entering() {
+            for (i, line) in current_piece.states[current_state].iter().enumerate() {
+                for (j, col) in line.iter().enumerate() {
+                    // Only draw a piece when the value is not empty (ShapeE)
+                    if *col == Shapes::ShapeE {
+                        continue;
+                    }
+
+                    let delta_x = BOX_SIZE as i32 * (j as i32 + current_piece.x_pos as i32);
+                    let delta_y = BOX_SIZE as i32 * (i as i32 + current_piece.y_pos as i32);
+
+                    self.draw_cell(*col, MARGIN_X + delta_x, MARGIN_Y + delta_y, BOX_SIZE)?;
+                }
+            }
+        }
+
+        self.draw_side_lines(&tetris.stats.panel_lines(), STATS_Y, STATS_COLOR)?;
+
+        if tetris.paused {
+            let x = MARGIN_X + (BOX_SIZE * tetris::BOARD_WIDTH as u32 / 2) as i32;
+            let y = MARGIN_Y + (BOX_SIZE * tetris::BOARD_HEIGHT as u32 / 2) as i32;
+            self.draw_centered_text("PAUSED", x, y, TEXT_SCALE, Color::RGB(255, 255, 255))?;
+        }
+
+        Ok(())
+    }
+}
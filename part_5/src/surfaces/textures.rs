@@ -1,13 +1,17 @@
 use sdl2::pixels::Color;
-use sdl2::rect::Rect;
+use sdl2::rect::{Point, Rect};
 use sdl2::render::{Canvas, Texture, TextureCreator};
 use sdl2::video::{Window, WindowContext};
 
+use super::theme::{shade, Pattern, Style};
+
 pub const BOX_SIZE: u32 = 40;
 
-pub fn create_box<'s, 'a>(
-    canvas: &'s mut Canvas<Window>,
+pub fn create_box<'a>(
+    canvas: &mut Canvas<Window>,
     texture_creator: &'a TextureCreator<WindowContext>,
+    color: Color,
+    grid: Color,
 ) -> Result<Texture<'a>, String> {
     // Function to create a box texture for the board. This is the figure that will be drawn
     // and seen, when no figure is on top of the board.
@@ -18,14 +22,14 @@ pub fn create_box<'s, 'a>(
 
     canvas
         .with_texture_canvas(&mut box_target, |texture| {
-            // Selecting gray color to box and painting all
+            // Selecting the color of the theme to box and painting all
             // the box with that color
-            texture.set_draw_color(Color::RGB(100, 100, 100));
+            texture.set_draw_color(color);
             texture.clear();
 
-            // Changing color to red to draw a rectangle in the
+            // Changing color to the grid color to draw a rectangle in the
             // texture
-            texture.set_draw_color(Color::RGB(250, 0, 0));
+            texture.set_draw_color(grid);
             texture
                 .draw_rect(Rect::new(1, 1, BOX_SIZE - 2, BOX_SIZE - 2))
                 .expect("Unable to draw box");
@@ -35,94 +39,102 @@ pub fn create_box<'s, 'a>(
     Ok(box_target)
 }
 
-// There are 5 shapes in the tetris game. Since we want each piece to be unique then
-// we will use 5 different funtions to create different textures for each shape
-// Each created texture will be stored in the TextureManager hashmap.
-pub fn shape_s<'s, 'a>(
-    canvas: &'s mut Canvas<Window>,
+// All the shapes are drawn by the same function, using the color and the style
+// that the theme selects for each shape. Each created texture will be stored in
+// the TextureManager hashmap.
+pub fn create_block<'a>(
+    canvas: &mut Canvas<Window>,
     texture_creator: &'a TextureCreator<WindowContext>,
+    style: Style,
+    color: Color,
 ) -> Result<Texture<'a>, String> {
     let mut box_target = texture_creator
         .create_texture_target(None, BOX_SIZE, BOX_SIZE)
         .map_err(|e| e.to_string())?;
 
-    canvas
-        .with_texture_canvas(&mut box_target, |texture| {
-            texture.set_draw_color(Color::RGB(0, 0, 0));
-            texture.clear();
-
-            texture.set_draw_color(Color::RGB(255, 0, 0));
-            texture
-                .fill_rect(Rect::new(4, 4, BOX_SIZE - 8, BOX_SIZE - 8))
-                .expect("Unable to draw box");
-        })
-        .map_err(|e| e.to_string())?;
-
-    Ok(box_target)
-}
-
-pub fn shape_z<'s, 'a>(
-    canvas: &'s mut Canvas<Window>,
-    texture_creator: &'a TextureCreator<WindowContext>,
-) -> Result<Texture<'a>, String> {
-    let mut box_target = texture_creator
-        .create_texture_target(None, BOX_SIZE, BOX_SIZE)
-        .map_err(|e| e.to_string())?;
+    let size = BOX_SIZE as i32;
 
     canvas
         .with_texture_canvas(&mut box_target, |texture| {
             texture.set_draw_color(Color::RGB(0, 0, 0));
             texture.clear();
 
-            texture.set_draw_color(Color::RGB(0, 255, 0));
-            texture
-                .fill_rect(Rect::new(4, 4, BOX_SIZE - 8, BOX_SIZE - 8))
-                .expect("Unable to draw box");
+            let result = match style {
+                Style::Flat => {
+                    texture.set_draw_color(color);
+                    texture.fill_rect(Rect::new(4, 4, BOX_SIZE - 8, BOX_SIZE - 8))
+                }
+                Style::Beveled => {
+                    // The light and dark edges are drawn as lines that get shorter
+                    // towards the center, so the corners look cut at 45 degrees
+                    texture.set_draw_color(color);
+                    texture
+                        .fill_rect(Rect::new(1, 1, BOX_SIZE - 2, BOX_SIZE - 2))
+                        .and_then(|_| {
+                            (1..6).try_for_each(|i| {
+                                texture.set_draw_color(shade(color, 0.5));
+                                texture.draw_line(Point::new(i, i), Point::new(size - 1 - i, i))?;
+                                texture.draw_line(Point::new(i, i), Point::new(i, size - 1 - i))?;
+                                texture.set_draw_color(shade(color, -0.5));
+                                texture.draw_line(
+                                    Point::new(i, size - 1 - i),
+                                    Point::new(size - 1 - i, size - 1 - i),
+                                )?;
+                                texture.draw_line(
+                                    Point::new(size - 1 - i, i),
+                                    Point::new(size - 1 - i, size - 1 - i),
+                                )
+                            })
+                        })
+                }
+                Style::Gradient => (1..size - 1).try_for_each(|y| {
+                    let amount = 0.5 - y as f32 / size as f32;
+                    texture.set_draw_color(shade(color, amount));
+                    texture.draw_line(Point::new(1, y), Point::new(size - 2, y))
+                }),
+                Style::Outlined => {
+                    texture.set_draw_color(shade(color, -0.6));
+                    texture
+                        .fill_rect(Rect::new(2, 2, BOX_SIZE - 4, BOX_SIZE - 4))
+                        .and_then(|_| {
+                            texture.set_draw_color(color);
+                            texture.draw_rect(Rect::new(2, 2, BOX_SIZE - 4, BOX_SIZE - 4))?;
+                            texture.draw_rect(Rect::new(3, 3, BOX_SIZE - 6, BOX_SIZE - 6))
+                        })
+                }
+            };
+
+            result.expect("Unable to draw box");
         })
         .map_err(|e| e.to_string())?;
 
     Ok(box_target)
 }
 
-pub fn shape_l<'s, 'a>(
-    canvas: &'s mut Canvas<Window>,
+// Creates a block from a part of an image. The source is the tile to use, or
+// the whole image when there is no source, and the tint changes the color of
+// the image so a single white block can be used for all the shapes
+pub fn image_block<'a>(
+    canvas: &mut Canvas<Window>,
     texture_creator: &'a TextureCreator<WindowContext>,
+    image: &mut Texture,
+    source: Option<Rect>,
+    tint: Option<Color>,
 ) -> Result<Texture<'a>, String> {
     let mut box_target = texture_creator
         .create_texture_target(None, BOX_SIZE, BOX_SIZE)
         .map_err(|e| e.to_string())?;
 
-    canvas
-        .with_texture_canvas(&mut box_target, |texture| {
-            texture.set_draw_color(Color::RGB(0, 0, 0));
-            texture.clear();
-
-            texture.set_draw_color(Color::RGB(0, 0, 255));
-            texture
-                .fill_rect(Rect::new(4, 4, BOX_SIZE - 8, BOX_SIZE - 8))
-                .expect("Unable to draw box");
-        })
-        .map_err(|e| e.to_string())?;
-
-    Ok(box_target)
-}
-
-pub fn shape_t<'s, 'a>(
-    canvas: &'s mut Canvas<Window>,
-    texture_creator: &'a TextureCreator<WindowContext>,
-) -> Result<Texture<'a>, String> {
-    let mut box_target = texture_creator
-        .create_texture_target(None, BOX_SIZE, BOX_SIZE)
-        .map_err(|e| e.to_string())?;
+    let tint = tint.unwrap_or(Color::RGB(255, 255, 255));
+    image.set_color_mod(tint.r, tint.g, tint.b);
 
     canvas
         .with_texture_canvas(&mut box_target, |texture| {
             texture.set_draw_color(Color::RGB(0, 0, 0));
             texture.clear();
 
-            texture.set_draw_color(Color::RGB(255, 0, 255));
             texture
-                .fill_rect(Rect::new(4, 4, BOX_SIZE - 8, BOX_SIZE - 8))
+                .copy(image, source, None)
                 .expect("Unable to draw box");
         })
         .map_err(|e| e.to_string())?;
@@ -130,25 +142,59 @@ pub fn shape_t<'s, 'a>(
     Ok(box_target)
 }
 
-pub fn shape_r<'s, 'a>(
-    canvas: &'s mut Canvas<Window>,
-    texture_creator: &'a TextureCreator<WindowContext>,
-) -> Result<Texture<'a>, String> {
-    let mut box_target = texture_creator
-        .create_texture_target(None, BOX_SIZE, BOX_SIZE)
-        .map_err(|e| e.to_string())?;
+// Draws a pattern on top of a block that has already been created. The pattern
+// stays inside the block, away from its edges, so the style can still be seen
+pub fn add_pattern(
+    canvas: &mut Canvas<Window>,
+    block: &mut Texture,
+    pattern: Pattern,
+    color: Color,
+) -> Result<(), String> {
+    let low = 8;
+    let high = BOX_SIZE as i32 - 9;
 
     canvas
-        .with_texture_canvas(&mut box_target, |texture| {
-            texture.set_draw_color(Color::RGB(0, 0, 0));
-            texture.clear();
-
-            texture.set_draw_color(Color::RGB(0, 255, 255));
-            texture
-                .fill_rect(Rect::new(4, 4, BOX_SIZE - 8, BOX_SIZE - 8))
-                .expect("Unable to draw box");
+        .with_texture_canvas(block, |texture| {
+            texture.set_draw_color(color);
+
+            let result = match pattern {
+                Pattern::DiagonalDown => [-8, 0, 8].iter().try_for_each(|offset| {
+                    let start = Point::new(low + offset.max(&0), low - offset.min(&0));
+                    let end = Point::new(high + offset.min(&0), high - offset.max(&0));
+                    texture.draw_line(start, end)
+                }),
+                Pattern::DiagonalUp => [-8, 0, 8].iter().try_for_each(|offset| {
+                    let start = Point::new(low + offset.max(&0), high + offset.min(&0));
+                    let end = Point::new(high + offset.min(&0), low + offset.max(&0));
+                    texture.draw_line(start, end)
+                }),
+                Pattern::Cross => {
+                    let middle = BOX_SIZE as i32 / 2;
+                    texture
+                        .fill_rect(Rect::new(middle - 2, low, 4, (high - low + 1) as u32))
+                        .and_then(|_| {
+                            texture.fill_rect(Rect::new(
+                                low,
+                                middle - 2,
+                                (high - low + 1) as u32,
+                                4,
+                            ))
+                        })
+                }
+                Pattern::Horizontal => (low..=high).step_by(6).try_for_each(|y| {
+                    texture.fill_rect(Rect::new(low, y, (high - low + 1) as u32, 2))
+                }),
+                Pattern::Vertical => (low..=high).step_by(6).try_for_each(|x| {
+                    texture.fill_rect(Rect::new(x, low, 2, (high - low + 1) as u32))
+                }),
+                Pattern::Dots => (low..=high).step_by(8).try_for_each(|y| {
+                    (low..=high)
+                        .step_by(8)
+                        .try_for_each(|x| texture.fill_rect(Rect::new(x, y, 3, 3)))
+                }),
+            };
+
+            result.expect("Unable to draw pattern");
         })
-        .map_err(|e| e.to_string())?;
-
-    Ok(box_target)
+        .map_err(|e| e.to_string())
 }
@@ -0,0 +1,443 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sdl2::pixels::Color;
+
+use crate::tetris::Shapes;
+
+// How the blocks of a theme are drawn when the theme doesn't use an image
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Style {
+    Flat,     // A square of the color with a black border
+    Beveled,  // A square with light top and left edges and dark bottom and right edges
+    Gradient, // The color goes from light at the top to dark at the bottom
+    Outlined, // A bright outline around a dark square of the color
+}
+
+impl Style {
+    pub fn from_name(name: &str) -> Option<Style> {
+        match name {
+            "flat" => Some(Style::Flat),
+            "beveled" => Some(Style::Beveled),
+            "gradient" => Some(Style::Gradient),
+            "outlined" => Some(Style::Outlined),
+            _ => None,
+        }
+    }
+}
+
+// Palettes that replace the colors of the pieces with colors that can be told
+// apart with each kind of color blindness. The normal palette keeps the colors
+// of the theme
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Palette {
+    Normal,
+    Protanopia,   // Weak red
+    Deuteranopia, // Weak green
+    Tritanopia,   // Weak blue
+}
+
+impl Palette {
+    pub const ALL: [Palette; 4] = [
+        Palette::Normal,
+        Palette::Protanopia,
+        Palette::Deuteranopia,
+        Palette::Tritanopia,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Palette::Normal => "normal",
+            Palette::Protanopia => "protanopia",
+            Palette::Deuteranopia => "deuteranopia",
+            Palette::Tritanopia => "tritanopia",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Palette> {
+        Palette::ALL
+            .iter()
+            .find(|palette| palette.name() == name)
+            .copied()
+    }
+
+    pub fn next(&self) -> Palette {
+        let index = Palette::ALL.iter().position(|palette| palette == self);
+        Palette::ALL[(index.unwrap_or(0) + 1) % Palette::ALL.len()]
+    }
+
+    // The colors of the pieces for the palette, in the order S, Z, T, L and R. The
+    // red and green palettes avoid mixing red with green and use blue, orange and
+    // yellow instead, while the blue palette relies on red, teal and brightness
+    fn colors(&self) -> Option<[Color; 5]> {
+        match self {
+            Palette::Normal => None,
+            Palette::Protanopia => Some([
+                Color::RGB(0, 114, 178),
+                Color::RGB(230, 159, 0),
+                Color::RGB(204, 121, 167),
+                Color::RGB(240, 228, 66),
+                Color::RGB(86, 180, 233),
+            ]),
+            Palette::Deuteranopia => Some([
+                Color::RGB(0, 114, 178),
+                Color::RGB(213, 94, 0),
+                Color::RGB(240, 228, 66),
+                Color::RGB(86, 180, 233),
+                Color::RGB(204, 121, 167),
+            ]),
+            Palette::Tritanopia => Some([
+                Color::RGB(220, 50, 32),
+                Color::RGB(0, 150, 150),
+                Color::RGB(255, 170, 200),
+                Color::RGB(240, 240, 240),
+                Color::RGB(140, 0, 60),
+            ]),
+        }
+    }
+}
+
+// Patterns drawn on top of the blocks so the shapes can be told apart without
+// their colors. Each shape has its own pattern
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    DiagonalDown, // Stripes from the top left to the bottom right
+    DiagonalUp,   // Stripes from the bottom left to the top right
+    Cross,
+    Horizontal,
+    Vertical,
+    Dots,
+}
+
+impl Pattern {
+    pub fn for_shape(shape: Shapes) -> Option<Pattern> {
+        match shape {
+            Shapes::ShapeS => Some(Pattern::DiagonalDown),
+            Shapes::ShapeZ => Some(Pattern::DiagonalUp),
+            Shapes::ShapeT => Some(Pattern::Cross),
+            Shapes::ShapeL => Some(Pattern::Horizontal),
+            Shapes::ShapeR => Some(Pattern::Vertical),
+            Shapes::ShapeG => Some(Pattern::Dots),
+            Shapes::ShapeE => None,
+        }
+    }
+}
+
+// Black or white, whichever can be seen better on top of a color
+pub fn contrast(color: Color) -> Color {
+    let luminance = 0.299 * color.r as f32 + 0.587 * color.g as f32 + 0.114 * color.b as f32;
+
+    match luminance > 140.0 {
+        true => Color::RGB(0, 0, 0),
+        false => Color::RGB(255, 255, 255),
+    }
+}
+
+// The blocks can also be taken from an image. An image with a single block is
+// tinted with the color of each shape. A tile sheet has one tile per shape in
+// a row, in the order given by the theme, and the tiles are used as they are
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockImage {
+    pub path: PathBuf,
+    pub tile_size: u32,
+    pub order: Vec<Shapes>, // Empty for a single block image
+}
+
+// A theme controls how the blocks of the board are drawn. The themes are stored
+// in text files with one `key = value` line per setting, for example:
+//
+//   name = Pastel
+//   style = beveled
+//   s = 255, 150, 150
+//   empty = 40, 40, 40
+//
+// A theme that sets `image = blocks.bmp` uses the image instead of the style.
+// The path of the image is relative to the theme file
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    pub style: Style,
+    pub colors: HashMap<Shapes, Color>,
+    pub grid: Color, // Border of the empty cells
+    pub image: Option<BlockImage>,
+}
+
+const SHAPE_NAMES: [(&str, Shapes); 7] = [
+    ("s", Shapes::ShapeS),
+    ("z", Shapes::ShapeZ),
+    ("t", Shapes::ShapeT),
+    ("l", Shapes::ShapeL),
+    ("r", Shapes::ShapeR),
+    ("garbage", Shapes::ShapeG),
+    ("empty", Shapes::ShapeE),
+];
+
+fn shape_from_name(name: &str) -> Option<Shapes> {
+    SHAPE_NAMES
+        .iter()
+        .find(|(shape_name, _)| *shape_name == name)
+        .map(|(_, shape)| *shape)
+}
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    let parts = value
+        .split(',')
+        .map(|part| part.trim().parse::<u8>())
+        .collect::<Result<Vec<u8>, _>>()
+        .map_err(|_| format!("Invalid color: {}", value))?;
+
+    match parts.as_slice() {
+        [r, g, b] => Ok(Color::RGB(*r, *g, *b)),
+        _ => Err(format!("Invalid color: {}", value)),
+    }
+}
+
+// Moves a color towards white (positive amounts) or black (negative amounts).
+// The amount goes from -1 to 1
+pub fn shade(color: Color, amount: f32) -> Color {
+    let channel = |value: u8| {
+        let value = value as f32;
+        let target = if amount > 0.0 { 255.0 } else { 0.0 };
+        (value + (target - value) * amount.abs()).round() as u8
+    };
+
+    Color::RGB(channel(color.r), channel(color.g), channel(color.b))
+}
+
+impl Theme {
+    // The theme used when there is no theme file. These are the colors the game
+    // has always used
+    pub fn classic() -> Self {
+        let colors = [
+            (Shapes::ShapeS, Color::RGB(255, 0, 0)),
+            (Shapes::ShapeZ, Color::RGB(0, 255, 0)),
+            (Shapes::ShapeT, Color::RGB(255, 0, 255)),
+            (Shapes::ShapeL, Color::RGB(0, 0, 255)),
+            (Shapes::ShapeR, Color::RGB(0, 255, 255)),
+            (Shapes::ShapeG, Color::RGB(150, 150, 150)),
+            (Shapes::ShapeE, Color::RGB(100, 100, 100)),
+        ];
+
+        Theme {
+            name: String::from("Classic"),
+            style: Style::Flat,
+            colors: colors.iter().copied().collect(),
+            grid: Color::RGB(250, 0, 0),
+            image: None,
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let folder = path.parent().unwrap_or_else(|| Path::new(""));
+
+        Theme::parse(&text, folder)
+    }
+
+    // Reads a theme from the text of a theme file. The settings that are not in
+    // the file keep the values of the classic theme
+    pub fn parse(text: &str, folder: &Path) -> Result<Self, String> {
+        let mut theme = Theme::classic();
+        let mut image = None;
+        let mut tile_size = None;
+        let mut order = Vec::new();
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                Some(index) => (line[..index].trim(), line[index + 1..].trim()),
+                None => return Err(format!("Invalid theme line: {}", line)),
+            };
+
+            match key {
+                "name" => theme.name = value.to_string(),
+                "style" => {
+                    theme.style =
+                        Style::from_name(value).ok_or(format!("Unknown style: {}", value))?
+                }
+                "grid" => theme.grid = parse_color(value)?,
+                "image" => image = Some(folder.join(value)),
+                "tile" => {
+                    tile_size = Some(
+                        value
+                            .parse::<u32>()
+                            .map_err(|_| format!("Invalid tile size: {}", value))?,
+                    )
+                }
+                "order" => {
+                    order = value
+                        .split_whitespace()
+                        .map(|name| shape_from_name(name).ok_or(format!("Unknown shape: {}", name)))
+                        .collect::<Result<Vec<Shapes>, String>>()?
+                }
+                _ => {
+                    let shape =
+                        shape_from_name(key).ok_or(format!("Unknown theme key: {}", key))?;
+                    theme.colors.insert(shape, parse_color(value)?);
+                }
+            }
+        }
+
+        // A tile sheet needs the size of its tiles to be sliced
+        if let Some(path) = image {
+            let tile_size = match (tile_size, order.is_empty()) {
+                (Some(size), _) => size,
+                (None, true) => 0, // The whole image is used as the block
+                (None, false) => return Err(String::from("A tile sheet needs a tile size")),
+            };
+
+            theme.image = Some(BlockImage {
+                path,
+                tile_size,
+                order,
+            });
+        }
+
+        Ok(theme)
+    }
+
+    // A copy of the theme with the colors of the pieces taken from a palette
+    pub fn with_palette(&self, palette: Palette) -> Theme {
+        let mut theme = self.clone();
+
+        if let Some(colors) = palette.colors() {
+            let shapes = [
+                Shapes::ShapeS,
+                Shapes::ShapeZ,
+                Shapes::ShapeT,
+                Shapes::ShapeL,
+                Shapes::ShapeR,
+            ];
+
+            for (shape, color) in shapes.iter().zip(colors.iter()) {
+                theme.colors.insert(*shape, *color);
+            }
+        }
+
+        theme
+    }
+
+    pub fn color(&self, shape: Shapes) -> Color {
+        self.colors
+            .get(&shape)
+            .copied()
+            .unwrap_or(Color::RGB(255, 255, 255))
+    }
+}
+
+// The theme files of a folder, sorted by their names. A missing folder doesn't
+// have any theme
+pub fn theme_files(folder: &Path) -> Vec<PathBuf> {
+    let entries = match fs::read_dir(folder) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "theme"))
+        .collect();
+    paths.sort();
+
+    paths
+}
+
+// Loads the classic theme and all the themes of a folder. A theme that can't be
+// loaded is reported and left out
+pub fn load_themes(folder: &Path) -> Vec<Theme> {
+    let mut themes = vec![Theme::classic()];
+
+    for path in theme_files(folder) {
+        match Theme::load(&path) {
+            Ok(theme) => themes.push(theme),
+            Err(e) => println!("Unable to load the theme {}: {}", path.display(), e),
+        }
+    }
+
+    themes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_theme() {
+        let text = "
+            # A theme with a tile sheet
+            name = Test
+            style = outlined
+            s = 10, 20, 30
+            grid = 1,2,3
+            image = blocks.bmp
+            tile = 16
+            order = empty s z t l r garbage
+        ";
+
+        let theme = Theme::parse(text, Path::new("themes")).unwrap();
+        assert_eq!(theme.name, "Test");
+        assert_eq!(theme.style, Style::Outlined);
+        assert_eq!(theme.color(Shapes::ShapeS), Color::RGB(10, 20, 30));
+        assert_eq!(theme.color(Shapes::ShapeZ), Color::RGB(0, 255, 0));
+        assert_eq!(theme.grid, Color::RGB(1, 2, 3));
+
+        let image = theme.image.unwrap();
+        assert_eq!(image.path, Path::new("themes").join("blocks.bmp"));
+        assert_eq!(image.tile_size, 16);
+        assert_eq!(image.order[1], Shapes::ShapeS);
+
+        assert!(Theme::parse("style = shiny", Path::new("")).is_err());
+        assert!(Theme::parse("s = 1, 2", Path::new("")).is_err());
+        assert!(Theme::parse("image = a.bmp\norder = s z", Path::new("")).is_err());
+    }
+
+    #[test]
+    fn test_load_themes() {
+        // The themes of the game are loaded after the classic theme
+        let themes = load_themes(Path::new("themes"));
+        let names: Vec<&str> = themes.iter().map(|theme| theme.name.as_str()).collect();
+        assert_eq!(names, ["Classic", "Bevel", "Sunset", "Neon", "Pixel"]);
+
+        let image = themes[4].image.as_ref().unwrap();
+        assert!(image.path.exists());
+        assert_eq!(image.order.len(), 7);
+    }
+
+    #[test]
+    fn test_palette() {
+        let theme = Theme::classic();
+        assert_eq!(theme.with_palette(Palette::Normal), theme);
+
+        // The palettes only change the pieces, and all the pieces are different
+        for palette in Palette::ALL[1..].iter() {
+            let changed = theme.with_palette(*palette);
+            assert_eq!(changed.color(Shapes::ShapeE), theme.color(Shapes::ShapeE));
+            assert_ne!(changed.color(Shapes::ShapeS), theme.color(Shapes::ShapeS));
+
+            let mut colors: Vec<(u8, u8, u8)> = SHAPE_NAMES[..5]
+                .iter()
+                .map(|(_, shape)| changed.color(*shape).rgb())
+                .collect();
+            colors.sort();
+            colors.dedup();
+            assert_eq!(colors.len(), 5);
+        }
+
+        assert_eq!(Palette::from_name("tritanopia"), Some(Palette::Tritanopia));
+        assert_eq!(Palette::Tritanopia.next(), Palette::Normal);
+        assert_eq!(contrast(Color::RGB(240, 228, 66)), Color::RGB(0, 0, 0));
+        assert_eq!(contrast(Color::RGB(0, 114, 178)), Color::RGB(255, 255, 255));
+    }
+
+    #[test]
+    fn test_shade() {
+        let color = Color::RGB(100, 200, 0);
+        assert_eq!(shade(color, 0.0), color);
+        assert_eq!(shade(color, 1.0), Color::RGB(255, 255, 255));
+        assert_eq!(shade(color, -0.5), Color::RGB(50, 100, 0));
+    }
+}
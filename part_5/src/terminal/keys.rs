@@ -0,0 +1,106 @@
+use crate::tetris::actions::Action;
+
+// Keys read from the terminal. The arrows arrive as escape sequences, and any
+// other key is the character it writes
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Escape,
+    Char(char),
+}
+
+// What the game does with a key
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Command {
+    Action(Action),
+    Quit,
+}
+
+// Splits the bytes read from the terminal into keys. The terminal sends the
+// arrows as `ESC [ A` to `ESC [ D`, so an escape that isn't followed by `[` is
+// the escape key itself. Unknown sequences are skipped
+pub fn parse(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut index = 0;
+
+    while index < bytes.len() {
+        match bytes[index] {
+            0x1b if bytes.get(index + 1) == Some(&b'[') => {
+                let key = match bytes.get(index + 2) {
+                    Some(b'A') => Some(Key::Up),
+                    Some(b'B') => Some(Key::Down),
+                    Some(b'C') => Some(Key::Right),
+                    Some(b'D') => Some(Key::Left),
+                    _ => None,
+                };
+
+                keys.extend(key);
+                index += 3;
+            }
+            0x1b => {
+                keys.push(Key::Escape);
+                index += 1;
+            }
+            byte => {
+                keys.push(Key::Char(byte as char));
+                index += 1;
+            }
+        }
+    }
+
+    keys
+}
+
+// The terminal keys follow the default keyboard bindings of the window, and
+// Ctrl-C quits because the raw mode doesn't turn it into a signal
+pub fn command(key: Key) -> Option<Command> {
+    let action = match key {
+        Key::Left => Action::MoveLeft,
+        Key::Right => Action::MoveRight,
+        Key::Down => Action::SoftDrop,
+        Key::Up => Action::RotateCw,
+        Key::Escape => return Some(Command::Quit),
+        Key::Char(c) => match c.to_ascii_lowercase() {
+            ' ' => Action::HardDrop,
+            'x' => Action::RotateCw,
+            'z' => Action::RotateCcw,
+            'a' => Action::Rotate180,
+            'c' => Action::Hold,
+            'p' => Action::Pause,
+            'q' | '\u{3}' => return Some(Command::Quit),
+            _ => return None,
+        },
+    };
+
+    Some(Command::Action(action))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            parse(b"\x1b[A\x1b[Dx \x1b"),
+            vec![
+                Key::Up,
+                Key::Left,
+                Key::Char('x'),
+                Key::Char(' '),
+                Key::Escape
+            ]
+        );
+
+        // Unknown sequences are dropped without losing the next keys
+        assert_eq!(parse(b"\x1b[Hc"), vec![Key::Char('c')]);
+
+        assert_eq!(command(Key::Down), Some(Command::Action(Action::SoftDrop)));
+        assert_eq!(command(Key::Char('C')), Some(Command::Action(Action::Hold)));
+        assert_eq!(command(Key::Char('\u{3}')), Some(Command::Quit));
+        assert_eq!(command(Key::Char('k')), None);
+    }
+}
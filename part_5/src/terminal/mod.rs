@@ -0,0 +1,299 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::process::{Command as Process, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+use common::assets::SearchPath;
+use common::timing::{FixedTimestep, FrameLimiter};
+use sdl2::pixels::Color;
+
+pub mod keys;
+pub mod screen;
+use crate::settings::Settings;
+use crate::surfaces::theme::{self, shade, Theme};
+use crate::tetris::pieces::TetrisPiece;
+use crate::tetris::rules::{Ruleset, FRAME_RATE};
+use crate::tetris::{Shapes, Tetris, BOARD_HEIGHT, BOARD_WIDTH};
+use crate::{SETTINGS_FILE, THEME_FOLDER};
+use keys::Command;
+use screen::Screen;
+
+// Size of the screen in characters. Each cell of the board is two characters
+// wide, so the cells look square in most terminals
+const SCREEN_WIDTH: usize = 64;
+const SCREEN_HEIGHT: usize = BOARD_HEIGHT + 3;
+
+const SIDE_X: usize = 2 * BOARD_WIDTH + 4;
+const STATS_X: usize = SIDE_X + 14;
+const HOLD_Y: usize = 6;
+const SCORE_Y: usize = 12;
+
+const TEXT: Color = Color::RGB(200, 200, 200);
+const BORDER: Color = Color::RGB(120, 120, 120);
+
+const HELP: &str = "ARROWS MOVE  Z X A ROTATE  SPACE DROP  C HOLD  P PAUSE  Q QUIT";
+
+// Puts the terminal in raw mode while it is alive: the keys are read as soon as
+// they are pressed and they aren't written on the screen. The screen is switched
+// to the alternate buffer so the terminal is left as it was when the game ends
+struct RawMode {
+    saved: String, // Settings of the terminal before the raw mode
+}
+
+impl RawMode {
+    fn enter() -> Result<Self, String> {
+        let saved = stty(&["-g"])?;
+        stty(&["raw", "-echo"])?;
+
+        print!("\x1b[?1049h\x1b[?25l\x1b[2J");
+        io::stdout().flush().map_err(|e| e.to_string())?;
+
+        Ok(RawMode {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        print!("\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+// The settings of the terminal are changed with stty, which reads the terminal
+// from its standard input
+fn stty(args: &[&str]) -> Result<String, String> {
+    let output = Process::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()
+        .map_err(|e| format!("The terminal frontend needs stty: {}", e))?;
+
+    if !output.status.success() {
+        return Err(String::from(
+            "The terminal frontend needs to run in a terminal",
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// Reading the standard input blocks, so it is read in its own thread and the
+// bytes are sent to the game
+fn read_input() -> Receiver<Vec<u8>> {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut stdin = io::stdin();
+        let mut buffer = [0; 64];
+
+        while let Ok(count) = stdin.read(&mut buffer) {
+            if count == 0 || sender.send(buffer[..count].to_vec()).is_err() {
+                break;
+            }
+        }
+    });
+
+    receiver
+}
+
+// Plays a game in the terminal with the same engine and rules as the window.
+// The terminal doesn't tell when a key is released, so every key is pressed and
+// released at once and the held movements use the repetition of the keyboard
+pub fn run(rules: Ruleset, stats_path: Option<&Path>) -> Result<(), String> {
+    // The colors of the pieces come from the theme and palette of the settings
+    let settings = Settings::load(Path::new(SETTINGS_FILE))?;
+    let themes = theme::load_themes(&SearchPath::from_exe().find(THEME_FOLDER));
+    let theme = themes
+        .iter()
+        .find(|theme| theme.name == settings.theme)
+        .unwrap_or(&themes[0])
+        .with_palette(settings.palette);
+
+    let mut tetris = Tetris::with_rules(rules);
+    let mut timestep = FixedTimestep::new(FRAME_RATE);
+    let mut limiter = FrameLimiter::new(Some(FRAME_RATE));
+    let mut previous: Option<Screen> = None;
+    let mut quit = false;
+
+    let raw = RawMode::enter()?;
+    let input = read_input();
+    let mut stdout = io::stdout();
+
+    while tetris.running && !quit {
+        for key in input.try_iter().flat_map(|bytes| keys::parse(&bytes)) {
+            match keys::command(key) {
+                Some(Command::Action(action)) => {
+                    tetris.press(action);
+                    tetris.release(action);
+                }
+                Some(Command::Quit) => quit = true,
+                None => {}
+            }
+        }
+
+        for _ in 0..timestep.tick() {
+            if !tetris.running {
+                break;
+            }
+
+            tetris.update();
+        }
+
+        // There is no sound in the terminal
+        tetris.take_events();
+
+        let screen = draw(&tetris, &theme);
+        write!(stdout, "{}", screen.diff(previous.as_ref())).map_err(|e| e.to_string())?;
+        stdout.flush().map_err(|e| e.to_string())?;
+        previous = Some(screen);
+
+        limiter.wait();
+    }
+
+    drop(raw);
+
+    println!("Game over!\n Your score is: {}", tetris.score);
+
+    // The statistics are only saved for a game that has finished
+    if let Some(path) = stats_path.filter(|_| !quit) {
+        tetris.stats.export(path)?;
+        println!("Statistics saved to {}", path.display());
+    }
+
+    Ok(())
+}
+
+// Draws the board with a border, the next and held pieces, the score and the
+// statistics of the game
+fn draw(tetris: &Tetris, theme: &Theme) -> Screen {
+    let mut screen = Screen::new(SCREEN_WIDTH, SCREEN_HEIGHT);
+
+    for y in 0..BOARD_HEIGHT + 2 {
+        screen.put(0, y, '|', BORDER);
+        screen.put(2 * BOARD_WIDTH + 1, y, '|', BORDER);
+    }
+    for x in 1..2 * BOARD_WIDTH + 1 {
+        screen.put(x, 0, '-', BORDER);
+        screen.put(x, BOARD_HEIGHT + 1, '-', BORDER);
+    }
+
+    for (i, line) in tetris.board.iter().enumerate() {
+        for (j, shape) in line.iter().enumerate() {
+            draw_cell(&mut screen, 1 + 2 * j, 1 + i, *shape, theme);
+        }
+    }
+
+    // The current piece is hidden while it waits for the entry delay
+    if !tetris.is_entering() {
+        let piece = &tetris.current_piece;
+        let x = piece.x_pos as usize;
+        let y = piece.y_pos as usize;
+
+        draw_piece(
+            &mut screen,
+            piece,
+            piece.current_state,
+            |i, j| (1 + 2 * (x + j), 1 + y + i),
+            theme,
+        );
+    }
+
+    screen.text(SIDE_X, 0, "NEXT", TEXT);
+    if let Some(piece) = tetris.next_piece.as_ref() {
+        draw_piece(&mut screen, piece, 0, |i, j| (SIDE_X + 2 * j, 1 + i), theme);
+    }
+
+    screen.text(SIDE_X, HOLD_Y, "HOLD", TEXT);
+    if let Some(piece) = tetris.hold_piece.as_ref() {
+        draw_piece(
+            &mut screen,
+            piece,
+            0,
+            |i, j| (SIDE_X + 2 * j, HOLD_Y + 1 + i),
+            theme,
+        );
+    }
+
+    screen.text(SIDE_X, SCORE_Y, &format!("LINES {}", tetris.score), TEXT);
+    screen.text(
+        SIDE_X,
+        SCORE_Y + 1,
+        &format!("LEVEL {}", tetris.level),
+        TEXT,
+    );
+    if tetris.paused {
+        screen.text(SIDE_X, SCORE_Y + 3, "PAUSED", Color::RGB(255, 255, 255));
+    }
+
+    for (i, line) in tetris.stats.panel_lines().iter().enumerate() {
+        screen.text(STATS_X, i, line, TEXT);
+    }
+
+    screen.text(0, BOARD_HEIGHT + 2, HELP, BORDER);
+
+    screen
+}
+
+// Filled cells are drawn as blocks with the color of their shape, and the empty
+// cells as a dot so the columns can be counted
+fn draw_cell(screen: &mut Screen, x: usize, y: usize, shape: Shapes, theme: &Theme) {
+    match shape {
+        Shapes::ShapeE => {
+            screen.put(x, y, ' ', TEXT);
+            screen.put(x + 1, y, '.', shade(theme.color(shape), -0.3));
+        }
+        _ => {
+            screen.put(x, y, '█', theme.color(shape));
+            screen.put(x + 1, y, '█', theme.color(shape));
+        }
+    }
+}
+
+// Draws the filled cells of a state of a piece. The position of each cell is
+// given by a function of its row and column in the piece
+fn draw_piece(
+    screen: &mut Screen,
+    piece: &TetrisPiece,
+    state: usize,
+    position: impl Fn(usize, usize) -> (usize, usize),
+    theme: &Theme,
+) {
+    for (i, line) in piece.states[state].iter().enumerate() {
+        for (j, shape) in line.iter().enumerate() {
+            if *shape != Shapes::ShapeE {
+                let (x, y) = position(i, j);
+                draw_cell(screen, x, y, *shape, theme);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tetris::puzzle::PuzzlePack;
+
+    #[test]
+    fn test_draw() {
+        let pack = PuzzlePack::parse(
+            "[puzzle]\ngoal = lines 1\npieces = R L T\nboard:\nGGG..GGTTG\nend\n",
+        )
+        .unwrap();
+        let tetris = Tetris::from_puzzle(&pack.puzzles[0], Ruleset::guideline());
+        let theme = Theme::classic();
+        let screen = draw(&tetris, &theme);
+
+        // The garbage row is the last row of the board, and each cell is two
+        // characters wide
+        let bottom = BOARD_HEIGHT;
+        assert_eq!(screen.cell(1, bottom).ch, '█');
+        assert_eq!(screen.cell(1, bottom).color, theme.color(Shapes::ShapeG));
+        assert_eq!(screen.cell(8, bottom).ch, '.');
+        assert_eq!(screen.cell(0, bottom).ch, '|');
+        assert_eq!(screen.cell(SIDE_X, 0).ch, 'N');
+    }
+}
@@ -0,0 +1,122 @@
+use sdl2::pixels::Color;
+
+// A character of the terminal with the color it is written with
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Cell {
+    pub ch: char,
+    pub color: Color,
+}
+
+const BLANK: Cell = Cell {
+    ch: ' ',
+    color: Color::RGB(255, 255, 255),
+};
+
+// The characters of the terminal for one frame. Each frame is drawn into a new
+// screen, and only the cells that are different from the previous frame are
+// written to the terminal
+#[derive(Clone, Debug, PartialEq)]
+pub struct Screen {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Screen {
+    pub fn new(width: usize, height: usize) -> Self {
+        Screen {
+            width,
+            height,
+            cells: vec![BLANK; width * height],
+        }
+    }
+
+    // Positions outside of the screen are ignored
+    pub fn put(&mut self, x: usize, y: usize, ch: char, color: Color) {
+        if x < self.width && y < self.height {
+            self.cells[y * self.width + x] = Cell { ch, color };
+        }
+    }
+
+    pub fn text(&mut self, x: usize, y: usize, text: &str, color: Color) {
+        for (i, ch) in text.chars().enumerate() {
+            self.put(x + i, y, ch, color);
+        }
+    }
+
+    pub fn cell(&self, x: usize, y: usize) -> Cell {
+        self.cells[y * self.width + x]
+    }
+
+    // ANSI escape codes that change the previous screen into this one. The cursor
+    // is only moved when the next changed cell isn't right after the last one,
+    // and the color is only set when it changes. Without a previous screen, or
+    // with one of another size, every cell is written
+    pub fn diff(&self, previous: Option<&Screen>) -> String {
+        let previous = previous
+            .filter(|previous| previous.width == self.width && previous.height == self.height);
+
+        let mut output = String::new();
+        let mut cursor = None;
+        let mut color = None;
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let cell = self.cell(x, y);
+                if previous.map(|previous| previous.cell(x, y)) == Some(cell) {
+                    continue;
+                }
+
+                if cursor != Some((x, y)) {
+                    output.push_str(&format!("\x1b[{};{}H", y + 1, x + 1));
+                }
+
+                if color != Some(cell.color) {
+                    let Color { r, g, b, .. } = cell.color;
+                    output.push_str(&format!("\x1b[38;2;{};{};{}m", r, g, b));
+                    color = Some(cell.color);
+                }
+
+                output.push(cell.ch);
+                cursor = Some((x + 1, y));
+            }
+        }
+
+        if !output.is_empty() {
+            output.push_str("\x1b[0m");
+        }
+
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff() {
+        let red = Color::RGB(255, 0, 0);
+        let mut screen = Screen::new(3, 2);
+        screen.text(0, 0, "ab", red);
+
+        let full = screen.diff(None);
+        assert!(full.starts_with("\x1b[1;1H\x1b[38;2;255;0;0mab\x1b[38;2;255;255;255m \x1b[2;1H"));
+        assert!(full.ends_with("   \x1b[0m"));
+
+        // Only the changed cell is written
+        let mut next = screen.clone();
+        next.put(1, 1, '#', red);
+        assert_eq!(
+            next.diff(Some(&screen)),
+            "\x1b[2;2H\x1b[38;2;255;0;0m#\x1b[0m"
+        );
+        assert_eq!(next.diff(Some(&next)), "");
+
+        // A screen of another size is written again
+        assert_eq!(
+            Screen::new(1, 1).diff(Some(&screen)).matches('H').count(),
+            1
+        );
+    }
+}
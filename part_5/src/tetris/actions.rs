@@ -0,0 +1,78 @@
+// Everything the player can do in the game. The frontends translate their own
+// input (keyboard, gamepad or terminal) into these actions, so the game logic
+// doesn't depend on how the actions are produced
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateCw,
+    RotateCcw,
+    Rotate180,
+    Hold,
+    Pause,
+}
+
+impl Action {
+    pub const ALL: [Action; 9] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::SoftDrop,
+        Action::HardDrop,
+        Action::RotateCw,
+        Action::RotateCcw,
+        Action::Rotate180,
+        Action::Hold,
+        Action::Pause,
+    ];
+
+    // Name used to store the action in the configuration files
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move_left",
+            Action::MoveRight => "move_right",
+            Action::SoftDrop => "soft_drop",
+            Action::HardDrop => "hard_drop",
+            Action::RotateCw => "rotate_cw",
+            Action::RotateCcw => "rotate_ccw",
+            Action::Rotate180 => "rotate_180",
+            Action::Hold => "hold",
+            Action::Pause => "pause",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .find(|action| action.name() == name)
+            .copied()
+    }
+
+    // Only the movements repeat when their input is held down
+    pub fn repeats(&self) -> bool {
+        matches!(
+            self,
+            Action::MoveLeft | Action::MoveRight | Action::SoftDrop
+        )
+    }
+}
+
+// The handling controls how the held movements repeat. All the values are
+// measured in game frames
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Handling {
+    pub das: u32,       // Delayed auto shift: frames before a held move starts repeating
+    pub arr: u32,       // Auto repeat rate: frames between the repeated moves
+    pub soft_drop: u32, // Frames between the moves of a held soft drop
+}
+
+impl Default for Handling {
+    fn default() -> Self {
+        Handling {
+            das: 10,
+            arr: 2,
+            soft_drop: 2,
+        }
+    }
+}
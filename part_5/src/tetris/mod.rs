@@ -1,14 +1,19 @@
 use rand::Rng;
-use std::time::Instant;
-
-use sdl2::event::Event;
-use sdl2::keyboard::Keycode;
+use std::collections::VecDeque;
 
+pub mod actions;
 pub mod pieces;
+pub mod puzzle;
+pub mod rules;
+pub mod stats;
+use actions::{Action, Handling};
 use pieces::CreatePiece;
+use puzzle::{Goal, Outcome, Puzzle};
+use rules::{Ruleset, Speed};
+use stats::Stats;
 
-const BOARD_WIDTH: usize = 10;
-const BOARD_HEIGHT: usize = 16;
+pub const BOARD_WIDTH: usize = 10;
+pub const BOARD_HEIGHT: usize = 16;
 
 #[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
 pub enum Shapes {
@@ -17,22 +22,62 @@ pub enum Shapes {
     ShapeT,
     ShapeL,
     ShapeR,
+    ShapeG, // Represents a garbage cell from a puzzle board
     ShapeE, // Represents empty cell
 }
 
+// Events produced by the game so the frontends can react to them, for example
+// by playing a sound. The events are stored until the frontend takes them
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    Move,
+    Rotate,
+    Hold,
+    Lock,
+    LineClear(u32), // Number of lines cleared at once
+    LevelUp(u32),   // The new level
+    GameOver,
+}
+
+// State of the timers that move and lock the current piece
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Timers {
+    pub gravity: f64,     // Accumulated gravity, a cell is moved when it reaches 1
+    pub lock_timer: u32,  // Frames the piece has been on the stack
+    pub lock_delay: u32,  // Frames before the piece is locked
+    pub entry_timer: u32, // Frames left before the piece appears
+}
+
 pub struct Tetris {
     pub board: Vec<Vec<Shapes>>,
     pub running: bool,
     pub current_piece: pieces::TetrisPiece,
-    pub next_piece: pieces::TetrisPiece,
-    pub score: u32,    // Number of completed lines
-    change_score: u32, // Score threshold to increase speed
-    wait_time: u32,    // Time to wait for the piece to fall
-    prev_time: u128,   // This time is used to keep track of cycles
+    pub next_piece: Option<pieces::TetrisPiece>,
+    pub hold_piece: Option<pieces::TetrisPiece>,
+    pub score: u32,                        // Number of completed lines
+    pub level: u32,                        // Current level, it sets the speed of the game
+    pub rules: Ruleset,                    // Rules used to compute the speed for each level
+    pub goal: Option<Goal>,                // Puzzle goal, if the game is a puzzle
+    pub outcome: Option<Outcome>,          // Result of the puzzle once it has finished
+    pub paused: bool,                      // The game doesn't advance while it is paused
+    pub handling: Handling,                // Repetition of the held movements
+    pub stats: Stats,                      // Statistics of the game played so far
+    gravity: f64,                          // Accumulated gravity for the current piece
+    lock_timer: u32,                       // Frames the current piece has been on the stack
+    entry_timer: u32,                      // Frames left before the current piece appears
+    piece_queue: Option<VecDeque<Shapes>>, // Fixed sequence of pieces. None means random pieces
+    last_rotation: bool,                   // The last successful move was a rotation
+    hold_used: bool,                       // The hold has been used with the current piece
+    held: Vec<(Action, u32)>,              // Held actions and the frames they have been held
+    events: Vec<GameEvent>,                // Events that haven't been taken by the frontend
 }
 
 impl Tetris {
     pub fn new() -> Self {
+        Tetris::with_rules(Ruleset::guideline())
+    }
+
+    pub fn with_rules(rules: Ruleset) -> Self {
         // The board is represented by a vector of vectors
         // Each line in the board will be filled with an
         // enum representing the shape
@@ -50,11 +95,43 @@ impl Tetris {
             board,
             running: true,
             current_piece: Tetris::random_piece(),
-            next_piece: Tetris::random_piece(),
+            next_piece: Some(Tetris::random_piece()),
+            hold_piece: None,
             score: 0,
-            change_score: 20,
-            wait_time: 1000,
-            prev_time: 100,
+            level: rules.start_level,
+            rules,
+            goal: None,
+            outcome: None,
+            paused: false,
+            handling: Handling::default(),
+            stats: Stats::default(),
+            gravity: 0.0,
+            lock_timer: 0,
+            entry_timer: 0,
+            piece_queue: None,
+            last_rotation: false,
+            hold_used: false,
+            held: Vec::new(),
+            events: Vec::new(),
+        }
+    }
+
+    pub fn from_puzzle(puzzle: &Puzzle, rules: Ruleset) -> Self {
+        // A puzzle game starts with the board from the puzzle and takes its
+        // pieces from the puzzle sequence instead of the random generator
+        let mut queue: VecDeque<Shapes> = puzzle.pieces.iter().copied().collect();
+
+        let mut next_from_queue = || queue.pop_front().and_then(pieces::from_shape);
+        let current_piece = next_from_queue().expect("A puzzle needs at least one piece");
+        let next_piece = next_from_queue();
+
+        Tetris {
+            board: puzzle.board.clone(),
+            current_piece,
+            next_piece,
+            goal: Some(puzzle.goal),
+            piece_queue: Some(queue),
+            ..Tetris::with_rules(rules)
         }
     }
 
@@ -72,7 +149,23 @@ impl Tetris {
         }
     }
 
-    fn check_lines(&mut self) {
+    // Shapes still to come, in order, when the pieces are taken from a fixed
+    // sequence. The random pieces can't be known before they are made
+    pub fn upcoming(&self) -> Option<Vec<Shapes>> {
+        self.piece_queue
+            .as_ref()
+            .map(|queue| queue.iter().copied().collect())
+    }
+
+    fn new_piece(&mut self) -> Option<pieces::TetrisPiece> {
+        // The new piece comes from the fixed sequence if there is one
+        match self.piece_queue.as_mut() {
+            Some(queue) => queue.pop_front().and_then(pieces::from_shape),
+            None => Some(Tetris::random_piece()),
+        }
+    }
+
+    fn check_lines(&mut self) -> u32 {
         // Checking if a line has been completed. If it has been completed
         // the line is deleted and a new line is added
         //
@@ -94,6 +187,8 @@ impl Tetris {
             complete_lines.push(i);
         }
 
+        let cleared = complete_lines.len() as u32;
+
         for i in complete_lines {
             // The complete lines are removed based on the index collected before
             self.board.remove(i);
@@ -104,19 +199,32 @@ impl Tetris {
 
             // The game score is stored in the score variable
             self.score += 1;
+        }
 
-            // if the score pases a threadhold then the speed is increased
-            // this is done by reducing the wait time by 10%
-            if self.score % self.change_score == 0 {
-                self.wait_time = self.wait_time * (100 - 10) / 100;
-            }
+        if cleared > 0 {
+            self.events.push(GameEvent::LineClear(cleared));
+        }
+
+        // The level depends on the number of completed lines. A new level
+        // makes the game faster according to the gravity curve of the rules
+        let level = self.rules.level(self.score);
+        if level != self.level {
+            self.level = level;
+            self.events.push(GameEvent::LevelUp(level));
         }
+
+        cleared
     }
 
     fn copy_piece(&mut self) {
         // Copying the current piece to the board and creating
         // a new piece for the board
 
+        // The T-spin has to be checked before the piece becomes part of the board
+        let tspin = self.is_tspin();
+        let shape = self.current_piece.shape();
+        self.events.push(GameEvent::Lock);
+
         let piece = &self.current_piece.states[self.current_piece.current_state];
         for (i, line) in piece.iter().enumerate() {
             for (j, col) in line.iter().enumerate() {
@@ -135,35 +243,138 @@ impl Tetris {
             }
         }
 
-        // moving the next piece to the current piece
-        self.current_piece = self.next_piece.clone();
+        // Checking if a line is complete and if that completes the puzzle
+        let cleared = self.check_lines();
+        self.stats.record_lock(shape, cleared, tspin);
+        self.check_goal(cleared, tspin);
+
+        if !self.running {
+            return;
+        }
+
+        // moving the next piece to the current piece and creating a new next piece.
+        // The new piece waits for the entry delay before it starts falling
+        let next_piece = self.new_piece();
+        let piece = std::mem::replace(&mut self.next_piece, next_piece);
+
+        self.hold_used = false;
+        self.spawn_piece(piece);
+        self.entry_timer = self.speed().entry_delay;
+    }
+
+    fn spawn_piece(&mut self, piece: Option<pieces::TetrisPiece>) {
+        // When a fixed sequence runs out of pieces there is nothing else to play
+        match piece {
+            Some(piece) => self.current_piece = piece,
+            None => {
+                self.end_game(Outcome::Failure);
+                return;
+            }
+        }
+
+        self.last_rotation = false;
+        self.gravity = 0.0;
+        self.lock_timer = 0;
 
         // Checking if the current piece can be drawn in the board
         // If it can not be drawn then it means that there is no space available
         // thus ending the game. To end the game the running variable is changed
         // to false and this will finish the loop
-        match self.chech_new_position(
+        if !self.chech_new_position(
             self.current_piece.x_pos as i32,
             self.current_piece.y_pos as i32,
             self.current_piece.current_state,
         ) {
-            true => {
-                // Creating a new current piece for the board
-                self.next_piece = Tetris::random_piece();
+            self.end_game(Outcome::Failure);
+        }
+    }
+
+    fn end_game(&mut self, outcome: Outcome) {
+        // Only puzzles have an outcome. A normal game just stops running
+        self.running = false;
 
-                // Checking if a line is complete
-                self.check_lines();
+        if outcome == Outcome::Failure {
+            self.events.push(GameEvent::GameOver);
+        }
+
+        if self.goal.is_some() {
+            self.outcome = Some(outcome);
+        }
+    }
+
+    fn is_tspin(&self) -> bool {
+        // A T-spin happens when the T piece is locked right after a rotation and
+        // it is stuck in its place, meaning that it can't move left, right or down.
+        // Since the pieces don't have wall kicks this is the only way to recognize
+        // that a T piece has been spun into a slot
+        let piece = &self.current_piece;
+
+        if !self.last_rotation || piece.shape() != Shapes::ShapeT {
+            return false;
+        }
+
+        let x = piece.x_pos as i32;
+        let y = piece.y_pos as i32;
+        let state = piece.current_state;
+
+        [(x - 1, y), (x + 1, y), (x, y + 1)]
+            .iter()
+            .all(|&(new_x, new_y)| !self.check_position(new_x, new_y, state))
+    }
+
+    fn check_goal(&mut self, cleared: u32, tspin: bool) {
+        // Checking if the goal of the puzzle has been reached after a piece
+        // has been locked and the complete lines have been removed
+        let goal = match self.goal {
+            Some(goal) => goal,
+            None => return,
+        };
+
+        let mut cells = self.board.iter().flatten();
+        let reached = match goal {
+            Goal::ClearLines(lines) => self.score >= lines,
+            Goal::PerfectClear => cleared > 0 && cells.all(|col| *col == Shapes::ShapeE),
+            Goal::TSpinDouble => tspin && cleared == 2,
+            Goal::DigToBottom => !cells.any(|col| *col == Shapes::ShapeG),
+        };
+
+        if reached {
+            self.end_game(Outcome::Success);
+        }
+    }
+
+    fn hold(&mut self) {
+        // The current piece is kept for later and it is replaced by the piece that
+        // was held before, or by the next piece if nothing was held. The hold can
+        // only be used once for each piece. The last piece of a puzzle can't be
+        // held when nothing was held before, because there is no piece to play instead
+        if self.hold_used || (self.hold_piece.is_none() && self.next_piece.is_none()) {
+            return;
+        }
+
+        let held = pieces::from_shape(self.current_piece.shape());
+        let piece = match self.hold_piece.take() {
+            Some(piece) => Some(piece),
+            None => {
+                let next_piece = self.new_piece();
+                std::mem::replace(&mut self.next_piece, next_piece)
             }
-            false => self.running = false,
         };
+
+        self.hold_piece = held;
+        self.hold_used = true;
+        self.stats.holds += 1;
+        self.events.push(GameEvent::Hold);
+        self.spawn_piece(piece);
     }
 
-    fn change_state(&mut self) {
+    fn change_state(&mut self, turns: usize) {
         // To change the state of the current piece one can cycle through
-        // the indices of the available states in the shape type
+        // the indices of the available states in the shape type. The states
+        // are sorted clockwise so each turn moves to the next state
         let current_state = self.current_piece.current_state;
         let number_states = self.current_piece.states.len();
-        let new_state = (current_state + 1) % number_states;
+        let new_state = (current_state + turns) % number_states;
 
         // The new state is checked to see if it stays within the
         // bounds and if there are no other pieces on the board
@@ -172,7 +383,11 @@ impl Tetris {
             self.current_piece.y_pos as i32,
             new_state,
         ) {
-            true => self.current_piece.current_state = new_state,
+            true => {
+                self.current_piece.current_state = new_state;
+                self.last_rotation = true;
+                self.events.push(GameEvent::Rotate);
+            }
             false => self.current_piece.current_state = current_state,
         }
     }
@@ -197,7 +412,11 @@ impl Tetris {
             self.current_piece.y_pos as i32,
             self.current_piece.current_state,
         ) {
-            true => self.current_piece.x_pos += 1,
+            true => {
+                self.current_piece.x_pos += 1;
+                self.last_rotation = false;
+                self.events.push(GameEvent::Move);
+            }
             false => self.current_piece.x_pos += 0,
         }
     }
@@ -211,7 +430,11 @@ impl Tetris {
             self.current_piece.y_pos as i32,
             self.current_piece.current_state,
         ) {
-            true => self.current_piece.x_pos -= 1,
+            true => {
+                self.current_piece.x_pos -= 1;
+                self.last_rotation = false;
+                self.events.push(GameEvent::Move);
+            }
             false => self.current_piece.x_pos += 0,
         }
     }
@@ -227,6 +450,7 @@ impl Tetris {
         ) {
             true => {
                 self.current_piece.y_pos += 1;
+                self.last_rotation = false;
                 Some(true)
             }
             false => {
@@ -237,6 +461,10 @@ impl Tetris {
     }
 
     pub fn chech_new_position(&mut self, new_x: i32, new_y: i32, new_state: usize) -> bool {
+        self.check_position(new_x, new_y, new_state)
+    }
+
+    fn check_position(&self, new_x: i32, new_y: i32, new_state: usize) -> bool {
         // Checks the new probable new position of the current piece
         // It the new state or position is outsize the board or if there
         // is shape that is not empty then the new position es not allowed
@@ -247,6 +475,14 @@ impl Tetris {
             return false;
         }
 
+        self.collisions(new_x, new_y, new_state).is_empty()
+    }
+
+    // Cells of the current piece that would be outside the board or on top of
+    // another shape if the piece was moved to the new position
+    pub fn collisions(&self, new_x: i32, new_y: i32, new_state: usize) -> Vec<(i32, i32)> {
+        let mut cells = Vec::new();
+
         // Getting the new state of the piece
         let piece = &self.current_piece.states[new_state];
 
@@ -263,13 +499,15 @@ impl Tetris {
                 let board_position_x = new_x + j as i32;
                 let board_position_y = new_y + i as i32;
 
-                // If the new position is larger that the board itself then the
+                // If the new position is outside the board itself then the
                 // new position is not valid
-                if board_position_x >= BOARD_WIDTH as i32 {
-                    return false;
-                };
-                if board_position_y >= BOARD_HEIGHT as i32 {
-                    return false;
+                if board_position_x < 0
+                    || board_position_y < 0
+                    || board_position_x >= BOARD_WIDTH as i32
+                    || board_position_y >= BOARD_HEIGHT as i32
+                {
+                    cells.push((board_position_x, board_position_y));
+                    continue;
                 };
 
                 // Compare the piece section with the board section
@@ -277,71 +515,201 @@ impl Tetris {
                 // to move the piece
                 let board_value = &self.board[board_position_y as usize][board_position_x as usize];
                 if *board_value != Shapes::ShapeE {
-                    return false;
+                    cells.push((board_position_x, board_position_y));
                 };
             }
         }
 
-        true
+        cells
     }
 
-    pub fn manage_events(&mut self, event_pump: &mut sdl2::EventPump, timer: &Instant) {
-        // Using the SDL2 event pump all the event comming from
-        // the video contex can be managed. Each event will cause
-        // a change in the board
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::KeyDown {
-                    keycode: Some(Keycode::Escape),
-                    ..
-                }
-                | Event::Quit { .. } => {
-                    self.running = false;
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Up),
-                    ..
-                } => {
-                    self.change_state();
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Right),
-                    ..
-                } => {
-                    self.move_right();
+    // Cells that stop the current piece from moving left, right, down or from
+    // rotating
+    pub fn blocked_cells(&self) -> Vec<(i32, i32)> {
+        let piece = &self.current_piece;
+        let (x, y) = (piece.x_pos as i32, piece.y_pos as i32);
+        let rotated = (piece.current_state + 1) % piece.states.len();
+
+        let mut cells = Vec::new();
+        for &(new_x, new_y, new_state) in [
+            (x - 1, y, piece.current_state),
+            (x + 1, y, piece.current_state),
+            (x, y + 1, piece.current_state),
+            (x, y, rotated),
+        ]
+        .iter()
+        {
+            for cell in self.collisions(new_x, new_y, new_state) {
+                if !cells.contains(&cell) {
+                    cells.push(cell);
                 }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Left),
-                    ..
-                } => {
-                    self.move_left();
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Down),
-                    ..
-                } => {
-                    self.move_down();
-                }
-                Event::KeyDown {
-                    keycode: Some(Keycode::Space),
-                    ..
-                } => {
-                    self.move_to_bottom();
-                }
-                _ => {}
             }
         }
 
-        // moving the piece after some time has passed using the elapsed time
-        // The elapsed time is stored to use it as a reference when a cycle
-        // is completed
-        let elapsed = timer.elapsed().as_millis() % self.wait_time as u128;
+        cells
+    }
+
+    pub fn timers(&self) -> Timers {
+        Timers {
+            gravity: self.gravity,
+            lock_timer: self.lock_timer,
+            lock_delay: self.speed().lock_delay,
+            entry_timer: self.entry_timer,
+        }
+    }
+
+    pub fn speed(&self) -> Speed {
+        self.rules.gravity.speed(self.level)
+    }
+
+    pub fn is_entering(&self) -> bool {
+        // During the entry delay the current piece hasn't appeared on the board
+        self.entry_timer > 0
+    }
+
+    fn can_move_down(&self) -> bool {
+        self.check_position(
+            self.current_piece.x_pos as i32,
+            self.current_piece.y_pos as i32 + 1,
+            self.current_piece.current_state,
+        )
+    }
+
+    pub fn update(&mut self) {
+        // Playing a single frame of the game. While the entry delay is running the
+        // next piece waits to appear. Once it has appeared the gravity pulls it down
+        if self.paused {
+            return;
+        }
+
+        self.stats.frames += 1;
+        self.auto_repeat();
+
+        if self.entry_timer > 0 {
+            self.entry_timer -= 1;
+            return;
+        }
+
+        let speed = self.speed();
+
+        // The gravity is measured in cells per frame. Only full cells move the piece,
+        // and gravities above one cell per frame move the piece several cells at once
+        self.gravity += speed.gravity;
+        while self.gravity >= 1.0 {
+            self.gravity -= 1.0;
+
+            if !self.can_move_down() {
+                self.gravity = 0.0;
+                break;
+            }
+
+            self.current_piece.y_pos += 1;
+            self.last_rotation = false;
+            self.lock_timer = 0;
+        }
+
+        // A piece on top of the stack can still be moved until the lock delay is over
+        if self.can_move_down() {
+            self.lock_timer = 0;
+        } else {
+            self.lock_timer += 1;
+
+            if self.lock_timer >= speed.lock_delay {
+                self.copy_piece();
+            }
+        }
+    }
+
+    pub fn action(&mut self, action: Action) {
+        // Performs a single action. The pause can be toggled at any moment, but the
+        // piece can't be moved while the game is paused or before the piece appears
+        if action == Action::Pause {
+            self.paused = !self.paused;
+            self.held.clear();
+            return;
+        }
+
+        if self.paused || self.is_entering() || !self.running {
+            return;
+        }
+
+        match action {
+            Action::MoveLeft => self.move_left(),
+            Action::MoveRight => self.move_right(),
+            Action::SoftDrop => {
+                self.move_down();
+            }
+            Action::HardDrop => self.move_to_bottom(),
+            Action::RotateCw => self.change_state(1),
+            Action::RotateCcw => self.change_state(3),
+            Action::Rotate180 => self.change_state(2),
+            Action::Hold => self.hold(),
+            Action::Pause => {}
+        }
+    }
+
+    pub fn press(&mut self, action: Action) {
+        // Pressing an action performs it once. The movements keep repeating
+        // while they are held, following the handling of the game
+        if action != Action::Pause && !self.paused {
+            self.stats.key_presses += 1;
+        }
+
+        if action.repeats() {
+            // Only the last horizontal direction that was pressed is repeated
+            let horizontal =
+                |action: &Action| *action == Action::MoveLeft || *action == Action::MoveRight;
+
+            self.held
+                .retain(|(held, _)| *held != action && !(horizontal(held) && horizontal(&action)));
+            self.held.push((action, 0));
+        }
+
+        self.action(action);
+    }
 
-        if elapsed < self.prev_time {
-            self.move_down();
+    pub fn release(&mut self, action: Action) {
+        self.held.retain(|(held, _)| *held != action);
+    }
+
+    fn auto_repeat(&mut self) {
+        // Counting the frames of the held actions. A held movement waits for the
+        // DAS frames and then repeats every ARR frames, while the soft drop repeats
+        // at its own rate from the beginning
+        let handling = self.handling;
+        let mut repeated = Vec::new();
+
+        for (action, frames) in self.held.iter_mut() {
+            *frames += 1;
+
+            let (delay, rate) = match action {
+                Action::SoftDrop => (handling.soft_drop, handling.soft_drop),
+                _ => (handling.das, handling.arr),
+            };
+
+            if *frames >= delay && (*frames - delay) % rate.max(1) == 0 {
+                repeated.push(*action);
+            }
+        }
+
+        for action in repeated {
+            match action {
+                // A repeated soft drop never locks the piece, that is done by the lock delay
+                Action::SoftDrop if !self.is_entering() => {
+                    if self.can_move_down() {
+                        self.current_piece.y_pos += 1;
+                        self.last_rotation = false;
+                        self.lock_timer = 0;
+                    }
+                }
+                Action::SoftDrop => {}
+                _ => self.action(action),
+            }
         }
+    }
 
-        self.prev_time = elapsed;
+    pub fn take_events(&mut self) -> Vec<GameEvent> {
+        std::mem::take(&mut self.events)
     }
 }
 
@@ -361,4 +729,71 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_hold_piece() {
+        // The held piece is swapped with the current piece only once per piece
+        let mut game = Tetris::new();
+        let first = game.current_piece.shape();
+        let next = game.next_piece.as_ref().unwrap().shape();
+
+        game.action(Action::Hold);
+        assert_eq!(game.hold_piece.as_ref().unwrap().shape(), first);
+        assert_eq!(game.current_piece.shape(), next);
+
+        game.action(Action::Hold);
+        assert_eq!(game.current_piece.shape(), next);
+    }
+
+    #[test]
+    fn test_hold_last_piece() {
+        // Holding the only piece of a puzzle does nothing instead of ending it
+        let pack = puzzle::PuzzlePack::parse("[puzzle]\ngoal = lines 1\npieces = T\n").unwrap();
+        let mut game = Tetris::from_puzzle(&pack.puzzles[0], Ruleset::guideline());
+
+        game.action(Action::Hold);
+        assert!(game.running);
+        assert_eq!(game.outcome, None);
+        assert!(game.hold_piece.is_none());
+        assert_eq!(game.current_piece.shape(), Shapes::ShapeT);
+    }
+
+    #[test]
+    fn test_upcoming() {
+        // Only the pieces of a fixed sequence are known before they come
+        assert_eq!(Tetris::new().upcoming(), None);
+
+        let pack =
+            puzzle::PuzzlePack::parse("[puzzle]\ngoal = lines 1\npieces = T L S Z\n").unwrap();
+        let game = Tetris::from_puzzle(&pack.puzzles[0], Ruleset::guideline());
+        assert_eq!(game.upcoming(), Some(vec![Shapes::ShapeS, Shapes::ShapeZ]));
+    }
+
+    #[test]
+    fn test_collisions() {
+        // A piece at the bottom of the board is blocked by the floor
+        let mut game = Tetris::new();
+        game.current_piece = pieces::TetrisT::new();
+        game.current_piece.y_pos = BOARD_HEIGHT as u32 - 2;
+
+        let x = game.current_piece.x_pos as i32;
+        let y = game.current_piece.y_pos as i32;
+        assert!(game.collisions(x, y, 0).is_empty());
+        assert!(!game.collisions(x, y + 1, 0).is_empty());
+
+        let bottom = BOARD_HEIGHT as i32;
+        assert!(game.blocked_cells().iter().all(|&(_, y)| y == bottom));
+    }
+
+    #[test]
+    fn test_20g_gravity() {
+        // With 20G the new piece falls to the top of the stack in a single frame
+        let mut rules = Ruleset::arcade();
+        rules.start_level = 40;
+
+        let mut game = Tetris::with_rules(rules);
+        game.update();
+
+        assert!(!game.can_move_down());
+    }
 }
@@ -11,6 +11,18 @@ pub struct TetrisPiece {
     pub current_state: usize,
 }
 
+impl TetrisPiece {
+    // The shape of the piece is the value of any of its non empty cells
+    pub fn shape(&self) -> Shapes {
+        self.states[0]
+            .iter()
+            .flatten()
+            .copied()
+            .find(|col| *col != ShapeE)
+            .unwrap_or(ShapeE)
+    }
+}
+
 pub trait CreatePiece {
     fn new() -> TetrisPiece;
 }
@@ -143,3 +155,16 @@ impl CreatePiece for TetrisR {
         }
     }
 }
+
+// Creates the piece that corresponds to a shape. This is used when the pieces
+// come from a fixed sequence (like the puzzles) instead of the random generator
+pub fn from_shape(shape: Shapes) -> Option<TetrisPiece> {
+    match shape {
+        ShapeS => Some(TetrisS::new()),
+        ShapeZ => Some(TetrisZ::new()),
+        ShapeT => Some(TetrisT::new()),
+        ShapeL => Some(TetrisL::new()),
+        ShapeR => Some(TetrisR::new()),
+        _ => None,
+    }
+}
@@ -0,0 +1,415 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::tetris::rules::Ruleset;
+use crate::tetris::{Shapes, Tetris, BOARD_HEIGHT, BOARD_WIDTH};
+
+// A puzzle is won when its goal is reached before the piece sequence runs out
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Goal {
+    ClearLines(u32), // Clear at least this number of lines
+    PerfectClear,    // Leave the board completely empty after a line clear
+    TSpinDouble,     // Clear two lines with a T piece that was rotated into place
+    DigToBottom,     // Remove every garbage cell from the starting board
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    Failure,
+}
+
+#[derive(Clone, Debug)]
+pub struct Puzzle {
+    pub name: String,
+    pub board: Vec<Vec<Shapes>>,
+    pub pieces: Vec<Shapes>,
+    pub goal: Goal,
+}
+
+// Puzzles are grouped in packs. Each pack is a single text file that
+// contains all its puzzles in the order they have to be solved
+#[derive(Clone, Debug)]
+pub struct PuzzlePack {
+    pub name: String,
+    pub puzzles: Vec<Puzzle>,
+}
+
+fn shape_from_char(c: char) -> Option<Shapes> {
+    match c {
+        'S' => Some(Shapes::ShapeS),
+        'Z' => Some(Shapes::ShapeZ),
+        'T' => Some(Shapes::ShapeT),
+        'L' => Some(Shapes::ShapeL),
+        'R' => Some(Shapes::ShapeR),
+        'G' => Some(Shapes::ShapeG),
+        '.' => Some(Shapes::ShapeE),
+        _ => None,
+    }
+}
+
+fn parse_goal(value: &str) -> Result<Goal, String> {
+    let words: Vec<&str> = value.split_whitespace().collect();
+
+    match words.as_slice() {
+        ["lines", n] => n
+            .parse()
+            .map(Goal::ClearLines)
+            .map_err(|_| format!("Invalid number of lines: {}", n)),
+        ["perfect", "clear"] => Ok(Goal::PerfectClear),
+        ["tspin", "double"] => Ok(Goal::TSpinDouble),
+        ["dig"] => Ok(Goal::DigToBottom),
+        _ => Err(format!("Unknown goal: {}", value)),
+    }
+}
+
+fn parse_pieces(value: &str) -> Result<Vec<Shapes>, String> {
+    value
+        .split_whitespace()
+        .map(|piece| {
+            let mut chars = piece.chars();
+            match (chars.next().and_then(shape_from_char), chars.next()) {
+                (Some(shape), None) if shape != Shapes::ShapeE && shape != Shapes::ShapeG => {
+                    Ok(shape)
+                }
+                _ => Err(format!("Invalid piece: {}", piece)),
+            }
+        })
+        .collect()
+}
+
+fn parse_board_line(line: &str) -> Result<Vec<Shapes>, String> {
+    if line.chars().count() != BOARD_WIDTH {
+        return Err(format!(
+            "Board line '{}' must have {} cells",
+            line, BOARD_WIDTH
+        ));
+    }
+
+    line.chars()
+        .map(|c| shape_from_char(c).ok_or(format!("Invalid board cell: {}", c)))
+        .collect()
+}
+
+// Values of a puzzle while its lines are being read from the pack file
+struct PuzzleEntry {
+    name: String,
+    lines: Vec<Vec<Shapes>>,
+    pieces: Vec<Shapes>,
+    goal: Option<Goal>,
+}
+
+impl Puzzle {
+    // The board in the file only contains the bottom lines of the puzzle. The
+    // rest of the board is filled with empty lines so it always has the full height
+    fn new(entry: PuzzleEntry) -> Result<Self, String> {
+        let PuzzleEntry {
+            name,
+            lines,
+            pieces,
+            goal,
+        } = entry;
+
+        if lines.len() > BOARD_HEIGHT {
+            return Err(format!("Puzzle '{}' has a board that is too tall", name));
+        }
+
+        if pieces.is_empty() {
+            return Err(format!("Puzzle '{}' has no pieces", name));
+        }
+
+        let goal = goal.ok_or(format!("Puzzle '{}' has no goal", name))?;
+
+        let mut board = vec![vec![Shapes::ShapeE; BOARD_WIDTH]; BOARD_HEIGHT - lines.len()];
+        board.extend(lines);
+
+        Ok(Puzzle {
+            name,
+            board,
+            pieces,
+            goal,
+        })
+    }
+}
+
+impl PuzzlePack {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+        PuzzlePack::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    // A pack file is made of `key = value` lines. The `pack` key names the pack and
+    // every `[puzzle]` header starts a new puzzle with its own name, goal, pieces and
+    // board. The board is written between `board:` and `end`, one row per line.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut name = String::from("Unnamed");
+        let mut puzzles = Vec::new();
+
+        let mut current: Option<PuzzleEntry> = None;
+        let mut reading_board = false;
+
+        for line in text.lines() {
+            let line = line.trim();
+
+            if reading_board {
+                if line == "end" {
+                    reading_board = false;
+                } else if let Some(entry) = current.as_mut() {
+                    entry.lines.push(parse_board_line(line)?);
+                }
+                continue;
+            }
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            if line == "[puzzle]" {
+                if let Some(entry) = current.take() {
+                    puzzles.push(Puzzle::new(entry)?);
+                }
+
+                current = Some(PuzzleEntry {
+                    name: format!("Puzzle {}", puzzles.len() + 1),
+                    lines: Vec::new(),
+                    pieces: Vec::new(),
+                    goal: None,
+                });
+                continue;
+            }
+
+            if line == "board:" {
+                if current.is_none() {
+                    return Err(String::from("Board found outside of a puzzle"));
+                }
+                reading_board = true;
+                continue;
+            }
+
+            let (key, value) = match line.find('=') {
+                Some(index) => (line[..index].trim(), line[index + 1..].trim()),
+                None => return Err(format!("Invalid line: {}", line)),
+            };
+
+            match (key, current.as_mut()) {
+                ("pack", _) => name = value.to_string(),
+                ("name", Some(entry)) => entry.name = value.to_string(),
+                ("pieces", Some(entry)) => entry.pieces = parse_pieces(value)?,
+                ("goal", Some(entry)) => entry.goal = Some(parse_goal(value)?),
+                _ => return Err(format!("Unexpected key: {}", key)),
+            }
+        }
+
+        if reading_board {
+            return Err(String::from("Board is missing its end line"));
+        }
+
+        if let Some(entry) = current.take() {
+            puzzles.push(Puzzle::new(entry)?);
+        }
+
+        if puzzles.is_empty() {
+            return Err(format!("Pack '{}' has no puzzles", name));
+        }
+
+        Ok(PuzzlePack { name, puzzles })
+    }
+}
+
+// Loads a single pack file or all the pack files found in a folder. The packs
+// from a folder are sorted by file name so they are always played in the same order
+pub fn load_packs(path: &Path) -> Result<Vec<PuzzlePack>, String> {
+    if !path.is_dir() {
+        return Ok(vec![PuzzlePack::load(path)?]);
+    }
+
+    let mut files: Vec<PathBuf> = fs::read_dir(path)
+        .map_err(|e| format!("{}: {}", path.display(), e))?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|file| file.extension().is_some_and(|ext| ext == "pack"))
+        .collect();
+    files.sort();
+
+    files.iter().map(|file| PuzzlePack::load(file)).collect()
+}
+
+// The completed puzzles are stored in a local file, one line per puzzle
+// using the pack name and the puzzle name separated by a slash
+pub struct PuzzleProgress {
+    path: PathBuf,
+    completed: HashSet<String>,
+}
+
+impl PuzzleProgress {
+    pub fn load(path: &Path) -> Self {
+        // A missing progress file only means that no puzzle has been solved yet
+        let completed = fs::read_to_string(path)
+            .map(|text| {
+                text.lines()
+                    .map(|line| line.trim().to_string())
+                    .filter(|line| !line.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        PuzzleProgress {
+            path: path.to_path_buf(),
+            completed,
+        }
+    }
+
+    fn key(pack: &PuzzlePack, puzzle: &Puzzle) -> String {
+        format!("{}/{}", pack.name, puzzle.name)
+    }
+
+    pub fn is_completed(&self, pack: &PuzzlePack, puzzle: &Puzzle) -> bool {
+        self.completed.contains(&PuzzleProgress::key(pack, puzzle))
+    }
+
+    pub fn complete(&mut self, pack: &PuzzlePack, puzzle: &Puzzle) -> Result<(), String> {
+        if !self.completed.insert(PuzzleProgress::key(pack, puzzle)) {
+            return Ok(());
+        }
+
+        let mut lines: Vec<&String> = self.completed.iter().collect();
+        lines.sort();
+
+        let text: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+        fs::write(&self.path, text).map_err(|e| e.to_string())
+    }
+}
+
+// The session keeps track of which puzzle is being played. When a puzzle is
+// solved the session moves to the next puzzle in the pack, and then to the
+// next pack once all its puzzles have been played
+pub struct PuzzleSession {
+    pub packs: Vec<PuzzlePack>,
+    pub progress: PuzzleProgress,
+    pack_index: usize,
+    puzzle_index: usize,
+}
+
+impl PuzzleSession {
+    pub fn new(packs: Vec<PuzzlePack>, progress: PuzzleProgress) -> Self {
+        let mut session = PuzzleSession {
+            packs,
+            progress,
+            pack_index: 0,
+            puzzle_index: 0,
+        };
+
+        // Starting from the first puzzle that hasn't been solved. If all of them
+        // have been completed then the session starts again from the beginning
+        let first_pending = session.positions().find(|&(pack, puzzle)| {
+            !session
+                .progress
+                .is_completed(&session.packs[pack], &session.packs[pack].puzzles[puzzle])
+        });
+
+        if let Some((pack, puzzle)) = first_pending {
+            session.pack_index = pack;
+            session.puzzle_index = puzzle;
+        }
+
+        session
+    }
+
+    fn positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.packs
+            .iter()
+            .enumerate()
+            .flat_map(|(i, pack)| (0..pack.puzzles.len()).map(move |j| (i, j)))
+    }
+
+    pub fn current_pack(&self) -> &PuzzlePack {
+        &self.packs[self.pack_index]
+    }
+
+    pub fn current(&self) -> &Puzzle {
+        &self.current_pack().puzzles[self.puzzle_index]
+    }
+
+    pub fn start_game(&self, rules: Ruleset) -> Tetris {
+        Tetris::from_puzzle(self.current(), rules)
+    }
+
+    pub fn complete_current(&mut self) -> Result<(), String> {
+        let pack = &self.packs[self.pack_index];
+        self.progress
+            .complete(pack, &pack.puzzles[self.puzzle_index])
+    }
+
+    // Moves to the next puzzle. Returns false when there are no more puzzles
+    pub fn advance(&mut self) -> bool {
+        if self.puzzle_index + 1 < self.current_pack().puzzles.len() {
+            self.puzzle_index += 1;
+            true
+        } else if self.pack_index + 1 < self.packs.len() {
+            self.pack_index += 1;
+            self.puzzle_index = 0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACK: &str = "
+        pack = Test
+        [puzzle]
+        name = Dig
+        goal = dig
+        pieces = L
+        board:
+        GGGGGG....
+        end
+        [puzzle]
+        name = Lines
+        goal = lines 2
+        pieces = R R S
+        board:
+        GGGGGGGG..
+        GGGGGGGG..
+        end
+    ";
+
+    #[test]
+    fn test_parse_pack() {
+        let pack = PuzzlePack::parse(PACK).unwrap();
+
+        assert_eq!(pack.name, "Test");
+        assert_eq!(pack.puzzles.len(), 2);
+        assert_eq!(pack.puzzles[0].goal, Goal::DigToBottom);
+        assert_eq!(pack.puzzles[1].goal, Goal::ClearLines(2));
+        assert_eq!(pack.puzzles[1].pieces.len(), 3);
+        assert_eq!(pack.puzzles[1].board.len(), BOARD_HEIGHT);
+        assert_eq!(pack.puzzles[1].board[BOARD_HEIGHT - 1][0], Shapes::ShapeG);
+        assert_eq!(pack.puzzles[1].board[BOARD_HEIGHT - 3][0], Shapes::ShapeE);
+    }
+
+    #[test]
+    fn test_invalid_pack() {
+        assert!(PuzzlePack::parse("[puzzle]\ngoal = dig\npieces = X").is_err());
+        assert!(PuzzlePack::parse("[puzzle]\npieces = T\nboard:\nGGG\nend").is_err());
+    }
+
+    #[test]
+    fn test_puzzle_goal() {
+        // Dropping the square piece on the right side of the board completes
+        // the two garbage lines of the second puzzle
+        let pack = PuzzlePack::parse(PACK).unwrap();
+        let mut game = Tetris::from_puzzle(&pack.puzzles[1], Ruleset::guideline());
+
+        game.current_piece.x_pos = 8;
+        game.move_to_bottom();
+
+        assert_eq!(game.score, 2);
+        assert_eq!(game.outcome, Some(Outcome::Success));
+        assert!(!game.running);
+    }
+}
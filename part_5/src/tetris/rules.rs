@@ -0,0 +1,170 @@
+// The game logic runs in frames. All the delays and the gravity are measured
+// in frames so the speed of the game doesn't depend on the rendering speed
+pub const FRAME_RATE: u32 = 60;
+
+// Gravity that moves a piece all the way to the bottom of the board in a single
+// frame. With this gravity the pieces appear directly on top of the stack
+pub const GRAVITY_20G: f64 = 20.0;
+
+// The speed of the game for a level
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Speed {
+    pub gravity: f64,     // Cells per frame that the piece falls
+    pub lock_delay: u32,  // Frames a piece can stay on the stack before it is locked
+    pub entry_delay: u32, // Frames between a piece is locked and the next one appears
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GravityCurve {
+    Guideline, // Modern games: the gravity follows a formula and reaches 20G at level 20
+    Nes,       // Classic console table: one cell per frame is the fastest speed
+    Arcade,    // Master mode: reaches 20G quickly and then shortens the delays
+}
+
+// Frames per cell for each level of the classic console game. From level 29
+// onwards the pieces fall one cell every frame
+const NES_FRAMES_PER_CELL: [u32; 29] = [
+    48, 43, 38, 33, 28, 23, 18, 13, 8, 6, 5, 5, 5, 4, 4, 4, 3, 3, 3, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2,
+];
+
+// Gravity for each arcade level measured in 1/256 of a cell per frame. The last
+// value (5120 / 256 = 20) is 20G and it is used for all the remaining levels
+const ARCADE_GRAVITY: [u32; 16] = [
+    4, 8, 12, 16, 32, 48, 64, 96, 128, 192, 256, 512, 768, 1024, 2560, 5120,
+];
+
+// Entry and lock delays for the arcade levels once the gravity is 20G. Each row
+// holds the first level where the delays are used: (level, entry delay, lock delay)
+const ARCADE_DELAYS: [(u32, u32, u32); 6] = [
+    (0, 25, 30),
+    (15, 16, 30),
+    (18, 12, 26),
+    (21, 10, 22),
+    (24, 6, 18),
+    (27, 4, 15),
+];
+
+impl GravityCurve {
+    pub fn speed(&self, level: u32) -> Speed {
+        match self {
+            GravityCurve::Guideline => {
+                // Seconds per cell = (0.8 - (level - 1) * 0.007) ^ (level - 1), with levels
+                // starting at 1. After level 19 the result is above 20G so it is capped
+                let level = level.clamp(1, 20) as f64 - 1.0;
+                let seconds = (0.8 - level * 0.007).powf(level);
+                let gravity = 1.0 / (seconds * FRAME_RATE as f64);
+
+                Speed {
+                    gravity: gravity.min(GRAVITY_20G),
+                    lock_delay: 30,
+                    entry_delay: 6,
+                }
+            }
+            GravityCurve::Nes => {
+                // The classic game locks the piece when it can't fall any more, which is
+                // the same as waiting one gravity step on top of the stack
+                let frames = NES_FRAMES_PER_CELL
+                    .get(level as usize)
+                    .copied()
+                    .unwrap_or(1);
+
+                Speed {
+                    gravity: 1.0 / frames as f64,
+                    lock_delay: frames,
+                    entry_delay: 10,
+                }
+            }
+            GravityCurve::Arcade => {
+                let index = (level as usize).min(ARCADE_GRAVITY.len() - 1);
+                let (_, entry_delay, lock_delay) = ARCADE_DELAYS
+                    .iter()
+                    .rev()
+                    .find(|(first, _, _)| level >= *first)
+                    .copied()
+                    .unwrap_or(ARCADE_DELAYS[0]);
+
+                Speed {
+                    gravity: ARCADE_GRAVITY[index] as f64 / 256.0,
+                    lock_delay,
+                    entry_delay,
+                }
+            }
+        }
+    }
+}
+
+// The ruleset groups all the values that change how the game is played
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ruleset {
+    pub name: &'static str,
+    pub gravity: GravityCurve,
+    pub start_level: u32,
+    pub lines_per_level: u32,
+}
+
+impl Ruleset {
+    pub fn guideline() -> Self {
+        Ruleset {
+            name: "guideline",
+            gravity: GravityCurve::Guideline,
+            start_level: 1,
+            lines_per_level: 10,
+        }
+    }
+
+    pub fn nes() -> Self {
+        Ruleset {
+            name: "nes",
+            gravity: GravityCurve::Nes,
+            start_level: 0,
+            lines_per_level: 10,
+        }
+    }
+
+    pub fn arcade() -> Self {
+        Ruleset {
+            name: "arcade",
+            gravity: GravityCurve::Arcade,
+            start_level: 0,
+            lines_per_level: 4,
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "guideline" => Some(Ruleset::guideline()),
+            "nes" => Some(Ruleset::nes()),
+            "arcade" => Some(Ruleset::arcade()),
+            _ => None,
+        }
+    }
+
+    pub fn level(&self, lines: u32) -> u32 {
+        self.start_level + lines / self.lines_per_level
+    }
+
+    pub fn speed(&self, lines: u32) -> Speed {
+        self.gravity.speed(self.level(lines))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_curves_reach_top_speed() {
+        // The guideline and arcade curves end in 20G while the classic curve
+        // never goes faster than one cell per frame
+        assert_eq!(GravityCurve::Guideline.speed(1).gravity, 1.0 / 60.0);
+        assert_eq!(GravityCurve::Guideline.speed(25).gravity, GRAVITY_20G);
+        assert_eq!(GravityCurve::Arcade.speed(40).gravity, GRAVITY_20G);
+        assert_eq!(GravityCurve::Nes.speed(40).gravity, 1.0);
+
+        // The arcade delays get shorter once the gravity is 20G
+        let slow = GravityCurve::Arcade.speed(0);
+        let fast = GravityCurve::Arcade.speed(30);
+        assert!(fast.lock_delay < slow.lock_delay);
+        assert!(fast.entry_delay < slow.entry_delay);
+    }
+}
@@ -0,0 +1,215 @@
+use std::fs;
+use std::path::Path;
+
+use crate::tetris::rules::FRAME_RATE;
+use crate::tetris::Shapes;
+
+// Shapes counted by the statistics, in the order they are shown
+const PIECE_SHAPES: [(Shapes, &str); 5] = [
+    (Shapes::ShapeS, "s"),
+    (Shapes::ShapeZ, "z"),
+    (Shapes::ShapeT, "t"),
+    (Shapes::ShapeL, "l"),
+    (Shapes::ShapeR, "r"),
+];
+
+// Statistics collected by the game while it is played. The time is counted in
+// game frames, so the pauses are not part of the time played
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub pieces: [u32; 5], // Locked pieces of each shape, in the order of PIECE_SHAPES
+    pub frames: u64,      // Frames played
+    pub key_presses: u32, // Actions pressed, without the pause
+    pub clears: [u32; 4], // Singles, doubles, triples and tetrises
+    pub tspins: u32,      // T-spins, with or without lines
+    pub combo: u32,       // Pieces in a row that have cleared lines
+    pub max_combo: u32,   // Longest combo of the game
+    pub holds: u32,       // Times the hold was used
+    pub lines: u32,       // Lines cleared
+}
+
+impl Stats {
+    // Counts a piece that has been locked and the lines it has cleared
+    pub fn record_lock(&mut self, shape: Shapes, cleared: u32, tspin: bool) {
+        if let Some(index) = PIECE_SHAPES.iter().position(|(s, _)| *s == shape) {
+            self.pieces[index] += 1;
+        }
+
+        if tspin {
+            self.tspins += 1;
+        }
+
+        if cleared > 0 {
+            self.clears[(cleared.min(4) - 1) as usize] += 1;
+            self.lines += cleared;
+            self.combo += 1;
+            self.max_combo = self.max_combo.max(self.combo);
+        } else {
+            self.combo = 0;
+        }
+    }
+
+    pub fn total_pieces(&self) -> u32 {
+        self.pieces.iter().sum()
+    }
+
+    pub fn seconds(&self) -> f64 {
+        self.frames as f64 / FRAME_RATE as f64
+    }
+
+    pub fn pieces_per_second(&self) -> f64 {
+        match self.frames {
+            0 => 0.0,
+            _ => self.total_pieces() as f64 / self.seconds(),
+        }
+    }
+
+    pub fn presses_per_piece(&self) -> f64 {
+        match self.total_pieces() {
+            0 => 0.0,
+            pieces => self.key_presses as f64 / pieces as f64,
+        }
+    }
+
+    // Time played as minutes and seconds
+    pub fn time(&self) -> String {
+        let seconds = self.seconds() as u64;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+
+    // Short lines for the panel on the side of the board
+    pub fn panel_lines(&self) -> Vec<String> {
+        let pieces: Vec<String> = PIECE_SHAPES
+            .iter()
+            .zip(self.pieces.iter())
+            .map(|((_, name), count)| format!("{}{}", name.to_uppercase(), count))
+            .collect();
+
+        vec![
+            format!("TIME {}", self.time()),
+            format!("PIECES {}", self.total_pieces()),
+            pieces.join(" "),
+            format!("PPS {:.2}", self.pieces_per_second()),
+            format!("KPP {:.2}", self.presses_per_piece()),
+            format!("SINGLE {}", self.clears[0]),
+            format!("DOUBLE {}", self.clears[1]),
+            format!("TRIPLE {}", self.clears[2]),
+            format!("TETRIS {}", self.clears[3]),
+            format!("T-SPIN {}", self.tspins),
+            format!("COMBO {}/{}", self.combo, self.max_combo),
+            format!("HOLD {}", self.holds),
+        ]
+    }
+
+    // Every value of the statistics with a name, as it is written in the files
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = vec![
+            (String::from("time"), format!("{:.2}", self.seconds())),
+            (String::from("lines"), self.lines.to_string()),
+            (String::from("pieces"), self.total_pieces().to_string()),
+        ];
+
+        for ((_, name), count) in PIECE_SHAPES.iter().zip(self.pieces.iter()) {
+            entries.push((format!("pieces_{}", name), count.to_string()));
+        }
+
+        let values = [
+            (
+                "pieces_per_second",
+                format!("{:.3}", self.pieces_per_second()),
+            ),
+            ("key_presses", self.key_presses.to_string()),
+            (
+                "presses_per_piece",
+                format!("{:.3}", self.presses_per_piece()),
+            ),
+            ("singles", self.clears[0].to_string()),
+            ("doubles", self.clears[1].to_string()),
+            ("triples", self.clears[2].to_string()),
+            ("tetrises", self.clears[3].to_string()),
+            ("tspins", self.tspins.to_string()),
+            ("max_combo", self.max_combo.to_string()),
+            ("holds", self.holds.to_string()),
+        ];
+        entries.extend(
+            values
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.clone())),
+        );
+
+        entries
+    }
+
+    // All the values are numbers, so they are written without quotes
+    pub fn to_json(&self) -> String {
+        let fields: Vec<String> = self
+            .entries()
+            .iter()
+            .map(|(name, value)| format!("  \"{}\": {}", name, value))
+            .collect();
+
+        format!("{{\n{}\n}}\n", fields.join(",\n"))
+    }
+
+    pub fn to_csv(&self) -> String {
+        let (names, values): (Vec<String>, Vec<String>) = self.entries().into_iter().unzip();
+        format!("{}\n{}\n", names.join(","), values.join(","))
+    }
+
+    // Writes the statistics to a file. A file with the extension csv is written
+    // as CSV and any other file as JSON
+    pub fn export(&self, path: &Path) -> Result<(), String> {
+        let text = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("csv") => self.to_csv(),
+            _ => self.to_json(),
+        };
+
+        fs::write(path, text).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_lock() {
+        let mut stats = Stats::default();
+        stats.record_lock(Shapes::ShapeT, 2, true);
+        stats.record_lock(Shapes::ShapeL, 1, false);
+        stats.record_lock(Shapes::ShapeS, 0, false);
+        stats.record_lock(Shapes::ShapeT, 3, false);
+
+        assert_eq!(stats.pieces, [1, 0, 2, 1, 0]);
+        assert_eq!(stats.clears, [1, 1, 1, 0]);
+        assert_eq!(stats.lines, 6);
+        assert_eq!(stats.tspins, 1);
+        assert_eq!(stats.combo, 1);
+        assert_eq!(stats.max_combo, 2);
+
+        stats.frames = FRAME_RATE as u64 * 2;
+        stats.key_presses = 10;
+        assert_eq!(stats.pieces_per_second(), 2.0);
+        assert_eq!(stats.presses_per_piece(), 2.5);
+        assert_eq!(stats.time(), "0:02");
+    }
+
+    #[test]
+    fn test_export() {
+        let mut stats = Stats::default();
+        stats.record_lock(Shapes::ShapeZ, 1, false);
+        stats.holds = 3;
+
+        let json = stats.to_json();
+        assert!(json.starts_with("{\n  \"time\": 0.00,\n  \"lines\": 1,"));
+        assert!(json.contains("\"pieces_z\": 1,"));
+        assert!(json.ends_with("\"holds\": 3\n}\n"));
+
+        let csv = stats.to_csv();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("time,lines,pieces,pieces_s,"));
+        assert!(lines[1].starts_with("0.00,1,1,0,1,"));
+        assert!(lines[1].ends_with(",3"));
+    }
+}